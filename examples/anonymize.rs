@@ -0,0 +1,99 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use gst_log_parser::anonymize::{Rule, Sanitizer};
+use gst_log_parser::parse;
+use gst_log_parser::writer::EntryWriter;
+use regex::Regex;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(
+    name = "anonymize",
+    about = "Remove URIs, home paths, host names, addresses and object names from a log so it can be shared"
+)]
+struct Opt {
+    #[structopt(parse(from_os_str), help = "Input log file")]
+    input: PathBuf,
+    #[structopt(
+        short = "o",
+        long,
+        parse(from_os_str),
+        help = "Output file, default to stdout"
+    )]
+    output: Option<PathBuf>,
+    #[structopt(
+        long,
+        number_of_values = 1,
+        help = "Host name to remove, on top of the one logged by GStreamer"
+    )]
+    hostname: Vec<String>,
+    #[structopt(
+        long,
+        number_of_values = 1,
+        help = "Regular expression whose matches should be removed"
+    )]
+    redact: Vec<Regex>,
+    #[structopt(long, help = "Do not replace URIs")]
+    keep_uris: bool,
+    #[structopt(long, help = "Do not replace paths in home directories")]
+    keep_paths: bool,
+    #[structopt(long, help = "Do not replace pointer addresses")]
+    keep_addresses: bool,
+    #[structopt(long, help = "Do not replace object names")]
+    keep_object_names: bool,
+    #[structopt(long, help = "Use colors in the output")]
+    color: bool,
+}
+
+fn main() -> anyhow::Result<()> {
+    let opt = Opt::from_args();
+    let input = File::open(&opt.input)?;
+
+    let mut sanitizer = Sanitizer::new();
+    if !opt.keep_uris {
+        sanitizer = sanitizer.rule(Rule::Uris);
+    }
+    if !opt.keep_paths {
+        sanitizer = sanitizer.rule(Rule::HomePaths);
+    }
+    sanitizer = sanitizer.rule(Rule::Hostnames(opt.hostname));
+    if !opt.keep_addresses {
+        sanitizer = sanitizer.rule(Rule::Addresses);
+    }
+    if !opt.keep_object_names {
+        sanitizer = sanitizer.rule(Rule::ObjectNames);
+    }
+    for regex in opt.redact {
+        sanitizer = sanitizer.rule(Rule::Custom {
+            regex,
+            replacement: "[REDACTED]".to_string(),
+        });
+    }
+
+    // The host name is only logged once GStreamer is initialized so look for it
+    // first, to also replace its earlier mentions.
+    parse(File::open(&opt.input)?).any(|entry| sanitizer.learn_hostname(&entry));
+
+    let output: Box<dyn Write> = match opt.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+    let mut writer = EntryWriter::new(output).color(opt.color);
+
+    for entry in parse(input) {
+        writer.write(&sanitizer.sanitize(entry))?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
@@ -8,23 +8,71 @@
 
 // Generate input logs with: GST_DEBUG="GST_TRACER:7" GST_TRACERS=latency
 
+use anyhow::Context;
+use gst_log_parser::latency::{LatencyReport, Samples, Violation};
 use gst_log_parser::parse;
-use gstreamer::{ClockTime, DebugLevel};
+use gst_log_parser::tracer::{is_tracer_entry, Record};
+use gstreamer::ClockTime;
+use regex::Regex;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::path::PathBuf;
+use std::process;
 use structopt::StructOpt;
 
+fn parse_ms(s: &str) -> anyhow::Result<u64> {
+    match s.parse() {
+        Ok(ms) if ms > 0 => Ok(ms),
+        _ => anyhow::bail!("invalid duration, expected a positive number of ms: {}", s),
+    }
+}
+
 #[derive(StructOpt, Debug, PartialEq, Copy, Clone)]
 #[structopt(name = "command")]
 enum Command {
     #[structopt(
         name = "filter-higher",
-        about = "Check for latency higher than a value"
+        about = "Check for latency higher than a value, exit with 2 on errors"
     )]
     FilterHigher {
         #[structopt(help = "The minimum latency to display, in ms")]
         min: u64,
     },
+    #[structopt(
+        name = "sustained",
+        about = "Check for latency higher than a value for consecutive measurements, exit with 1 if found and 2 on errors"
+    )]
+    Sustained {
+        #[structopt(help = "The latency threshold, in ms")]
+        min: u64,
+        #[structopt(
+            long,
+            default_value = "5",
+            help = "The minimum number of consecutive measurements above the threshold"
+        )]
+        count: usize,
+        #[structopt(
+            long,
+            default_value = "0",
+            help = "The minimum time during which consecutive measurements are above the threshold, in ms"
+        )]
+        duration: u64,
+    },
+    #[structopt(
+        name = "windowed",
+        about = "Check for mean latency higher than a value over time windows, exit with 1 if found and 2 on errors"
+    )]
+    Windowed {
+        #[structopt(help = "The latency threshold, in ms")]
+        min: u64,
+        #[structopt(
+            long,
+            default_value = "1000",
+            parse(try_from_str = parse_ms),
+            help = "The length of the windows, in ms"
+        )]
+        window: u64,
+    },
 }
 
 #[derive(StructOpt, Debug)]
@@ -35,28 +83,128 @@ enum Command {
 struct Opt {
     #[structopt(parse(from_os_str))]
     input: PathBuf,
+    #[structopt(
+        long,
+        help = "Regular expression for the element names or 'src -> sink' paths to check"
+    )]
+    filter: Option<String>,
     #[structopt(subcommand)]
     command: Command,
 }
 
-fn main() -> Result<(), anyhow::Error> {
-    let opt = Opt::from_args();
-    let input = File::open(opt.input)?;
+#[derive(Debug, Copy, Clone)]
+enum Checker {
+    Sustained {
+        min: ClockTime,
+        count: usize,
+        duration: ClockTime,
+    },
+    Windowed {
+        min: ClockTime,
+        window: ClockTime,
+    },
+}
+
+impl Checker {
+    fn violations(&self, samples: &Samples) -> Vec<Violation> {
+        match *self {
+            Checker::Sustained {
+                min,
+                count,
+                duration,
+            } => samples.sustained(min, count, duration),
+            Checker::Windowed { min, window } => samples.windowed(min, window),
+        }
+    }
+}
 
-    let parsed = parse(input)
-        .filter(|entry| entry.category == "GST_TRACER" && entry.level == DebugLevel::Trace);
+// Exit code used when the log cannot be checked, distinct from the one of violations
+const ERROR_EXIT_CODE: i32 = 2;
 
-    for entry in parsed {
-        let s = match entry.message_to_struct() {
-            None => continue,
-            Some(s) => s,
+fn ms(t: ClockTime) -> f64 {
+    t.nseconds() as f64 / 1_000_000.0
+}
+
+// Return the number of violations
+fn check(samples: &BTreeMap<String, Samples>, checker: Checker) -> usize {
+    let mut summary = Vec::new();
+
+    for (name, s) in samples.iter() {
+        let violations = checker.violations(s);
+
+        for v in violations.iter() {
+            println!(
+                "{} from {} to {}: {} measurements, mean {:.3} ms, max {:.3} ms",
+                name,
+                v.start,
+                v.end,
+                v.samples,
+                ms(v.mean),
+                ms(v.max)
+            );
+        }
+
+        if !violations.is_empty() {
+            summary.push((name, violations));
+        }
+    }
+
+    if !summary.is_empty() {
+        println!();
+        println!(
+            "{:<50} {:>10} {:>12} {:>12} {:>10}",
+            "", "violations", "measurements", "duration", "max (ms)"
+        );
+        for (name, violations) in summary.iter() {
+            println!(
+                "{:<50} {:>10} {:>12} {:>12} {:>10.3}",
+                name,
+                violations.len(),
+                violations.iter().map(|v| v.samples).sum::<usize>(),
+                violations
+                    .iter()
+                    .map(|v| v.duration())
+                    .sum::<ClockTime>()
+                    .to_string(),
+                violations.iter().map(|v| ms(v.max)).fold(0.0, f64::max)
+            );
+        }
+    }
+
+    summary.iter().map(|(_, v)| v.len()).sum()
+}
+
+// Return the number of violations found in the log
+fn run(opt: &Opt) -> anyhow::Result<usize> {
+    let input =
+        File::open(&opt.input).with_context(|| format!("cannot open {}", opt.input.display()))?;
+    let filter = opt
+        .filter
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .context("invalid filter")?;
+    let mut report = LatencyReport::new();
+    let mut malformed = 0;
+
+    for entry in parse(input).filter(is_tracer_entry) {
+        let record = match Record::from_entry(&entry) {
+            Ok(Some(record)) => record,
+            Ok(None) => continue,
+            Err(_) => {
+                malformed += 1;
+                continue;
+            }
         };
 
-        if s.name() != "latency" && s.name() != "element-latency" {
+        let (name, latency) = match record.latency() {
+            Some(latency) => latency,
+            None => continue,
+        };
+        if filter.as_ref().is_some_and(|f| !f.is_match(&name)) {
             continue;
         }
 
-        let latency = s.get::<ClockTime>("time").unwrap();
         match opt.command {
             Command::FilterHigher { min } => {
                 let min_time = ClockTime::from_mseconds(min);
@@ -64,8 +212,58 @@ fn main() -> Result<(), anyhow::Error> {
                     println!("{}", entry);
                 }
             }
+            _ => report.add(&record, entry.ts),
         }
     }
 
-    Ok(())
+    if malformed > 0 {
+        eprintln!(
+            "Skipped {} malformed tracer records in {}",
+            malformed,
+            opt.input.display()
+        );
+    }
+
+    let checker = match opt.command {
+        Command::FilterHigher { .. } => return Ok(0),
+        Command::Sustained {
+            min,
+            count,
+            duration,
+        } => Checker::Sustained {
+            min: ClockTime::from_mseconds(min),
+            count,
+            duration: ClockTime::from_mseconds(duration),
+        },
+        Command::Windowed { min, window } => Checker::Windowed {
+            min: ClockTime::from_mseconds(min),
+            window: ClockTime::from_mseconds(window),
+        },
+    };
+
+    if report.is_empty() {
+        anyhow::bail!("no latency measurement to check in {}", opt.input.display());
+    }
+
+    Ok(check(&report.elements, checker) + check(&report.paths, checker))
+}
+
+fn main() {
+    let opt = Opt::from_iter_safe(std::env::args()).unwrap_or_else(|e| {
+        if e.use_stderr() {
+            eprintln!("{}", e.message);
+            process::exit(ERROR_EXIT_CODE);
+        }
+        // --help and --version
+        e.exit()
+    });
+
+    match run(&opt) {
+        Ok(0) => {}
+        Ok(_) => process::exit(1),
+        Err(err) => {
+            eprintln!("Error: {:#}", err);
+            process::exit(ERROR_EXIT_CODE);
+        }
+    }
 }
@@ -8,7 +8,8 @@
 
 use std::fs::File;
 
-use gst_log_parser::parse;
+use gst_log_parser::format::{Format, LineFormat, TemplateFormat};
+use gst_log_parser::{parse_auto_with, parse_with_format};
 use structopt::StructOpt;
 
 #[derive(StructOpt)]
@@ -19,13 +20,35 @@ use structopt::StructOpt;
 struct Opt {
     #[structopt(help = "Input file")]
     input: String,
+    #[structopt(
+        long,
+        help = "Format of the log (native, logcat, journald or syslog), auto-detected by default"
+    )]
+    format: Option<Format>,
+    #[structopt(
+        long,
+        number_of_values = 1,
+        help = "Template of a custom log format, such as \"{ts} {level} {category} {file}:{line}:{function}:<{object}> {message}\""
+    )]
+    template: Vec<TemplateFormat>,
 }
 
 fn main() {
     let opt = Opt::from_args();
     let f = File::open(opt.input).expect("Failed to open log file");
 
-    let parsed = parse(f);
+    let parsed = match opt.format {
+        Some(format) => parse_with_format(f, format.line_format()),
+        None => {
+            let mut formats: Vec<Box<dyn LineFormat>> = Vec::new();
+            for template in opt.template {
+                formats.push(Box::new(template));
+            }
+            formats.extend(Format::all().iter().map(|f| f.line_format()));
+
+            parse_auto_with(f, formats)
+        }
+    };
     for entry in parsed {
         println!("{}", entry);
     }
@@ -9,27 +9,162 @@
 // Generate input logs with: GST_DEBUG="GST_TRACER:7" GST_TRACERS=stats
 
 use gnuplot::*;
-use gst_log_parser::parse;
-use gstreamer::{ClockTime, DebugLevel, Structure};
-use std::collections::HashMap;
-use std::fmt;
+use gst_log_parser::flow::{
+    Flags, IssueKind, Throughput, ThroughputStats, Timestamp, TimestampChecker,
+};
+use gst_log_parser::frames::FrameTracker;
+use gst_log_parser::lateness::Lateness;
+use gst_log_parser::tracer::{is_tracer_entry, BufferRecord, Record, Topology};
+use gst_log_parser::{parse, Entry};
+use gstreamer::ClockTime;
+use std::collections::BTreeMap;
 use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::path::PathBuf;
 use structopt::StructOpt;
 
 use regex::Regex;
 
-#[derive(StructOpt, Debug, PartialEq, Copy, Clone)]
+fn parse_ms(s: &str) -> anyhow::Result<u64> {
+    match s.parse() {
+        Ok(ms) if ms > 0 => Ok(ms),
+        _ => anyhow::bail!("invalid duration, expected a positive number of ms: {}", s),
+    }
+}
+
+#[derive(StructOpt, Debug, PartialEq, Clone)]
+struct CheckOpts {
+    #[structopt(
+        long,
+        default_value = "1",
+        help = "Maximum difference between the timestamp of a buffer and the timestamp + duration of the previous one not reported as a discontinuity, in ms"
+    )]
+    tolerance: u64,
+}
+
+#[derive(StructOpt, Debug, PartialEq, Clone)]
+struct PlotOpts {
+    #[structopt(
+        short = "o",
+        long,
+        parse(from_os_str),
+        help = "Render the plot to this file instead of displaying it (.svg or .png)"
+    )]
+    output: Option<PathBuf>,
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Dump the plotted series to this CSV file"
+    )]
+    csv: Option<PathBuf>,
+    #[structopt(
+        long,
+        default_value = "1280",
+        help = "Width of the rendered plot, in pixels"
+    )]
+    width: u32,
+    #[structopt(
+        long,
+        default_value = "720",
+        help = "Height of the rendered plot, in pixels"
+    )]
+    height: u32,
+}
+
+#[derive(StructOpt, Debug, PartialEq, Clone)]
 #[structopt(name = "command")]
 enum Command {
-    #[structopt(name = "check-decreasing-pts", about = "Check for decreasing PTS")]
-    DecreasingPts,
-    #[structopt(name = "check-decreasing-dts", about = "Check for decreasing DTS")]
-    DecreasingDts,
+    #[structopt(
+        name = "check-decreasing-pts",
+        about = "Check for decreasing, duplicated and discontinuous PTS"
+    )]
+    DecreasingPts {
+        #[structopt(flatten)]
+        check: CheckOpts,
+    },
+    #[structopt(
+        name = "check-decreasing-dts",
+        about = "Check for decreasing, duplicated and discontinuous DTS"
+    )]
+    DecreasingDts {
+        #[structopt(flatten)]
+        check: CheckOpts,
+    },
     #[structopt(name = "plot-pts", about = "Plot PTS")]
-    PlotPts,
+    PlotPts {
+        #[structopt(flatten)]
+        plot: PlotOpts,
+    },
     #[structopt(name = "plot-dts", about = "Plot DTS")]
-    PlotDts,
+    PlotDts {
+        #[structopt(flatten)]
+        plot: PlotOpts,
+    },
+    #[structopt(
+        name = "throughput",
+        about = "Report the framerate, bitrate and jitter of pads over sliding windows"
+    )]
+    Throughput {
+        #[structopt(
+            long,
+            default_value = "1000",
+            parse(try_from_str = parse_ms),
+            help = "Length of the windows, in ms"
+        )]
+        window: u64,
+        #[structopt(
+            long,
+            default_value = "250",
+            parse(try_from_str = parse_ms),
+            help = "Interval between the start of two consecutive windows, in ms"
+        )]
+        step: u64,
+        #[structopt(
+            long,
+            default_value = "90",
+            help = "Report the periods during which pads produce less than this percentage of real time"
+        )]
+        threshold: f64,
+        #[structopt(long, help = "Plot the buffers per second of each pad over time")]
+        plot: bool,
+        #[structopt(flatten)]
+        output: PlotOpts,
+    },
+    #[structopt(
+        name = "flags",
+        about = "Report the DISCONT, GAP, DELTA_UNIT and corrupted buffers and the keyframes of pads"
+    )]
+    Flags {
+        #[structopt(
+            long,
+            help = "List the timestamps of the DISCONT, GAP and corrupted buffers"
+        )]
+        list: bool,
+    },
+    #[structopt(
+        name = "lateness",
+        about = "Report how late buffers reach sinks compared to their running time, requires GST_DEBUG=\"GST_TRACER:7,basesink:5,GST_CLOCK:5,GST_BIN:5\""
+    )]
+    Lateness {
+        #[structopt(
+            long,
+            default_value = "20",
+            help = "Count the buffers later than this, in ms"
+        )]
+        max_lateness: u64,
+        #[structopt(long, help = "Plot the lateness of buffers and QoS events over time")]
+        plot: bool,
+        #[structopt(flatten)]
+        output: PlotOpts,
+    },
+    #[structopt(
+        name = "frames",
+        about = "Follow frames through elements using their PTS: time spent in each element, end-to-end latency and dropped frames"
+    )]
+    Frames {
+        #[structopt(long, help = "Display the path of each frame")]
+        list: bool,
+    },
     #[structopt(name = "gap", about = "Detect GAP in buffers flow")]
     Gap {
         #[structopt(default_value = "500", help = "The minimum gap size to report, in ms")]
@@ -58,162 +193,213 @@ struct Opt {
     command: Command,
 }
 
-#[derive(Debug)]
-struct Element {
-    name: String,
+struct PadFilter {
+    include: Option<Regex>,
+    exclude: Option<Regex>,
 }
 
-impl Element {
-    fn new(name: &str) -> Self {
-        Self {
-            name: name.to_string(),
+impl PadFilter {
+    fn matches(&self, pad_name: &str) -> bool {
+        if let Some(include) = self.include.as_ref() {
+            if !include.is_match(pad_name) {
+                return false;
+            }
         }
+
+        if let Some(exclude) = self.exclude.as_ref() {
+            if exclude.is_match(pad_name) {
+                return false;
+            }
+        }
+
+        true
     }
 }
 
-#[derive(Debug)]
-struct Pad {
-    name: String,
-    last_buffer_ts: Option<ClockTime>,
-    last_buffer_pts: Option<ClockTime>,
-    last_buffer_dts: Option<ClockTime>,
-    element_name: Option<String>,
-    pts: Vec<(ClockTime, ClockTime)>,
-    dts: Vec<(ClockTime, ClockTime)>,
+fn ms(t: ClockTime) -> f64 {
+    t.nseconds() as f64 / 1_000_000.0
 }
 
-impl Pad {
-    fn new(name: &str, element_name: Option<String>) -> Self {
-        Self {
-            name: name.to_string(),
-            last_buffer_ts: None,
-            last_buffer_pts: None,
-            last_buffer_dts: None,
-            element_name,
-            pts: Vec::new(),
-            dts: Vec::new(),
+fn render(fg: &mut Figure, opts: &PlotOpts) -> anyhow::Result<()> {
+    match &opts.output {
+        // Rendering to a file does not need a display and returns once gnuplot is done
+        Some(output) => match output.extension().and_then(|e| e.to_str()) {
+            Some("svg") => fg.save_to_svg(output, opts.width, opts.height)?,
+            Some("png") => fg.save_to_png(output, opts.width, opts.height)?,
+            _ => anyhow::bail!(
+                "Unsupported output format {}, use .svg or .png",
+                output.display()
+            ),
+        },
+        None => {
+            fg.set_post_commands("pause mouse close");
+            fg.show()?;
         }
     }
+
+    Ok(())
 }
 
-impl fmt::Display for Pad {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match &self.element_name {
-            None => write!(f, "{}", self.name),
-            Some(e) => write!(f, "{}:{}", e, self.name),
-        }
-    }
+#[derive(Debug, Default)]
+struct Pad {
+    last_buffer_ts: Option<ClockTime>,
+    pts: Vec<(ClockTime, ClockTime)>,
+    dts: Vec<(ClockTime, ClockTime)>,
 }
 
 #[derive(Debug)]
 struct Flow {
     command: Command,
-    elements: HashMap<u32, Element>,
-    pads: HashMap<u32, Pad>,
+    topology: Topology,
+    pads: BTreeMap<u32, Pad>,
+    checker: Option<TimestampChecker>,
+    throughput: Throughput,
+    flags: Flags,
+    lateness: Lateness,
+    frames: FrameTracker,
 }
 
 impl Flow {
     fn new(command: Command) -> Self {
+        let checker = match &command {
+            Command::DecreasingPts { check } => Some(
+                TimestampChecker::new(Timestamp::Pts)
+                    .tolerance(ClockTime::from_mseconds(check.tolerance)),
+            ),
+            Command::DecreasingDts { check } => Some(
+                TimestampChecker::new(Timestamp::Dts)
+                    .tolerance(ClockTime::from_mseconds(check.tolerance)),
+            ),
+            _ => None,
+        };
+
         Self {
             command,
-            elements: HashMap::new(),
-            pads: HashMap::new(),
+            topology: Topology::new(),
+            pads: BTreeMap::new(),
+            checker,
+            throughput: Throughput::new(),
+            flags: Flags::new(),
+            lateness: Lateness::new(),
+            frames: FrameTracker::new(),
         }
     }
 
-    fn parse(&mut self, s: &Structure) -> anyhow::Result<()> {
-        match s.name().as_str() {
-            "new-element" => {
-                let idx = s.get::<u32>("ix").unwrap();
-                self.elements
-                    .entry(idx)
-                    .or_insert_with(|| Element::new(s.get::<&str>("name").unwrap()));
-            }
-            "new-pad" => {
-                let idx = s.get::<u32>("ix").unwrap();
-                let parent_ix = s.get::<u32>("parent-ix").unwrap();
-                let element_name = self.elements.get(&parent_ix).map(|e| e.name.clone());
-
-                self.pads
-                    .entry(idx)
-                    .or_insert_with(|| Pad::new(s.get::<&str>("name").unwrap(), element_name));
+    fn handle_record(&mut self, record: &Record) {
+        self.topology.add(record);
+
+        if let Some(checker) = self.checker.as_mut() {
+            if let Some(issue) = checker.add(record) {
+                let pad = self.topology.pad_name(issue.pad_ix);
+                let timestamp = checker.timestamp();
+
+                match issue.kind {
+                    IssueKind::Decreasing { previous } => println!(
+                        "{} Decreasing {} {} {} < {}",
+                        issue.ts, timestamp, pad, issue.timestamp, previous
+                    ),
+                    IssueKind::Duplicate => println!(
+                        "{} Duplicated {} {} {}",
+                        issue.ts, timestamp, pad, issue.timestamp
+                    ),
+                    IssueKind::Discontinuity { expected } => println!(
+                        "{} Discontinuous {} {} {} (expected {})",
+                        issue.ts, timestamp, pad, issue.timestamp, expected
+                    ),
+                }
             }
-            "buffer" => self.handle_buffer(s)?,
+        }
+
+        match self.command {
+            Command::Throughput { .. } => self.throughput.add(record),
+            Command::Flags { .. } => self.flags.add(record),
+            Command::Lateness { .. } => self.lateness.add_record(record, &self.topology),
+            Command::Frames { .. } => self.frames.add(record),
             _ => {}
         }
 
-        Ok(())
+        if let Record::Buffer(buffer) = record {
+            self.handle_buffer(buffer);
+        }
     }
 
-    fn handle_buffer(&mut self, s: &Structure) -> anyhow::Result<()> {
-        let pad_ix = s.get::<u32>("pad-ix").unwrap();
-        let pad = self
-            .pads
-            .get_mut(&pad_ix)
-            .ok_or(anyhow::anyhow!("Unknown pad-ix {}", pad_ix))?;
-
-        let element_ix = s.get::<u32>("element-ix").unwrap();
-        let element = self
-            .elements
-            .get(&element_ix)
-            .ok_or(anyhow::anyhow!("Unknown element-ix {}", element_ix))?;
-
-        if pad.element_name.is_none() {
-            pad.element_name = Some(element.name.clone());
+    // Entries logged by other categories than the tracers
+    fn handle_entry(&mut self, entry: &Entry) {
+        if let Command::Lateness { .. } = self.command {
+            self.lateness.add_entry(entry);
         }
+    }
 
-        let ts = ClockTime::from_nseconds(s.get::<u64>("ts").unwrap());
-
-        if s.get::<bool>("have-buffer-pts").unwrap() {
-            let pts = s.get::<ClockTime>("buffer-pts").unwrap();
+    fn handle_buffer(&mut self, buffer: &BufferRecord) {
+        let pad = self.pads.entry(buffer.pad_ix).or_default();
+        let ts = buffer.ts;
 
-            if let Some(last_buffer_pts) = pad.last_buffer_pts {
-                if self.command == Command::DecreasingPts && pts < last_buffer_pts {
-                    println!("Decreasing pts {} {} < {}", pad, pts, last_buffer_pts);
-                }
-            }
+        if let Some(pts) = buffer.pts {
             pad.pts.push((ts, pts));
-            pad.last_buffer_pts = Some(pts);
         }
-
-        if s.get::<bool>("have-buffer-dts").unwrap() {
-            let dts = s.get::<ClockTime>("buffer-dts").unwrap();
-
-            if let Some(last_buffer_dts) = pad.last_buffer_dts {
-                if self.command == Command::DecreasingPts && dts < last_buffer_dts {
-                    println!("Decreasing dts {} {} < {}", pad, dts, last_buffer_dts);
-                }
-            }
+        if let Some(dts) = buffer.dts {
             pad.dts.push((ts, dts));
-            pad.last_buffer_dts = Some(dts);
         }
 
         if let Command::Gap { len } = self.command {
-            if pad.last_buffer_ts.is_some() {
-                let len = ClockTime::from_mseconds(len);
-                if let Some(last_buffer_ts) = pad.last_buffer_ts {
-                    let diff = ts - last_buffer_ts;
-
-                    if diff >= len {
-                        println!(
-                            "gap from {} : {} since previous buffer (received: {} previous: {})",
-                            pad, diff, ts, last_buffer_ts
-                        );
-                    }
+            let len = ClockTime::from_mseconds(len);
+            if let Some(last_buffer_ts) = pad.last_buffer_ts {
+                let diff = ts.saturating_sub(last_buffer_ts);
+
+                if diff >= len {
+                    println!(
+                        "gap from {} : {} since previous buffer (received: {} previous: {})",
+                        self.topology.pad_name(buffer.pad_ix),
+                        diff,
+                        ts,
+                        last_buffer_ts
+                    );
                 }
             }
         }
 
         pad.last_buffer_ts = Some(ts);
+    }
 
-        Ok(())
+    fn print_summary(&self) {
+        let checker = match &self.checker {
+            Some(checker) => checker,
+            None => return,
+        };
+
+        println!();
+        println!(
+            "{:<40} {:>8} {:>10} {:>10} {:>10} {:>10} {:>6}",
+            "pad", "buffers", "decreasing", "duplicated", "discont", "reordered", "resets"
+        );
+        for (ix, pad) in checker.pads() {
+            println!(
+                "{:<40} {:>8} {:>10} {:>10} {:>10} {:>10} {:>6}",
+                self.topology.pad_name(ix),
+                pad.buffers,
+                pad.decreasing,
+                pad.duplicates,
+                pad.discontinuities,
+                pad.reordered,
+                pad.resets
+            );
+        }
     }
 
-    fn plot(&self, include_filter: Option<Regex>, exclude_filter: Option<Regex>) {
-        let title = match self.command {
-            Command::PlotPts => "buffer pts",
-            Command::PlotDts => "buffer dts",
-            _ => return,
+    fn plot(&self, filter: &PadFilter) -> anyhow::Result<()> {
+        let (title, field, opts) = match &self.command {
+            Command::PlotPts { plot } => ("buffer pts", "pts", plot),
+            Command::PlotDts { plot } => ("buffer dts", "dts", plot),
+            _ => return Ok(()),
+        };
+
+        let mut csv = match &opts.csv {
+            Some(path) => {
+                let mut csv = BufWriter::new(File::create(path)?);
+                writeln!(csv, "pad,ts,{}", field)?;
+                Some(csv)
+            }
+            None => None,
         };
 
         let mut fg = Figure::new();
@@ -221,24 +407,15 @@ impl Flow {
             .axes2d()
             .set_title(title, &[])
             .set_x_label("time (ms)", &[])
-            .set_y_label("pts (ms)", &[]);
+            .set_y_label(&format!("{} (ms)", field), &[]);
 
-        for pad in self.pads.values() {
-            let pad_name = format!("{}:{}", pad.name, pad.element_name.as_deref().unwrap_or(""));
-
-            if let Some(include_filter) = include_filter.as_ref() {
-                if !include_filter.is_match(&pad_name) {
-                    continue;
-                }
-            }
-
-            if let Some(exclude_filter) = exclude_filter.as_ref() {
-                if exclude_filter.is_match(&pad_name) {
-                    continue;
-                }
+        for (ix, pad) in self.pads.iter() {
+            let pad_name = self.topology.pad_name(*ix);
+            if !filter.matches(&pad_name) {
+                continue;
             }
 
-            let data = if self.command == Command::PlotPts {
+            let data = if matches!(self.command, Command::PlotPts { .. }) {
                 &pad.pts
             } else {
                 &pad.dts
@@ -248,20 +425,468 @@ impl Flow {
                 continue;
             }
 
-            let caption = format!("{}", pad);
-
             let mut x = Vec::new();
             let mut y = Vec::new();
             for (ts, buffer_ts) in data.iter() {
                 x.push(ts.mseconds());
                 y.push(buffer_ts.mseconds());
+
+                // Dump full precision timestamps, in ns
+                if let Some(csv) = csv.as_mut() {
+                    writeln!(
+                        csv,
+                        "{},{},{}",
+                        pad_name,
+                        ts.nseconds(),
+                        buffer_ts.nseconds()
+                    )?;
+                }
             }
 
-            axes.points(&x, &y, &[Caption(&caption)]);
+            axes.points(&x, &y, &[Caption(&pad_name)]);
+        }
+
+        if let Some(mut csv) = csv {
+            csv.flush()?;
         }
 
-        fg.set_post_commands("pause mouse close");
-        fg.show().unwrap();
+        render(&mut fg, opts)
+    }
+
+    fn report_throughput(&self, filter: &PadFilter) -> anyhow::Result<()> {
+        let (window, step, threshold, plot, opts) = match &self.command {
+            Command::Throughput {
+                window,
+                step,
+                threshold,
+                plot,
+                output,
+            } => (
+                ClockTime::from_mseconds(*window),
+                ClockTime::from_mseconds(*step),
+                *threshold / 100.0,
+                *plot || output.output.is_some(),
+                output,
+            ),
+            _ => return Ok(()),
+        };
+
+        let pads: Vec<(String, Option<ThroughputStats>, Vec<ThroughputStats>)> = self
+            .throughput
+            .pads()
+            .map(|(ix, pad)| (self.topology.pad_name(ix), pad))
+            .filter(|(name, _)| filter.matches(name))
+            .map(|(name, pad)| (name, pad.total(), pad.windows(window, step)))
+            .collect();
+
+        let opt_ms = |t: Option<ClockTime>| t.map(|t| format!("{:.3}", ms(t))).unwrap_or_default();
+        println!(
+            "{:<40} {:>8} {:>10} {:>12} {:>13} {:>13} {:>11} {:>9}",
+            "pad",
+            "buffers",
+            "buffers/s",
+            "kbit/s",
+            "duration (ms)",
+            "interval (ms)",
+            "jitter (ms)",
+            "realtime"
+        );
+        for (name, total, _) in pads.iter() {
+            let total = match total {
+                Some(total) => total,
+                None => continue,
+            };
+            println!(
+                "{:<40} {:>8} {:>10.2} {:>12.1} {:>13} {:>13} {:>11} {:>9}",
+                name,
+                total.buffers,
+                total.buffers_per_sec(),
+                total.bitrate() / 1000.0,
+                opt_ms(total.mean_duration()),
+                opt_ms(total.mean_interval),
+                opt_ms(total.jitter),
+                total
+                    .realtime()
+                    .map(|r| format!("{:.1}%", r * 100.0))
+                    .unwrap_or_default()
+            );
+        }
+
+        // Merge overlapping windows below real time into periods
+        println!();
+        for (name, _, windows) in pads.iter() {
+            let print = |(start, end, min): (ClockTime, ClockTime, f64)| {
+                println!(
+                    "{} below real time from {} to {} (down to {:.1}%)",
+                    name,
+                    start,
+                    end,
+                    min * 100.0
+                )
+            };
+
+            let mut period: Option<(ClockTime, ClockTime, f64)> = None;
+            for w in windows.iter() {
+                match w.realtime().filter(|r| *r < threshold) {
+                    Some(r) => match period.as_mut() {
+                        Some((_, end, min)) if w.start <= *end => {
+                            *end = w.end;
+                            *min = min.min(r);
+                        }
+                        _ => {
+                            if let Some(p) = period.replace((w.start, w.end, r)) {
+                                print(p);
+                            }
+                        }
+                    },
+                    None => {
+                        if let Some(p) = period.take() {
+                            print(p);
+                        }
+                    }
+                }
+            }
+            if let Some(p) = period {
+                print(p);
+            }
+        }
+
+        if let Some(path) = &opts.csv {
+            let mut csv = BufWriter::new(File::create(path)?);
+            writeln!(
+                csv,
+                "pad,start,end,buffers,bytes,buffers_per_sec,bitrate,mean_duration,mean_interval,jitter,realtime"
+            )?;
+            let opt_ns =
+                |t: Option<ClockTime>| t.map(|t| t.nseconds().to_string()).unwrap_or_default();
+            for (name, _, windows) in pads.iter() {
+                for w in windows.iter() {
+                    writeln!(
+                        csv,
+                        "{},{},{},{},{},{},{},{},{},{},{}",
+                        name,
+                        w.start.nseconds(),
+                        w.end.nseconds(),
+                        w.buffers,
+                        w.bytes,
+                        w.buffers_per_sec(),
+                        w.bitrate(),
+                        opt_ns(w.mean_duration()),
+                        opt_ns(w.mean_interval),
+                        opt_ns(w.jitter),
+                        w.realtime().map(|r| r.to_string()).unwrap_or_default()
+                    )?;
+                }
+            }
+            csv.flush()?;
+        }
+
+        if plot {
+            let mut fg = Figure::new();
+            let axes = fg
+                .axes2d()
+                .set_title("throughput", &[])
+                .set_x_label("time (ms)", &[])
+                .set_y_label("buffers/s", &[]);
+            for (name, _, windows) in pads.iter().filter(|(_, _, w)| !w.is_empty()) {
+                let x: Vec<f64> = windows.iter().map(|w| ms(w.start)).collect();
+                let y: Vec<f64> = windows.iter().map(|w| w.buffers_per_sec()).collect();
+                axes.lines(&x, &y, &[Caption(name)]);
+            }
+            render(&mut fg, opts)?;
+        }
+
+        Ok(())
+    }
+
+    fn report_flags(&self, filter: &PadFilter) {
+        let list = match self.command {
+            Command::Flags { list } => list,
+            _ => return,
+        };
+
+        let pads: Vec<_> = self
+            .flags
+            .pads()
+            .map(|(ix, pad)| (self.topology.pad_name(ix), pad))
+            .filter(|(name, _)| filter.matches(name))
+            .collect();
+
+        println!(
+            "{:<40} {:>8} {:>8} {:>8} {:>10} {:>9} {:>9} {:>9} {:>16}",
+            "pad",
+            "buffers",
+            "discont",
+            "gap",
+            "delta-unit",
+            "droppable",
+            "corrupted",
+            "keyframes",
+            "gop min/avg/max"
+        );
+        for (name, pad) in pads.iter() {
+            let gop = if pad.gops.is_empty() {
+                String::new()
+            } else {
+                let sum: u64 = pad.gops.iter().sum();
+                format!(
+                    "{}/{:.1}/{}",
+                    pad.gops.iter().min().unwrap(),
+                    sum as f64 / pad.gops.len() as f64,
+                    pad.gops.iter().max().unwrap()
+                )
+            };
+            println!(
+                "{:<40} {:>8} {:>8} {:>8} {:>10} {:>9} {:>9} {:>9} {:>16}",
+                name,
+                pad.buffers,
+                pad.discont.len(),
+                pad.gap.len(),
+                pad.delta_units,
+                pad.droppable,
+                pad.corrupted.len(),
+                if pad.is_encoded() {
+                    pad.keyframes.len().to_string()
+                } else {
+                    String::new()
+                },
+                gop
+            );
+        }
+
+        // Decoders cannot decode delta units received before the first keyframe following a discontinuity
+        println!();
+        for (name, pad) in pads.iter().filter(|(_, pad)| pad.is_encoded()) {
+            for recovery in pad.recoveries.iter().filter(|r| r.delta_units > 0) {
+                match recovery.keyframe {
+                    Some(keyframe) => println!(
+                        "{} DISCONT at {} followed by {} delta units before keyframe at {}",
+                        name, recovery.discont, recovery.delta_units, keyframe
+                    ),
+                    None => println!(
+                        "{} DISCONT at {} followed by {} delta units and no keyframe",
+                        name, recovery.discont, recovery.delta_units
+                    ),
+                }
+            }
+        }
+
+        if list {
+            let mut events = Vec::new();
+            for (name, pad) in pads.iter() {
+                for (flag, timestamps) in [
+                    ("DISCONT", &pad.discont),
+                    ("GAP", &pad.gap),
+                    ("CORRUPTED", &pad.corrupted),
+                ] {
+                    events.extend(timestamps.iter().map(|ts| (*ts, name, flag)));
+                }
+            }
+            events.sort();
+
+            println!();
+            for (ts, name, flag) in events {
+                println!("{} {} {}", ts, name, flag);
+            }
+        }
+    }
+
+    fn report_lateness(&self, filter: &PadFilter) -> anyhow::Result<()> {
+        let (max_lateness, plot, opts) = match &self.command {
+            Command::Lateness {
+                max_lateness,
+                plot,
+                output,
+            } => (
+                ClockTime::from_mseconds(*max_lateness),
+                *plot || output.output.is_some(),
+                output,
+            ),
+            _ => return Ok(()),
+        };
+
+        let ns_to_ms = |ns: i64| ns as f64 / 1_000_000.0;
+        let sinks: Vec<_> = self
+            .lateness
+            .sinks()
+            .filter(|(name, _)| filter.matches(name))
+            .collect();
+
+        println!("pipeline latency: {}", self.lateness.latency());
+        println!(
+            "{:<30} {:>8} {:>8} {:>10} {:>10} {:>8} {:>5}",
+            "sink", "buffers", "unknown", "mean (ms)", "max (ms)", "late", "qos"
+        );
+        for (name, sink) in sinks.iter() {
+            println!(
+                "{:<30} {:>8} {:>8} {:>10} {:>10} {:>8} {:>5}",
+                name,
+                sink.buffers.len(),
+                sink.unknown,
+                sink.mean()
+                    .map(|m| format!("{:.3}", m / 1_000_000.0))
+                    .unwrap_or_default(),
+                sink.max()
+                    .map(|m| format!("{:.3}", ns_to_ms(m)))
+                    .unwrap_or_default(),
+                sink.late(max_lateness),
+                sink.qos.len()
+            );
+        }
+
+        println!();
+        for (name, sink) in sinks.iter() {
+            for qos in sink.qos.iter() {
+                match qos.lateness {
+                    Some(lateness) => println!(
+                        "{} QoS event from {}, last buffer lateness {:.3} ms",
+                        qos.ts,
+                        name,
+                        ns_to_ms(lateness)
+                    ),
+                    None => println!("{} QoS event from {}", qos.ts, name),
+                }
+            }
+        }
+
+        if let Some(path) = &opts.csv {
+            let mut csv = BufWriter::new(File::create(path)?);
+            writeln!(csv, "sink,ts,pts,running_time,lateness")?;
+            for (name, sink) in sinks.iter() {
+                for b in sink.buffers.iter() {
+                    writeln!(
+                        csv,
+                        "{},{},{},{},{}",
+                        name,
+                        b.ts.nseconds(),
+                        b.pts.nseconds(),
+                        b.running_time.nseconds(),
+                        b.lateness
+                    )?;
+                }
+            }
+            csv.flush()?;
+        }
+
+        if plot {
+            let mut fg = Figure::new();
+            let axes = fg
+                .axes2d()
+                .set_title("lateness", &[])
+                .set_x_label("time (ms)", &[])
+                .set_y_label("lateness (ms)", &[]);
+            for (name, sink) in sinks.iter() {
+                if !sink.buffers.is_empty() {
+                    let x: Vec<f64> = sink.buffers.iter().map(|b| ms(b.ts)).collect();
+                    let y: Vec<f64> = sink.buffers.iter().map(|b| ns_to_ms(b.lateness)).collect();
+                    axes.points(&x, &y, &[Caption(name)]);
+                }
+
+                let qos: Vec<(f64, f64)> = sink
+                    .qos
+                    .iter()
+                    .filter_map(|q| Some((ms(q.ts), ns_to_ms(q.lateness?))))
+                    .collect();
+                if !qos.is_empty() {
+                    let caption = format!("{} QoS", name);
+                    axes.points(
+                        qos.iter().map(|(x, _)| *x),
+                        qos.iter().map(|(_, y)| *y),
+                        &[Caption(&caption), PointSymbol('O'), PointSize(2.0)],
+                    );
+                }
+            }
+            render(&mut fg, opts)?;
+        }
+
+        Ok(())
+    }
+
+    fn report_frames(&self) {
+        let list = match self.command {
+            Command::Frames { list } => list,
+            _ => return,
+        };
+
+        // (count, mean, min, max), in ms
+        let stats = |times: &[ClockTime]| {
+            let sum: ClockTime = times.iter().copied().sum();
+            (
+                times.len(),
+                ms(sum) / times.len() as f64,
+                ms(*times.iter().min().unwrap()),
+                ms(*times.iter().max().unwrap()),
+            )
+        };
+        let tracker = &self.frames;
+
+        println!(
+            "{:<30} {:>8} {:>10} {:>10} {:>10}",
+            "element", "frames", "mean (ms)", "min (ms)", "max (ms)"
+        );
+        for (ix, times) in tracker.residence_times() {
+            let (count, mean, min, max) = stats(&times);
+            println!(
+                "{:<30} {:>8} {:>10.3} {:>10.3} {:>10.3}",
+                self.topology.element_name(ix),
+                count,
+                mean,
+                min,
+                max
+            );
+        }
+
+        let latencies: Vec<ClockTime> = tracker
+            .frames()
+            .iter()
+            .filter_map(|f| tracker.latency(f))
+            .collect();
+        println!();
+        if latencies.is_empty() {
+            println!("No frame reached a sink");
+        } else {
+            let (count, mean, min, max) = stats(&latencies);
+            println!(
+                "{} frames reached a sink, end-to-end latency: mean {:.3} ms, min {:.3} ms, max {:.3} ms",
+                count, mean, min, max
+            );
+        }
+
+        for frame in tracker.frames() {
+            if let Some(element) = tracker.dropped(frame) {
+                println!(
+                    "Frame {} from {} dropped by {}",
+                    frame.pts,
+                    self.topology.element_name(frame.source),
+                    self.topology.element_name(element)
+                );
+            }
+        }
+
+        if list {
+            println!();
+            for frame in tracker.frames() {
+                let mut path = vec![self.topology.element_name(frame.source)];
+                for hop in frame.hops.iter() {
+                    let name = self.topology.element_name(hop.element_ix);
+                    match hop.residence() {
+                        Some(residence) => path.push(format!("{} ({:.3} ms)", name, ms(residence))),
+                        None => path.push(name),
+                    }
+                }
+                let latency = tracker
+                    .latency(frame)
+                    .map(|l| format!(": {:.3} ms", ms(l)))
+                    .unwrap_or_default();
+                println!(
+                    "{} {} {}{}",
+                    frame.start,
+                    frame.pts,
+                    path.join(" -> "),
+                    latency
+                );
+            }
+        }
     }
 }
 
@@ -270,24 +895,29 @@ fn main() -> Result<(), anyhow::Error> {
     let input = File::open(opt.input)?;
     let mut flow = Flow::new(opt.command);
 
-    let parsed = parse(input)
-        .filter(|entry| entry.category == "GST_TRACER" && entry.level == DebugLevel::Trace);
-
-    for entry in parsed {
-        let s = match entry.message_to_struct() {
-            None => continue,
-            Some(s) => s,
-        };
+    for entry in parse(input) {
+        if !is_tracer_entry(&entry) {
+            flow.handle_entry(&entry);
+            continue;
+        }
 
-        if let Err(err) = flow.parse(&s) {
-            eprintln!("failed to handle {}: {}", entry, err);
+        match Record::from_entry(&entry) {
+            Ok(Some(record)) => flow.handle_record(&record),
+            Ok(None) => {}
+            Err(err) => eprintln!("failed to handle {}: {}", entry, err),
         }
     }
 
-    let include_filter = opt.include_filter.map(|f| Regex::new(&f).unwrap());
-    let exclude_filter = opt.exclude_filter.map(|f| Regex::new(&f).unwrap());
+    flow.print_summary();
 
-    flow.plot(include_filter, exclude_filter);
+    let filter = PadFilter {
+        include: opt.include_filter.map(|f| Regex::new(&f).unwrap()),
+        exclude: opt.exclude_filter.map(|f| Regex::new(&f).unwrap()),
+    };
 
-    Ok(())
+    flow.plot(&filter)?;
+    flow.report_flags(&filter);
+    flow.report_lateness(&filter)?;
+    flow.report_frames();
+    flow.report_throughput(&filter)
 }
@@ -0,0 +1,115 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use colored::*;
+use gst_log_parser::frequency::{Frequencies, SortKey};
+use gst_log_parser::parse;
+use gst_log_parser::query::parse_duration;
+use gstreamer::ClockTime;
+use structopt::StructOpt;
+
+fn parse_window(s: &str) -> anyhow::Result<ClockTime> {
+    match parse_duration(s) {
+        Some(window) if window > ClockTime::ZERO => Ok(window),
+        _ => Err(anyhow!("invalid window: {}", s)),
+    }
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(
+    name = "frequency",
+    about = "Display the debug statements producing the most entries"
+)]
+struct Opt {
+    #[structopt(parse(from_os_str), help = "Input log file")]
+    input: PathBuf,
+    #[structopt(
+        long,
+        default_value = "count",
+        possible_values = &["count", "bytes", "first"],
+        help = "Sort by number of entries, size or time of first appearance"
+    )]
+    sort: SortKey,
+    #[structopt(long, default_value = "20", help = "Number of statements to display")]
+    top: usize,
+    #[structopt(
+        long,
+        default_value = "1s",
+        parse(try_from_str = parse_window),
+        help = "Window used to compute the peak rate"
+    )]
+    window: ClockTime,
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Log of a previous run, statements not logged in it are marked as new"
+    )]
+    baseline: Option<PathBuf>,
+}
+
+fn frequencies(path: &Path, window: ClockTime) -> anyhow::Result<Frequencies> {
+    let f = File::open(path)?;
+    let mut freq = Frequencies::new(window);
+    for entry in parse(f) {
+        freq.add(&entry);
+    }
+    Ok(freq)
+}
+
+fn main() -> anyhow::Result<()> {
+    let opt = Opt::from_args();
+    let freq = frequencies(&opt.input, opt.window)?;
+    let baseline = match &opt.baseline {
+        Some(path) => Some(frequencies(path, opt.window)?),
+        None => None,
+    };
+
+    let stats = freq.stats(opt.sort);
+    let total_bytes: u64 = stats.iter().map(|s| s.bytes).sum();
+    println!(
+        "{} entries, {} bytes, {} distinct statements over {}",
+        freq.total(),
+        total_bytes,
+        stats.len(),
+        freq.duration()
+    );
+    println!();
+    println!(
+        "{:>8} {:>6} {:>10} {:>6} {:>9} {:>9} {:>18}  statement",
+        "count", "%", "bytes", "%", "rate/s", "peak/s", "first"
+    );
+
+    for s in stats.iter().take(opt.top) {
+        let new = match &baseline {
+            Some(baseline) if !baseline.contains(&s.site, &s.template) => {
+                format!("{} ", "NEW".red())
+            }
+            _ => String::new(),
+        };
+
+        println!(
+            "{:>8} {:>5.1}% {:>10} {:>5.1}% {:>9.2} {:>9.2} {:>18}  {}{}: {}",
+            s.count,
+            s.count as f64 * 100.0 / freq.total() as f64,
+            s.bytes,
+            s.bytes as f64 * 100.0 / total_bytes as f64,
+            s.rate(freq.duration()),
+            s.peak_rate(freq.window()),
+            s.first.to_string(),
+            new,
+            s.site,
+            s.template
+        );
+    }
+
+    Ok(())
+}
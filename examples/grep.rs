@@ -0,0 +1,51 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs::File;
+use std::path::PathBuf;
+
+use gst_log_parser::parse;
+use gst_log_parser::query::Query;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(
+    name = "grep",
+    about = "Display log entries matching a query, such as 'level<=WARN and object~\"^v4l2\" and ts in 5s..8s'"
+)]
+struct Opt {
+    #[structopt(help = "Query selecting the entries to display")]
+    query: Query,
+    #[structopt(parse(from_os_str), help = "Input log file")]
+    input: PathBuf,
+    #[structopt(short = "v", long, help = "Display entries not matching the query")]
+    invert: bool,
+    #[structopt(
+        short = "c",
+        long,
+        help = "Only display the number of matching entries"
+    )]
+    count: bool,
+}
+
+fn main() -> anyhow::Result<()> {
+    let opt = Opt::from_args();
+    let input = File::open(&opt.input)?;
+
+    let parsed = parse(input).filter(|entry| opt.query.matches(entry) != opt.invert);
+
+    if opt.count {
+        println!("{}", parsed.count());
+    } else {
+        for entry in parsed {
+            println!("{}", entry);
+        }
+    }
+
+    Ok(())
+}
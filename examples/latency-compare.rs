@@ -0,0 +1,210 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Generate input logs with: GST_DEBUG="GST_TRACER:7" GST_TRACERS=latency\(flags="pipeline+element"\)
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::Context;
+use gst_log_parser::latency::{CompareThresholds, LatencyComparison, LatencyReport, Verdict};
+use gst_log_parser::parse;
+use gst_log_parser::tracer::{is_tracer_entry, Record};
+use gstreamer::ClockTime;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(
+    name = "latency-compare",
+    about = "Compare the latencies of two runs, exit with 1 if the candidate regressed and 2 on errors"
+)]
+struct Opt {
+    #[structopt(parse(from_os_str), help = "Log of the reference run")]
+    baseline: PathBuf,
+    #[structopt(parse(from_os_str), help = "Log of the run to check")]
+    candidate: PathBuf,
+    #[structopt(
+        long,
+        help = "Match elements ignoring their instance number (queue0 and queue1 are both 'queue')"
+    )]
+    normalize: bool,
+    #[structopt(
+        long,
+        default_value = "10",
+        help = "Minimum change of the mean latency to report, in percent"
+    )]
+    threshold: f64,
+    #[structopt(
+        long,
+        default_value = "20",
+        help = "Minimum change of the 90th and 99th percentiles to report, in percent"
+    )]
+    percentile_threshold: f64,
+    #[structopt(
+        long,
+        default_value = "1.96",
+        help = "Minimum Welch's t statistic for a change of the mean to be significant"
+    )]
+    t: f64,
+    #[structopt(
+        long,
+        default_value = "30",
+        help = "Minimum number of measurements in each run to compare an element"
+    )]
+    min_samples: usize,
+    #[structopt(long, help = "Also display the elements which did not change")]
+    all: bool,
+}
+
+// Exit code used when the logs cannot be compared, distinct from the one of regressions
+const ERROR_EXIT_CODE: i32 = 2;
+
+fn analyze(path: &Path, normalize: bool) -> anyhow::Result<LatencyReport> {
+    let input = File::open(path).with_context(|| format!("cannot open {}", path.display()))?;
+    let mut report = LatencyReport::new();
+    let mut malformed = 0;
+
+    for entry in parse(input).filter(is_tracer_entry) {
+        match Record::from_entry(&entry) {
+            Ok(Some(record)) => report.add(&record, entry.ts),
+            Ok(None) => {}
+            Err(_) => malformed += 1,
+        }
+    }
+
+    if malformed > 0 {
+        eprintln!(
+            "Skipped {} malformed tracer records in {}",
+            malformed,
+            path.display()
+        );
+    }
+
+    if report.is_empty() {
+        anyhow::bail!("no latency measurement in {}", path.display());
+    }
+
+    if normalize {
+        report = report.normalized();
+    }
+
+    Ok(report)
+}
+
+fn ms(t: ClockTime) -> f64 {
+    t.nseconds() as f64 / 1_000_000.0
+}
+
+// Return the number of regressions, including the elements missing from the candidate
+fn print_comparison(
+    title: &str,
+    comparison: &LatencyComparison,
+    thresholds: &CompareThresholds,
+    all: bool,
+) -> usize {
+    let mut regressions = 0;
+
+    println!("{}:", title);
+    for c in comparison.comparisons.iter() {
+        let verdict = c.verdict(thresholds);
+        let label = match verdict {
+            Verdict::Regression => {
+                regressions += 1;
+                "REGRESSION"
+            }
+            Verdict::Improvement => "improvement",
+            Verdict::NotEnoughSamples => "not enough samples",
+            Verdict::Unchanged if all => "unchanged",
+            Verdict::Unchanged => continue,
+        };
+
+        println!("  {}: {}", c.name, label);
+        for (stat, baseline, candidate, change) in [
+            ("mean", c.baseline.mean, c.candidate.mean, c.mean_change()),
+            ("p90", c.baseline.p90, c.candidate.p90, c.p90_change()),
+            ("p99", c.baseline.p99, c.candidate.p99, c.p99_change()),
+        ] {
+            println!(
+                "    {:<4} {:>10.3} ms -> {:>10.3} ms ({:+.1}%)",
+                stat,
+                ms(baseline),
+                ms(candidate),
+                change
+            );
+        }
+        println!(
+            "    samples {} -> {}, t = {:.2}",
+            c.baseline.count,
+            c.candidate.count,
+            c.t()
+        );
+    }
+
+    for name in comparison.only_baseline.iter() {
+        regressions += 1;
+        println!("  {}: MISSING in candidate", name);
+    }
+    for name in comparison.only_candidate.iter() {
+        println!("  {}: only in candidate", name);
+    }
+    println!();
+
+    regressions
+}
+
+// Return the number of regressions
+fn run(opt: &Opt) -> anyhow::Result<usize> {
+    let baseline = analyze(&opt.baseline, opt.normalize)?;
+    let candidate = analyze(&opt.candidate, opt.normalize)?;
+
+    let thresholds = CompareThresholds {
+        mean: opt.threshold,
+        percentile: opt.percentile_threshold,
+        t: opt.t,
+        min_samples: opt.min_samples,
+    };
+
+    let mut regressions = print_comparison(
+        "Element latency",
+        &LatencyComparison::new(&baseline.elements, &candidate.elements),
+        &thresholds,
+        opt.all,
+    );
+    regressions += print_comparison(
+        "Pipeline latency",
+        &LatencyComparison::new(&baseline.paths, &candidate.paths),
+        &thresholds,
+        opt.all,
+    );
+
+    Ok(regressions)
+}
+
+fn main() {
+    let opt = Opt::from_iter_safe(std::env::args()).unwrap_or_else(|e| {
+        if e.use_stderr() {
+            eprintln!("{}", e.message);
+            process::exit(ERROR_EXIT_CODE);
+        }
+        // --help and --version
+        e.exit()
+    });
+
+    match run(&opt) {
+        Ok(0) => println!("No latency regression"),
+        Ok(regressions) => {
+            println!("{} latency regression(s)", regressions);
+            process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("Error: {:#}", err);
+            process::exit(ERROR_EXIT_CODE);
+        }
+    }
+}
@@ -8,74 +8,128 @@
 
 // Generate input logs with: GST_DEBUG="GST_TRACER:7" GST_TRACERS=latency\(flags="pipeline+element+reported"\)
 
+use gst_log_parser::latency::{LatencyReport, Samples};
 use gst_log_parser::parse;
-use gstreamer::{ClockTime, DebugLevel};
-use itertools::Itertools;
-use std::collections::HashMap;
+use gst_log_parser::tracer::{is_tracer_entry, Record};
+use gstreamer::ClockTime;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::path::PathBuf;
 use structopt::StructOpt;
 
+// Width of the largest bar of histograms
+const HISTOGRAM_WIDTH: usize = 50;
+
 #[derive(StructOpt, Debug)]
 #[structopt(name = "latency")]
 struct Opt {
     #[structopt(parse(from_os_str))]
     input: PathBuf,
+    #[structopt(long, help = "Output the statistics as JSON, times in ns")]
+    json: bool,
+    #[structopt(
+        long,
+        default_value = "5",
+        help = "Number of highest latencies to display for each element and path"
+    )]
+    worst: usize,
+    #[structopt(
+        long,
+        help = "Display an histogram of the latencies with this number of bins"
+    )]
+    histogram: Option<usize>,
 }
 
-#[derive(Debug)]
-struct Count {
-    n: u64,
-    total: ClockTime,
+fn ms(t: ClockTime) -> f64 {
+    t.nseconds() as f64 / 1_000_000.0
 }
 
-impl Count {
-    fn new() -> Self {
-        Self {
-            n: 0,
-            total: ClockTime::from_nseconds(0),
+fn print_section(title: &str, samples: &BTreeMap<String, Samples>, opt: &Opt) {
+    if samples.is_empty() {
+        return;
+    }
+
+    println!("{} (ms):", title);
+    println!(
+        "  {:<40} {:>7} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+        "", "count", "mean", "stddev", "min", "p50", "p90", "p99", "max"
+    );
+    // Sorted by name so we can easily compare results
+    for (name, s) in samples.iter() {
+        let stats = match s.stats() {
+            Some(stats) => stats,
+            None => continue,
+        };
+        println!(
+            "  {:<40} {:>7} {:>9.3} {:>9.3} {:>9.3} {:>9.3} {:>9.3} {:>9.3} {:>9.3}",
+            name,
+            stats.count,
+            ms(stats.mean),
+            ms(stats.stddev),
+            ms(stats.min),
+            ms(stats.p50),
+            ms(stats.p90),
+            ms(stats.p99),
+            ms(stats.max)
+        );
+    }
+
+    if opt.worst > 0 {
+        println!();
+        println!("  Highest latencies:");
+        for (name, s) in samples.iter() {
+            let worst: Vec<String> = s
+                .worst(opt.worst)
+                .iter()
+                .map(|(ts, l)| format!("{:.3} at {}", ms(*l), ts))
+                .collect();
+            println!("  {}: {}", name, worst.join(", "));
         }
     }
 
-    fn mean(&self) -> ClockTime {
-        ClockTime::from_nseconds(self.total.nseconds() / self.n)
+    if let Some(bins) = opt.histogram {
+        for (name, s) in samples.iter() {
+            let histogram = s.histogram(bins);
+            let max = histogram.iter().map(|b| b.count).max().unwrap_or(0).max(1);
+
+            println!();
+            println!("  {}:", name);
+            for bin in histogram {
+                println!(
+                    "  {:>10.3} - {:>10.3} {:<width$} {}",
+                    ms(bin.start),
+                    ms(bin.end),
+                    "#".repeat(bin.count * HISTOGRAM_WIDTH / max),
+                    bin.count,
+                    width = HISTOGRAM_WIDTH
+                );
+            }
+        }
     }
+
+    println!();
 }
 
 fn main() -> Result<(), anyhow::Error> {
     let opt = Opt::from_args();
-    let input = File::open(opt.input)?;
-
-    let mut elt_latency: HashMap<String, Count> = HashMap::new();
-    let parsed = parse(input)
-        .filter(|entry| entry.category == "GST_TRACER" && entry.level == DebugLevel::Trace);
+    let input = File::open(&opt.input)?;
+    let mut report = LatencyReport::new();
 
-    for entry in parsed {
-        let s = match entry.message_to_struct() {
-            None => continue,
-            Some(s) => s,
-        };
-        match s.name().as_str() {
-            "element-latency" => {
-                let count = elt_latency
-                    .entry(s.get::<String>("src").expect("Missing 'src' field"))
-                    .or_insert_with(Count::new);
-
-                count.n += 1;
-                let time: u64 = s.get("time").expect("Missing 'time' field");
-                count.total += ClockTime::from_nseconds(time);
-            }
-            "latency" => { /* TODO */ }
-            "element-reported-latency" => { /* TODO */ }
-            _ => {}
-        };
+    for entry in parse(input).filter(is_tracer_entry) {
+        match Record::from_entry(&entry) {
+            Ok(Some(record)) => report.add(&record, entry.ts),
+            Ok(None) => {}
+            Err(err) => eprintln!("failed to handle {}: {}", entry, err),
+        }
     }
 
-    println!("Mean latency:");
-    // Sort by pad name so we can easily compare results
-    for (pad, count) in elt_latency.iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
-        println!("  {}: {}", pad, count.mean());
+    if opt.json {
+        println!("{}", report.to_json(opt.worst));
+        return Ok(());
     }
 
+    print_section("Element latency", &report.elements, &opt);
+    print_section("Pipeline latency", &report.paths, &opt);
+
     Ok(())
 }
@@ -0,0 +1,145 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs::File;
+use std::path::PathBuf;
+use std::process;
+
+use colored::*;
+use gst_log_parser::diff::{Change, LogDiff};
+use gst_log_parser::normalize::normalize;
+use gst_log_parser::parse;
+use gst_log_parser::writer::format_entry;
+use gst_log_parser::Entry;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(
+    name = "log-diff",
+    about = "Compare the logs of two runs, exit with 1 if they differ"
+)]
+struct Opt {
+    #[structopt(parse(from_os_str), help = "Log of the first run")]
+    a: PathBuf,
+    #[structopt(parse(from_os_str), help = "Log of the second run")]
+    b: PathBuf,
+    #[structopt(
+        long,
+        default_value = "20",
+        help = "Maximum number of entries displayed for each list"
+    )]
+    max: usize,
+    #[structopt(
+        long,
+        default_value = "10",
+        help = "Number of aligned entries with the largest timing drift to display"
+    )]
+    top: usize,
+}
+
+fn read(path: &PathBuf) -> anyhow::Result<Vec<Entry>> {
+    let f = File::open(path)?;
+    Ok(normalize(parse(f)).collect())
+}
+
+fn format_ns(ns: i64) -> String {
+    format!("{:+.3}ms", ns as f64 / 1_000_000.0)
+}
+
+fn print_entries(title: &str, prefix: &str, entries: &[&Entry], max: usize) {
+    println!("{} ({} entries):", title, entries.len());
+    for entry in entries.iter().take(max) {
+        println!("{} {}", prefix, format_entry(entry, false));
+    }
+    if entries.len() > max {
+        println!("  ... {} more", entries.len() - max);
+    }
+    println!();
+}
+
+fn main() -> anyhow::Result<()> {
+    let opt = Opt::from_args();
+    let a = read(&opt.a)?;
+    let b = read(&opt.b)?;
+    let diff = LogDiff::new(&a, &b);
+
+    println!("First divergence per thread:");
+    for thread in diff.threads.iter() {
+        let pos = match thread
+            .changes
+            .iter()
+            .position(|c| !matches!(c, Change::Aligned { .. }))
+        {
+            None => {
+                println!("  {}: {}", thread.thread, "identical".green());
+                continue;
+            }
+            Some(pos) => pos,
+        };
+
+        // Display the entries of both runs at the point where they diverge
+        println!("  {}: {}", thread.thread, "diverging".red());
+        let rest = &thread.changes[pos..];
+        let ia = rest.iter().find_map(|c| match c {
+            Change::OnlyA(a) | Change::Aligned { a, .. } => Some(*a),
+            Change::OnlyB(_) => None,
+        });
+        let ib = rest.iter().find_map(|c| match c {
+            Change::OnlyB(b) | Change::Aligned { b, .. } => Some(*b),
+            Change::OnlyA(_) => None,
+        });
+        match ia {
+            Some(ia) => println!("{}", format!("    - {}", format_entry(&a[ia], false)).red()),
+            None => println!("{}", "    - (end of thread)".red()),
+        }
+        match ib {
+            Some(ib) => println!(
+                "{}",
+                format!("    + {}", format_entry(&b[ib], false)).green()
+            ),
+            None => println!("{}", "    + (end of thread)".green()),
+        }
+    }
+    println!();
+
+    let only_a: Vec<_> = diff.only_a().map(|i| &a[i]).collect();
+    let only_b: Vec<_> = diff.only_b().map(|i| &b[i]).collect();
+    print_entries(
+        &format!("Only in {}", opt.a.display()),
+        &"-".red().to_string(),
+        &only_a,
+        opt.max,
+    );
+    print_entries(
+        &format!("Only in {}", opt.b.display()),
+        &"+".green().to_string(),
+        &only_b,
+        opt.max,
+    );
+
+    let mut deltas = diff.timing_deltas(&a, &b);
+    println!("Timing of aligned entries ({} entries):", deltas.len());
+    if let Some(last) = deltas.iter().max_by_key(|d| b[d.b].ts) {
+        println!("  end of the second run: {}", format_ns(last.delta));
+    }
+    deltas.sort_by_key(|d| std::cmp::Reverse(d.drift.abs()));
+    for delta in deltas.iter().take(opt.top).filter(|d| d.drift != 0) {
+        println!(
+            "  {} (total {}) {}",
+            format_ns(delta.drift),
+            format_ns(delta.delta),
+            format_entry(&b[delta.b], false)
+        );
+    }
+
+    if !diff.is_identical() {
+        process::exit(1);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use gst_log_parser::merge::Merge;
+use gst_log_parser::parse;
+use gst_log_parser::query::parse_duration;
+use gstreamer::{ClockTime, Signed};
+use structopt::StructOpt;
+
+#[derive(Debug)]
+struct Offset {
+    input: usize,
+    offset: Signed<ClockTime>,
+}
+
+impl FromStr for Offset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (input, offset) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("offset should be INPUT=DURATION: {}", s))?;
+        let input = input.parse()?;
+        let invalid = || anyhow!("invalid offset: {}", offset);
+        let offset = match offset.strip_prefix('-') {
+            Some(duration) => Signed::Negative(parse_duration(duration).ok_or_else(invalid)?),
+            None => Signed::Positive(parse_duration(offset).ok_or_else(invalid)?),
+        };
+
+        Ok(Self { input, offset })
+    }
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(
+    name = "merge",
+    about = "Interleave entries from several log files by timestamp"
+)]
+struct Opt {
+    #[structopt(parse(from_os_str), required = true, help = "Input log files")]
+    inputs: Vec<PathBuf>,
+    #[structopt(
+        long = "offset",
+        number_of_values = 1,
+        help = "Shift timestamps of an input, as INPUT=DURATION with INPUT the index of the input file starting from 0 and DURATION prefixed with '-' to shift it earlier"
+    )]
+    offsets: Vec<Offset>,
+}
+
+fn main() -> anyhow::Result<()> {
+    let opt = Opt::from_args();
+
+    let offsets: HashMap<usize, Signed<ClockTime>> =
+        opt.offsets.iter().map(|o| (o.input, o.offset)).collect();
+    if let Some(input) = offsets.keys().find(|input| **input >= opt.inputs.len()) {
+        return Err(anyhow!("no input with index {}", input));
+    }
+
+    let mut merge = Merge::new();
+    let mut names = Vec::new();
+
+    for (i, path) in opt.inputs.iter().enumerate() {
+        let input = File::open(path)?;
+        let offset = offsets
+            .get(&i)
+            .copied()
+            .unwrap_or(Signed::Positive(ClockTime::ZERO));
+
+        merge.add_with_offset(parse(input), offset);
+        names.push(
+            path.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        );
+    }
+
+    for merged in merge {
+        println!("{}: {}", names[merged.source], merged.entry);
+    }
+
+    Ok(())
+}
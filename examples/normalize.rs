@@ -0,0 +1,38 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+
+use gst_log_parser::normalize::normalize;
+use gst_log_parser::parse;
+use gst_log_parser::writer::EntryWriter;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(
+    name = "normalize",
+    about = "Replace threads and addresses by symbolic names so logs from different runs can be diffed"
+)]
+struct Opt {
+    #[structopt(parse(from_os_str), help = "Input log file")]
+    input: PathBuf,
+}
+
+fn main() -> anyhow::Result<()> {
+    let opt = Opt::from_args();
+    let input = File::open(opt.input)?;
+
+    let mut writer = EntryWriter::new(io::stdout().lock());
+    for entry in normalize(parse(input)) {
+        writer.write(&entry)?;
+    }
+
+    Ok(())
+}
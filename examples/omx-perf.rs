@@ -12,6 +12,7 @@ use std::fs::File;
 use std::io::Write;
 use std::process::exit;
 
+use gst_log_parser::omx::{ComponentNaming, OmxCall, OmxParser};
 use gst_log_parser::parse;
 use gstreamer::DebugLevel;
 use structopt::StructOpt;
@@ -26,6 +27,12 @@ struct Opt {
     input: String,
     #[structopt(help = "Output file")]
     output: String,
+    #[structopt(
+        long,
+        default_value = "suffix:4",
+        help = ComponentNaming::HELP
+    )]
+    component: ComponentNaming,
 }
 
 struct Count {
@@ -54,33 +61,28 @@ fn generate() -> Result<bool, std::io::Error> {
     let parsed = parse(input)
         .filter(|entry| entry.category == "OMX_API_TRACE" && entry.level == DebugLevel::Trace);
     let mut counts: HashMap<String, Count> = HashMap::new();
+    let mut parser = OmxParser::new(opt.component);
 
     for entry in parsed {
         println!("{}", entry);
-        let s = entry
-            .message_to_struct()
-            .expect("Failed to parse structure");
-        let object = entry.object.unwrap();
-        // Extract the component name by taking the 4th last chars of the gst object name
-        if let Some((i, _)) = object.char_indices().rev().nth(3) {
-            let comp_name = &object[i..];
-            let event = s.name();
-            let count = counts
-                .entry(comp_name.to_string())
-                .or_insert_with(Count::new);
+        let event = match parser.parse(&entry) {
+            Some(event) if !event.finished => event,
+            _ => continue,
+        };
+        let count = counts
+            .entry(event.component.clone())
+            .or_insert_with(Count::new);
 
-            match event.as_str() {
-                "EmptyThisBuffer" => count.empty_call += 1,
-                "EmptyBufferDone" => count.empty_done += 1,
-                "FillThisBuffer" => count.fill_call += 1,
-                "FillBufferDone" => count.fill_done += 1,
-                _ => continue,
-            }
-
-            let ts = entry.ts.nseconds();
-            writeln!(output, "{}_{} 1 {}", comp_name, event, ts)?;
-            writeln!(output, "{}_{} 0 {}", comp_name, event, ts + 1)?;
+        match event.call {
+            OmxCall::EmptyThisBuffer => count.empty_call += 1,
+            OmxCall::EmptyBufferDone => count.empty_done += 1,
+            OmxCall::FillThisBuffer => count.fill_call += 1,
+            OmxCall::FillBufferDone => count.fill_done += 1,
         }
+
+        let ts = entry.ts.nseconds();
+        writeln!(output, "{}_{} 1 {}", event.component, event.call, ts)?;
+        writeln!(output, "{}_{} 0 {}", event.component, event.call, ts + 1)?;
     }
 
     for (comp, count) in &counts {
@@ -95,6 +97,10 @@ fn generate() -> Result<bool, std::io::Error> {
         );
     }
 
+    if parser.skipped().total() > 0 {
+        eprintln!("Skipped OMX entries: {}", parser.skipped());
+    }
+
     println!("Generated {}", opt.output);
     Ok(true)
 }
@@ -0,0 +1,322 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Pipeline, buffers and latency sections require logs generated with:
+// GST_DEBUG="GST_TRACER:7" GST_TRACERS="stats;latency(flags=pipeline+element+reported)"
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::fs::File;
+use std::path::PathBuf;
+
+use gst_log_parser::latency::LatencyReport;
+use gst_log_parser::parse_auto;
+use gst_log_parser::report::{BarChart, Chart, ChartStyle, Graph, Report, Table};
+use gst_log_parser::summary::Summary;
+use gst_log_parser::tracer::{Record, Topology};
+use gst_log_parser::writer::format_entry;
+use gstreamer::ClockTime;
+use structopt::StructOpt;
+
+// Number of categories displayed in the histogram
+const TOP_CATEGORIES: usize = 20;
+
+#[derive(StructOpt, Debug)]
+#[structopt(
+    name = "report",
+    about = "Generate a self-contained HTML report of a log"
+)]
+struct Opt {
+    #[structopt(parse(from_os_str), help = "Input log file")]
+    input: PathBuf,
+    #[structopt(
+        short = "o",
+        long,
+        parse(from_os_str),
+        default_value = "report.html",
+        help = "Output file"
+    )]
+    output: PathBuf,
+}
+
+fn ms(t: ClockTime) -> f64 {
+    t.nseconds() as f64 / 1_000_000.0
+}
+
+#[derive(Debug, Default)]
+struct PadBuffers {
+    count: u64,
+    bytes: u64,
+    first: Option<ClockTime>,
+    last: Option<ClockTime>,
+    // (ts, pts), in ms
+    pts: Vec<(f64, f64)>,
+}
+
+#[derive(Debug, Default)]
+struct Analysis {
+    summary: Summary,
+    topology: Topology,
+    buffers: BTreeMap<u32, PadBuffers>,
+    latency: LatencyReport,
+    // last latency reported by each element: (live, min, max)
+    reported_latency: BTreeMap<String, (bool, ClockTime, Option<ClockTime>)>,
+    tracer_errors: u64,
+}
+
+impl Analysis {
+    fn handle_record(&mut self, record: Record, entry_ts: ClockTime) {
+        self.topology.add(&record);
+        self.latency.add(&record, entry_ts);
+
+        match record {
+            Record::Buffer(buffer) => {
+                let pad = self.buffers.entry(buffer.pad_ix).or_default();
+                pad.count += 1;
+                pad.bytes += buffer.size as u64;
+                pad.first.get_or_insert(buffer.ts);
+                pad.last = Some(buffer.ts);
+                if let Some(pts) = buffer.pts {
+                    pad.pts.push((ms(buffer.ts), ms(pts)));
+                }
+            }
+            Record::ReportedLatency(latency) => {
+                self.reported_latency
+                    .insert(latency.element, (latency.live, latency.min, latency.max));
+            }
+            _ => {}
+        }
+    }
+
+    fn summary_section(&self, report: &mut Report, opt: &Opt, format: &str) {
+        let summary = &self.summary;
+
+        report.heading("Summary");
+        let mut table = Table::new(&["", ""]);
+        table.row(vec!["file".to_string(), opt.input.display().to_string()]);
+        table.row(vec!["format".to_string(), format.to_string()]);
+        table.row(vec!["entries".to_string(), summary.entries.to_string()]);
+        table.row(vec![
+            "unparsed lines".to_string(),
+            summary.failures().to_string(),
+        ]);
+        if let (Some(first), Some(last)) = (summary.first, summary.last) {
+            table.row(vec!["first entry".to_string(), first.to_string()]);
+            table.row(vec!["last entry".to_string(), last.to_string()]);
+            table.row(vec!["duration".to_string(), summary.duration().to_string()]);
+        }
+        table.row(vec![
+            "PIDs".to_string(),
+            summary
+                .pids
+                .keys()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        ]);
+        table.row(vec![
+            "threads".to_string(),
+            summary.threads.len().to_string(),
+        ]);
+        report.html(&table.to_html());
+
+        let mut levels = BarChart::new("Entries per level");
+        for (level, count) in summary.levels.iter() {
+            levels.bar(&format!("{:?}", level), *count as f64);
+        }
+        report.html(&levels.to_svg());
+
+        let mut categories: Vec<_> = summary.categories.iter().collect();
+        categories.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
+        let mut chart = BarChart::new("Entries per category");
+        for (category, count) in categories.iter().take(TOP_CATEGORIES) {
+            chart.bar(category, **count as f64);
+        }
+        report.html(&chart.to_svg());
+
+        report.heading("Threads");
+        let mut threads: Vec<_> = summary.threads.iter().collect();
+        threads.sort_by_key(|(_, t)| t.first);
+        let mut table = Table::new(&["thread", "entries", "first", "last"]);
+        for (thread, t) in threads {
+            table.row(vec![
+                thread.clone(),
+                t.count.to_string(),
+                t.first.to_string(),
+                t.last.to_string(),
+            ]);
+        }
+        report.html(&table.to_html());
+
+        report.heading("Errors and warnings");
+        if summary.problems.is_empty() {
+            report.paragraph("No error or warning.");
+        } else {
+            let mut table = Table::new(&["count", "last", "entry"]);
+            for problem in summary.problems.iter() {
+                table.row(vec![
+                    problem.count.to_string(),
+                    problem.last.to_string(),
+                    format_entry(&problem.entry, false),
+                ]);
+            }
+            report.html(&table.to_html());
+        }
+
+        if !summary.errors.is_empty() || self.tracer_errors > 0 {
+            report.heading("Parsing failures");
+            let mut table = Table::new(&["error", "count"]);
+            for (kind, count) in summary.errors.iter() {
+                table.row(vec![kind.to_string(), count.to_string()]);
+            }
+            if self.tracer_errors > 0 {
+                table.row(vec![
+                    "invalid tracer record".to_string(),
+                    self.tracer_errors.to_string(),
+                ]);
+            }
+            report.html(&table.to_html());
+        }
+    }
+
+    fn pipeline_section(&self, report: &mut Report) {
+        let links = self.topology.element_links();
+        if links.is_empty() {
+            return;
+        }
+
+        report.heading("Pipeline");
+        let mut graph = Graph::new();
+        let mut nodes = HashMap::new();
+        for (src, sink) in links.iter() {
+            for ix in [src, sink] {
+                if !nodes.contains_key(ix) {
+                    let label = match self.topology.element(*ix) {
+                        Some(e) if !e.type_name.is_empty() => {
+                            format!("{} ({})", e.name, e.type_name)
+                        }
+                        _ => self.topology.element_name(*ix),
+                    };
+                    nodes.insert(*ix, graph.node(&label));
+                }
+            }
+            graph.edge(nodes[src], nodes[sink]);
+        }
+        report.html(&graph.to_svg());
+    }
+
+    fn buffers_section(&self, report: &mut Report) {
+        if self.buffers.is_empty() {
+            return;
+        }
+
+        report.heading("Buffers");
+        let mut table = Table::new(&["pad", "buffers", "bytes", "first", "last"]);
+        let mut chart = Chart::new("Buffer PTS", "time (ms)", "pts (ms)");
+        for (ix, pad) in self.buffers.iter() {
+            let name = self.topology.pad_name(*ix);
+            table.row(vec![
+                name.clone(),
+                pad.count.to_string(),
+                pad.bytes.to_string(),
+                pad.first.map(|t| t.to_string()).unwrap_or_default(),
+                pad.last.map(|t| t.to_string()).unwrap_or_default(),
+            ]);
+            if !pad.pts.is_empty() {
+                chart.series(&name, pad.pts.clone());
+            }
+        }
+        report.html(&table.to_html());
+        if !chart.is_empty() {
+            report.html(&chart.to_svg());
+        }
+    }
+
+    fn latency_section(&self, report: &mut Report) {
+        if self.latency.is_empty() && self.reported_latency.is_empty() {
+            return;
+        }
+
+        report.heading("Latency");
+        for (title, latencies) in [
+            ("Element latency", &self.latency.elements),
+            ("Pipeline latency", &self.latency.paths),
+        ] {
+            if latencies.is_empty() {
+                continue;
+            }
+
+            let mut table = Table::new(&["", "buffers", "mean (ms)", "min (ms)", "max (ms)"]);
+            let mut chart = Chart::new(title, "time (ms)", "latency (ms)").style(ChartStyle::Lines);
+            for (name, samples) in latencies.iter() {
+                if let Some(stats) = samples.stats() {
+                    table.row(vec![
+                        name.clone(),
+                        stats.count.to_string(),
+                        format!("{:.3}", ms(stats.mean)),
+                        format!("{:.3}", ms(stats.min)),
+                        format!("{:.3}", ms(stats.max)),
+                    ]);
+                }
+                chart.series(
+                    name,
+                    samples
+                        .values()
+                        .iter()
+                        .map(|(ts, latency)| (ms(*ts), ms(*latency)))
+                        .collect(),
+                );
+            }
+            report.html(&table.to_html());
+            report.html(&chart.to_svg());
+        }
+
+        if !self.reported_latency.is_empty() {
+            let mut table = Table::new(&["element", "live", "min", "max"]);
+            for (element, (live, min, max)) in self.reported_latency.iter() {
+                table.row(vec![
+                    element.clone(),
+                    live.to_string(),
+                    min.to_string(),
+                    max.map(|m| m.to_string())
+                        .unwrap_or_else(|| "none".to_string()),
+                ]);
+            }
+            report.html(&table.to_html());
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let opt = Opt::from_args();
+    let input = File::open(&opt.input)?;
+    let mut parser = parse_auto(input);
+    let mut analysis = Analysis::default();
+
+    for result in parser.results() {
+        if let Ok(entry) = &result {
+            match Record::from_entry(entry) {
+                Ok(Some(record)) => analysis.handle_record(record, entry.ts),
+                Ok(None) => {}
+                Err(_) => analysis.tracer_errors += 1,
+            }
+        }
+        analysis.summary.add(result);
+    }
+
+    let mut report = Report::new(&format!("GStreamer log report: {}", opt.input.display()));
+    analysis.summary_section(&mut report, &opt, parser.format_name());
+    analysis.pipeline_section(&mut report);
+    analysis.buffers_section(&mut report);
+    analysis.latency_section(&mut report);
+
+    fs::write(&opt.output, report.to_html())?;
+    println!("Report written to {}", opt.output.display());
+
+    Ok(())
+}
@@ -0,0 +1,105 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs::File;
+use std::path::PathBuf;
+
+use colored::*;
+use gst_log_parser::parse_auto;
+use gst_log_parser::summary::summarize;
+use gst_log_parser::writer::format_entry;
+use gstreamer::DebugLevel;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "summary", about = "Give an overview of the content of a log")]
+struct Opt {
+    #[structopt(parse(from_os_str), help = "Input log file")]
+    input: PathBuf,
+    #[structopt(
+        long,
+        default_value = "20",
+        help = "Maximum number of categories displayed"
+    )]
+    categories: usize,
+}
+
+fn main() -> anyhow::Result<()> {
+    let opt = Opt::from_args();
+    let input = File::open(&opt.input)?;
+    let mut parser = parse_auto(input);
+    let summary = summarize(&mut parser);
+
+    println!("Format: {}", parser.format_name());
+    println!(
+        "Entries: {} ({} lines could not be parsed)",
+        summary.entries,
+        summary.failures()
+    );
+    if let (Some(first), Some(last)) = (summary.first, summary.last) {
+        println!("Time span: {} - {} ({})", first, last, summary.duration());
+    }
+    println!();
+
+    println!("PIDs:");
+    for (pid, count) in summary.pids.iter() {
+        println!("  {:>8}: {} entries", pid, count);
+    }
+    println!();
+
+    println!("Threads:");
+    let mut threads: Vec<_> = summary.threads.iter().collect();
+    threads.sort_by_key(|(_, t)| t.first);
+    for (thread, t) in threads {
+        println!(
+            "  {:>16}: {:>8} entries from {} to {}",
+            thread, t.count, t.first, t.last
+        );
+    }
+    println!();
+
+    println!("Levels:");
+    for (level, count) in summary.levels.iter() {
+        println!("  {:>8}: {}", format!("{:?}", level), count);
+    }
+    println!();
+
+    println!("Categories:");
+    let mut categories: Vec<_> = summary.categories.iter().collect();
+    categories.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
+    for (category, count) in categories.iter().take(opt.categories) {
+        println!("  {:>20}: {}", category, count);
+    }
+    if categories.len() > opt.categories {
+        println!("  ... {} more", categories.len() - opt.categories);
+    }
+    println!();
+
+    println!("Errors and warnings:");
+    for problem in summary.problems.iter() {
+        let line = format_entry(&problem.entry, false);
+        let line = match problem.entry.level {
+            DebugLevel::Error => line.red(),
+            _ => line.yellow(),
+        };
+        println!(
+            "  {:>6}x (last at {}) {}",
+            problem.count, problem.last, line
+        );
+    }
+
+    if !summary.errors.is_empty() {
+        println!();
+        println!("Parsing failures:");
+        for (kind, count) in summary.errors.iter() {
+            println!("  {:>20}: {}", kind, count);
+        }
+    }
+
+    Ok(())
+}
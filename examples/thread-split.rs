@@ -1,10 +1,11 @@
 use std::{
     collections::{HashMap, VecDeque},
     fs::File,
-    io::Write,
+    io,
     path::PathBuf,
 };
 
+use gst_log_parser::writer::EntryWriter;
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -43,10 +44,10 @@ fn main() -> anyhow::Result<()> {
                 .or_insert_with_key(move |thd| {
                     let mut path = output_dir.clone();
                     path.push(format!("{thd}.log"));
-                    File::create(path).unwrap()
+                    EntryWriter::new(File::create(path).unwrap())
                 });
 
-            writeln!(output, "{entry}")?;
+            output.write(&entry)?;
         }
 
         if opt.tail > 0 {
@@ -61,10 +62,11 @@ fn main() -> anyhow::Result<()> {
         }
     }
 
+    let mut stdout = EntryWriter::new(io::stdout());
     for (thread, entries) in tails.into_iter() {
         println!("{thread}");
         for entry in entries {
-            println!("{entry}");
+            stdout.write(&entry)?;
         }
         println!();
     }
@@ -0,0 +1,91 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Generate input logs with:
+//   omx: GST_DEBUG="OMX_API_TRACE:7"
+//   v4l2: GST_DEBUG="v4l2bufferpool:7"
+//   vaapi: GST_DEBUG="vaapi:5"
+
+use std::fs::File;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use gst_log_parser::hwtrace::{self, HwTracker, OmxProfile, PatternProfile, Profile, PROFILES};
+use gst_log_parser::omx::ComponentNaming;
+use gst_log_parser::parse;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+#[structopt(
+    name = "track-frames-hw",
+    about = "Track progress of frames across hardware components"
+)]
+struct Opt {
+    #[structopt(parse(from_os_str))]
+    input: PathBuf,
+    #[structopt(
+        long,
+        default_value = "omx",
+        possible_values = PROFILES,
+        help = "How hardware components log their buffers"
+    )]
+    profile: String,
+    #[structopt(
+        long,
+        requires = "output-pattern",
+        help = "Regular expression matching messages of frames given to components, capturing their id in a 'frame' group. Overrides --profile"
+    )]
+    input_pattern: Option<String>,
+    #[structopt(
+        long,
+        requires = "input-pattern",
+        help = "Regular expression matching messages of frames produced by components, capturing their id in a 'frame' group"
+    )]
+    output_pattern: Option<String>,
+    #[structopt(
+        long,
+        default_value = ".*",
+        help = "Regular expression for the categories of the messages matched by the patterns"
+    )]
+    category: String,
+    #[structopt(
+        long,
+        default_value = "suffix:4",
+        help = ComponentNaming::HELP
+    )]
+    component: ComponentNaming,
+}
+
+fn main() -> Result<()> {
+    let opt = Opt::from_args();
+    let input = File::open(&opt.input)?;
+
+    let mut profile: Box<dyn Profile> = match (&opt.input_pattern, &opt.output_pattern) {
+        (Some(input), Some(output)) => {
+            Box::new(PatternProfile::new("custom", &opt.category, input, output)?)
+        }
+        _ if opt.profile == "omx" => Box::new(OmxProfile::new(opt.component.clone())),
+        _ => hwtrace::profile(&opt.profile)
+            .ok_or_else(|| anyhow!("unknown profile {}", opt.profile))?,
+    };
+
+    let mut tracker = HwTracker::new();
+    for entry in parse(input) {
+        if let Some(event) = profile.event(&entry) {
+            tracker.add(event);
+        }
+    }
+
+    print!("{}", tracker.report(|frame| profile.frame_name(frame)));
+
+    if let Some(skipped) = profile.skipped().filter(|s| s.total() > 0) {
+        eprintln!("Skipped {} entries: {}", profile.name(), skipped);
+    }
+
+    Ok(())
+}
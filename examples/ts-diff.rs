@@ -116,7 +116,11 @@ fn generate() -> Result<bool, std::io::Error> {
             e.entry.file,
             e.entry.line,
             e.entry.function,
-            e.entry.object.clone().unwrap_or_default(),
+            e.entry
+                .object
+                .as_ref()
+                .map(|o| o.to_string())
+                .unwrap_or_default(),
             e.entry.message
         );
     }
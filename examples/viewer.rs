@@ -0,0 +1,618 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use gst::prelude::*;
+use gst::{ClockTime, DebugLevel};
+use gst_log_parser::index::LogIndex;
+use gst_log_parser::query::parse_duration;
+use gst_log_parser::threshold::Thresholds;
+use gst_log_parser::Entry;
+use gstreamer as gst;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use structopt::StructOpt;
+
+// Number of lines parsed at once when looking for entries matching the filters
+const SCAN_CHUNK: usize = 10_000;
+// Maximum time spent scanning the log between two refreshes of the screen
+const SCAN_BUDGET: Duration = Duration::from_millis(50);
+// Maximum number of parsed entries kept in memory
+const CACHE_SIZE: usize = 4096;
+const DETAILS_HEIGHT: u16 = 12;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "viewer", about = "Browse a log interactively")]
+struct Opt {
+    #[structopt(parse(from_os_str), help = "Input log file")]
+    input: PathBuf,
+    #[structopt(long, help = "Initial filter, using the GST_DEBUG syntax")]
+    filter: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Input {
+    Filter,
+    Jump,
+}
+
+// Pending operations which may require to scan more of the log
+#[derive(Debug, Clone, Copy)]
+enum Goal {
+    End,
+    Ts(ClockTime),
+    // first entry from this line
+    Line(usize),
+}
+
+fn level_style(level: DebugLevel) -> Style {
+    match level {
+        DebugLevel::Error => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        DebugLevel::Warning => Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+        DebugLevel::Fixme => Style::default().fg(Color::Green),
+        DebugLevel::Info => Style::default().fg(Color::Cyan),
+        DebugLevel::Debug => Style::default(),
+        _ => Style::default().fg(Color::DarkGray),
+    }
+}
+
+struct Viewer {
+    name: String,
+    index: LogIndex<File>,
+    cache: HashMap<usize, Entry>,
+    filter: String,
+    thresholds: Option<Thresholds>,
+    thread: Option<String>,
+    object: Option<String>,
+    // line numbers of the entries matching the filters
+    view: Vec<usize>,
+    // number of lines of the log checked against the filters
+    scanned: usize,
+    scan_complete: bool,
+    // position of the selected and first displayed entries in the view
+    selected: usize,
+    top: usize,
+    height: usize,
+    bookmarks: BTreeSet<usize>,
+    details: bool,
+    input: Option<(Input, String)>,
+    goal: Option<Goal>,
+    status: Option<String>,
+    quit: bool,
+}
+
+impl Viewer {
+    fn new(opt: &Opt) -> anyhow::Result<Self> {
+        let mut viewer = Self {
+            name: opt.input.display().to_string(),
+            index: LogIndex::open(&opt.input)?,
+            cache: HashMap::new(),
+            filter: String::new(),
+            thresholds: None,
+            thread: None,
+            object: None,
+            view: Vec::new(),
+            scanned: 0,
+            scan_complete: false,
+            selected: 0,
+            top: 0,
+            height: 0,
+            bookmarks: BTreeSet::new(),
+            details: false,
+            input: None,
+            goal: None,
+            status: None,
+            quit: false,
+        };
+
+        if let Some(filter) = &opt.filter {
+            viewer.set_filter(filter);
+        }
+
+        Ok(viewer)
+    }
+
+    fn entry(&mut self, line: usize) -> Option<&Entry> {
+        if !self.cache.contains_key(&line) {
+            if self.cache.len() >= CACHE_SIZE {
+                self.cache.clear();
+            }
+            if let Ok(Some(Ok(entry))) = self.index.entry(line) {
+                self.cache.insert(line, entry);
+            }
+        }
+
+        self.cache.get(&line)
+    }
+
+    fn selected_line(&self) -> Option<usize> {
+        self.view.get(self.selected).cloned()
+    }
+
+    fn matches(&self, entry: &Entry) -> bool {
+        if let Some(thresholds) = &self.thresholds {
+            if !thresholds.matches(entry) {
+                return false;
+            }
+        }
+        if let Some(thread) = &self.thread {
+            if &entry.thread != thread {
+                return false;
+            }
+        }
+        if let Some(object) = &self.object {
+            if entry.object.as_ref().map(|o| o.to_string()).as_ref() != Some(object) {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Rebuild the view after filters changed, keeping the selection around the same place
+    fn reset_view(&mut self) {
+        let line = self.selected_line().unwrap_or(0);
+
+        self.view.clear();
+        self.scanned = 0;
+        self.scan_complete = false;
+        self.selected = 0;
+        self.top = 0;
+        self.goal = Some(Goal::Line(line));
+    }
+
+    fn set_filter(&mut self, filter: &str) {
+        if filter.trim().is_empty() {
+            self.thresholds = None;
+        } else {
+            match Thresholds::new(filter) {
+                Ok(thresholds) => self.thresholds = Some(thresholds),
+                Err(err) => {
+                    self.status = Some(err.to_string());
+                    return;
+                }
+            }
+        }
+
+        self.filter = filter.trim().to_string();
+        self.reset_view();
+    }
+
+    fn scan(&mut self) {
+        let entries = match self.index.entries(self.scanned, SCAN_CHUNK) {
+            Ok(entries) => entries,
+            Err(err) => {
+                self.status = Some(err.to_string());
+                self.scan_complete = true;
+                return;
+            }
+        };
+
+        if entries.is_empty() {
+            self.scan_complete = true;
+            return;
+        }
+
+        for (i, entry) in entries.iter().enumerate() {
+            if let Ok(entry) = entry {
+                if self.matches(entry) {
+                    self.view.push(self.scanned + i);
+                }
+            }
+        }
+        self.scanned += entries.len();
+    }
+
+    fn select(&mut self, pos: usize) {
+        self.selected = pos.min(self.view.len().saturating_sub(1));
+
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if self.height > 0 && self.selected >= self.top + self.height {
+            self.top = self.selected + 1 - self.height;
+        }
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        let pos = (self.selected as isize + delta).max(0) as usize;
+        self.select(pos);
+    }
+
+    // Position in the view of the first entry logged at or after @ts
+    fn find_ts(&mut self, ts: ClockTime) -> usize {
+        let (mut lo, mut hi) = (0, self.view.len());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let line = self.view[mid];
+            match self.entry(line) {
+                Some(entry) if entry.ts < ts => lo = mid + 1,
+                _ => hi = mid,
+            }
+        }
+        lo
+    }
+
+    // Try completing the pending goal with the part of the log scanned so far
+    fn resolve_goal(&mut self) {
+        let goal = match self.goal {
+            Some(goal) => goal,
+            None => return,
+        };
+
+        let pos = match goal {
+            Goal::End => {
+                if !self.scan_complete {
+                    return;
+                }
+                self.view.len()
+            }
+            Goal::Ts(ts) => {
+                let last = self.view.last().cloned();
+                let reached = match last {
+                    Some(line) => self.entry(line).is_some_and(|e| e.ts >= ts),
+                    None => false,
+                };
+                if !reached && !self.scan_complete {
+                    return;
+                }
+                self.find_ts(ts)
+            }
+            Goal::Line(line) => {
+                let pos = self.view.partition_point(|l| *l < line);
+                if pos == self.view.len() && !self.scan_complete {
+                    return;
+                }
+                pos
+            }
+        };
+
+        self.goal = None;
+        self.select(pos);
+    }
+
+    // Whether more of the log has to be scanned to display the screen or reach the goal
+    fn needs_scan(&self) -> bool {
+        !self.scan_complete && (self.goal.is_some() || self.view.len() <= self.top + self.height)
+    }
+
+    fn tick(&mut self) {
+        let start = Instant::now();
+
+        while self.needs_scan() && start.elapsed() < SCAN_BUDGET {
+            self.scan();
+            self.resolve_goal();
+        }
+        self.resolve_goal();
+    }
+
+    fn toggle_thread(&mut self) {
+        if self.thread.is_some() {
+            self.thread = None;
+        } else if let Some(line) = self.selected_line() {
+            self.thread = self.entry(line).map(|e| e.thread.clone());
+        }
+        self.reset_view();
+    }
+
+    fn toggle_object(&mut self) {
+        if self.object.is_some() {
+            self.object = None;
+        } else if let Some(line) = self.selected_line() {
+            self.object = self
+                .entry(line)
+                .and_then(|e| e.object.as_ref().map(|o| o.to_string()));
+            if self.object.is_none() {
+                self.status = Some("entry has no object".to_string());
+                return;
+            }
+        }
+        self.reset_view();
+    }
+
+    fn toggle_bookmark(&mut self) {
+        if let Some(line) = self.selected_line() {
+            if !self.bookmarks.remove(&line) {
+                self.bookmarks.insert(line);
+            }
+        }
+    }
+
+    // Select the closest bookmark in the view after, or before, the selected entry
+    fn jump_bookmark(&mut self, forward: bool) {
+        let current = match self.selected_line() {
+            Some(line) => line,
+            None => return,
+        };
+
+        let found = if forward {
+            self.bookmarks
+                .range(current + 1..)
+                .find_map(|line| self.view.binary_search(line).ok())
+        } else {
+            self.bookmarks
+                .range(..current)
+                .rev()
+                .find_map(|line| self.view.binary_search(line).ok())
+        };
+
+        match found {
+            Some(pos) => self.select(pos),
+            None => self.status = Some("no more bookmark".to_string()),
+        }
+    }
+
+    fn apply_input(&mut self, input: Input, text: &str) {
+        match input {
+            Input::Filter => self.set_filter(text),
+            Input::Jump => match parse_duration(text.trim()) {
+                Some(ts) => self.goal = Some(Goal::Ts(ts)),
+                None => self.status = Some(format!("invalid timestamp: {}", text)),
+            },
+        }
+    }
+
+    fn key(&mut self, key: KeyEvent) {
+        if let Some((input, mut text)) = self.input.take() {
+            match key.code {
+                KeyCode::Enter => self.apply_input(input, &text),
+                KeyCode::Esc => {}
+                KeyCode::Backspace => {
+                    text.pop();
+                    self.input = Some((input, text));
+                }
+                KeyCode::Char(c) => {
+                    text.push(c);
+                    self.input = Some((input, text));
+                }
+                _ => self.input = Some((input, text)),
+            }
+            return;
+        }
+
+        self.status = None;
+        let page = self.height.max(1) as isize;
+
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc => self.goal = None,
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::PageDown => self.move_by(page),
+            KeyCode::PageUp => self.move_by(-page),
+            KeyCode::Home | KeyCode::Char('g') => self.select(0),
+            KeyCode::End | KeyCode::Char('G') => self.goal = Some(Goal::End),
+            KeyCode::Char('/') => self.input = Some((Input::Filter, self.filter.clone())),
+            KeyCode::Char(':') => self.input = Some((Input::Jump, String::new())),
+            KeyCode::Char('T') => self.toggle_thread(),
+            KeyCode::Char('O') => self.toggle_object(),
+            KeyCode::Char('m') => self.toggle_bookmark(),
+            KeyCode::Char('n') => self.jump_bookmark(true),
+            KeyCode::Char('N') => self.jump_bookmark(false),
+            KeyCode::Enter | KeyCode::Char('d') => self.details = !self.details,
+            _ => {}
+        }
+    }
+
+    fn entry_line(&mut self, pos: usize) -> Line<'static> {
+        let line = self.view[pos];
+        let bookmark = if self.bookmarks.contains(&line) {
+            "*"
+        } else {
+            " "
+        };
+        let selected = pos == self.selected;
+
+        let line = match self.entry(line) {
+            Some(entry) => Line::from(vec![
+                Span::raw(format!("{} {} {:>14} ", bookmark, entry.ts, entry.thread)),
+                Span::styled(
+                    format!("{:<7}", format!("{:?}", entry.level)),
+                    level_style(entry.level),
+                ),
+                Span::raw(format!(
+                    " {:>20} {}{}",
+                    entry.category,
+                    entry
+                        .object
+                        .as_ref()
+                        .map(|o| format!("<{}> ", o))
+                        .unwrap_or_default(),
+                    entry.message
+                )),
+            ]),
+            None => Line::from(format!("{} <line {} is not available>", bookmark, line + 1)),
+        };
+
+        if selected {
+            line.style(Style::default().add_modifier(Modifier::REVERSED))
+        } else {
+            line
+        }
+    }
+
+    fn details_text(&mut self) -> Vec<Line<'static>> {
+        let line = match self.selected_line() {
+            Some(line) => line,
+            None => return Vec::new(),
+        };
+        let entry = match self.entry(line) {
+            Some(entry) => entry,
+            None => return Vec::new(),
+        };
+
+        let mut text = vec![
+            Line::from(format!(
+                "line {}  ts {}  pid {}  thread {}  {:?}",
+                line + 1,
+                entry.ts,
+                entry.pid,
+                entry.thread,
+                entry.level
+            )),
+            Line::from(format!(
+                "{} {}:{}:{} {}",
+                entry.category,
+                entry.file,
+                entry.line,
+                entry.function,
+                entry
+                    .object
+                    .as_ref()
+                    .map(|o| format!("<{}>", o))
+                    .unwrap_or_default()
+            )),
+            Line::from(entry.message.clone()),
+        ];
+
+        if let Some(s) = entry.message_to_struct() {
+            text.push(Line::from(""));
+            text.push(Line::styled(
+                s.name().to_string(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            for (field, value) in s.iter() {
+                let serialized = value
+                    .serialize()
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|_| "?".to_string());
+                text.push(Line::from(format!(
+                    "  {} ({}): {}",
+                    field,
+                    value.type_().name(),
+                    serialized
+                )));
+            }
+        }
+
+        text
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        if let Some((input, text)) = &self.input {
+            let prompt = match input {
+                Input::Filter => "filter (GST_DEBUG syntax): ",
+                Input::Jump => "jump to timestamp: ",
+            };
+            return Line::from(format!("{}{}", prompt, text));
+        }
+
+        let more = |complete: bool| if complete { "" } else { "+" };
+        let mut parts = vec![
+            self.name.clone(),
+            format!(
+                "entry {}/{}{}",
+                (self.selected + 1).min(self.view.len()),
+                self.view.len(),
+                more(self.scan_complete)
+            ),
+            format!(
+                "{}{} lines indexed",
+                self.index.len(),
+                more(self.index.is_complete())
+            ),
+        ];
+        if !self.filter.is_empty() {
+            parts.push(format!("filter {}", self.filter));
+        }
+        if let Some(thread) = &self.thread {
+            parts.push(format!("thread {}", thread));
+        }
+        if let Some(object) = &self.object {
+            parts.push(format!("object {}", object));
+        }
+        if self.goal.is_some() {
+            parts.push("searching... (Esc to cancel)".to_string());
+        }
+        if let Some(status) = &self.status {
+            parts.push(status.clone());
+        }
+        parts.push("q / : T O m n N d".to_string());
+
+        Line::from(parts.join(" | "))
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let details = if self.details { DETAILS_HEIGHT } else { 0 };
+        let [list_area, details_area, status_area] = Layout::vertical([
+            Constraint::Min(3),
+            Constraint::Length(details),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let block = Block::bordered().title(self.name.clone());
+        self.height = block.inner(list_area).height as usize;
+        // keep the selection visible if the screen has been resized
+        self.select(self.selected);
+
+        let end = (self.top + self.height).min(self.view.len());
+        let lines: Vec<_> = (self.top..end).map(|pos| self.entry_line(pos)).collect();
+        frame.render_widget(Paragraph::new(lines).block(block), list_area);
+
+        if self.details {
+            let text = self.details_text();
+            frame.render_widget(
+                Paragraph::new(text)
+                    .block(Block::bordered().title("details"))
+                    .wrap(Wrap { trim: false }),
+                details_area,
+            );
+        }
+
+        frame.render_widget(
+            Paragraph::new(self.status_line())
+                .style(Style::default().add_modifier(Modifier::REVERSED)),
+            status_area,
+        );
+    }
+}
+
+fn run(mut terminal: DefaultTerminal, mut viewer: Viewer) -> io::Result<()> {
+    while !viewer.quit {
+        viewer.tick();
+        terminal.draw(|frame| viewer.draw(frame))?;
+
+        // Keep scanning the log as long as needed, otherwise wait for user input
+        let timeout = if viewer.needs_scan() {
+            Duration::ZERO
+        } else {
+            Duration::from_millis(250)
+        };
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    viewer.key(key);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let opt = Opt::from_args();
+    gst::init()?;
+    let viewer = Viewer::new(&opt)?;
+
+    let terminal = ratatui::init();
+    let res = run(terminal, viewer);
+    ratatui::restore();
+
+    Ok(res?)
+}
@@ -0,0 +1,363 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Remove private information from logs so they can be shared.
+
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+use crate::{Entry, ObjectRef};
+
+lazy_static! {
+    static ref URI_RE: Regex = Regex::new(r#"\b([a-zA-Z][a-zA-Z0-9+.-]*)://[^\s"'<>]+"#).unwrap();
+    static ref HOME_RE: Regex = Regex::new(r#"(?:/home|/Users)/[^\s/"'<>:,;()]+(?:/[^\s"'<>:,;()]*)?"#).unwrap();
+    pub(crate) static ref ADDRESS_RE: Regex = Regex::new(r"\b0x[0-9a-fA-F]{6,}\b").unwrap();
+    static ref OBJECT_RE: Regex = Regex::new(r"<([^<>\s:]+)(:[^<>\s]+)?>").unwrap();
+    // uname logged by GStreamer at init: "Linux cass-x230 4.12.14-200.fc25.x86_64 #1 SMP ..."
+    static ref UNAME_RE: Regex = Regex::new(r"^\S+ (\S+) \S+ #").unwrap();
+}
+
+// Base of the pseudonym addresses so they still look like pointers
+const ADDRESS_BASE: u64 = 0x1000_0000;
+
+// Assign to each distinct value its index in order of first appearance, starting from 1
+#[derive(Debug, Default)]
+pub struct Pseudonyms {
+    map: HashMap<String, usize>,
+}
+
+impl Pseudonyms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&mut self, value: &str) -> usize {
+        let n = self.map.len() + 1;
+        *self.map.entry(value.to_string()).or_insert(n)
+    }
+
+    pub fn contains(&self, value: &str) -> bool {
+        self.map.contains_key(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub enum Rule {
+    // Replace URIs, keeping their scheme
+    Uris,
+    // Replace paths in home directories
+    HomePaths,
+    // Replace host names, including the one logged by GStreamer at init
+    Hostnames(Vec<String>),
+    // Replace pointer addresses by stable fake ones
+    Addresses,
+    // Replace object names, keeping pad names
+    ObjectNames,
+    // Replace matches of a regular expression, @replacement may refer to captures as '$1'
+    Custom { regex: Regex, replacement: String },
+}
+
+impl Rule {
+    // Rules matching text which may appear in any field
+    fn is_text(&self) -> bool {
+        matches!(
+            self,
+            Rule::Uris | Rule::HomePaths | Rule::Hostnames(_) | Rule::Custom { .. }
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Sanitizer {
+    rules: Vec<Rule>,
+    uris: Pseudonyms,
+    paths: Pseudonyms,
+    hosts: Pseudonyms,
+    host_re: Option<Regex>,
+    addresses: Pseudonyms,
+    objects: Pseudonyms,
+}
+
+impl Sanitizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Sanitizer applying all the built-in rules
+    pub fn with_default_rules() -> Self {
+        Self::new()
+            .rule(Rule::Uris)
+            .rule(Rule::HomePaths)
+            .rule(Rule::Hostnames(Vec::new()))
+            .rule(Rule::Addresses)
+            .rule(Rule::ObjectNames)
+    }
+
+    pub fn rule(mut self, rule: Rule) -> Self {
+        if let Rule::Hostnames(hosts) = &rule {
+            for host in hosts.iter() {
+                self.add_hostname(host);
+            }
+        }
+        self.rules.push(rule);
+        self
+    }
+
+    fn add_hostname(&mut self, host: &str) {
+        self.hosts.get(host);
+
+        let mut hosts: Vec<_> = self.hosts.map.keys().map(|h| regex::escape(h)).collect();
+        // Longest first so hosts containing others are fully replaced
+        hosts.sort_by_key(|h| std::cmp::Reverse(h.len()));
+        self.host_re = Regex::new(&format!(r"\b(?:{})\b", hosts.join("|"))).ok();
+    }
+
+    fn address(&mut self, address: &str) -> u64 {
+        ADDRESS_BASE + self.addresses.get(&address.to_lowercase()) as u64
+    }
+
+    fn object_name(&mut self, name: &str) -> String {
+        format!("obj{}", self.objects.get(name))
+    }
+
+    fn sanitize_text(&mut self, rule: &Rule, text: &str) -> String {
+        match rule {
+            Rule::Uris => {
+                let uris = &mut self.uris;
+                URI_RE
+                    .replace_all(text, |caps: &Captures| {
+                        format!("{}://uri{}", &caps[1], uris.get(&caps[0]))
+                    })
+                    .to_string()
+            }
+            Rule::HomePaths => {
+                let paths = &mut self.paths;
+                HOME_RE
+                    .replace_all(text, |caps: &Captures| {
+                        format!("/home/user/path{}", paths.get(&caps[0]))
+                    })
+                    .to_string()
+            }
+            Rule::Hostnames(_) => match &self.host_re {
+                Some(re) => {
+                    let hosts = &mut self.hosts;
+                    re.replace_all(text, |caps: &Captures| {
+                        format!("host{}", hosts.get(&caps[0]))
+                    })
+                    .to_string()
+                }
+                None => text.to_string(),
+            },
+            Rule::Addresses => {
+                let re = &*ADDRESS_RE;
+                re.replace_all(text, |caps: &Captures| {
+                    format!("{:#x}", self.address(&caps[0]))
+                })
+                .to_string()
+            }
+            Rule::ObjectNames => {
+                let re = &*OBJECT_RE;
+                re.replace_all(text, |caps: &Captures| {
+                    // addresses are handled by their own rule
+                    if caps[1].starts_with("0x") || caps[1].contains('@') {
+                        return caps[0].to_string();
+                    }
+                    format!(
+                        "<{}{}>",
+                        self.object_name(&caps[1]),
+                        caps.get(2).map(|m| m.as_str()).unwrap_or_default()
+                    )
+                })
+                .to_string()
+            }
+            Rule::Custom { regex, replacement } => {
+                regex.replace_all(text, replacement.as_str()).to_string()
+            }
+        }
+    }
+
+    fn sanitize_object(&mut self, rule: &Rule, object: ObjectRef) -> ObjectRef {
+        match (rule, object) {
+            (Rule::Addresses, ObjectRef::Address { type_name, address }) => ObjectRef::Address {
+                type_name,
+                address: format!("{:#x}", self.address(&address)),
+            },
+            (Rule::ObjectNames, ObjectRef::Named { name, pad }) => ObjectRef::Named {
+                name: self.object_name(&name),
+                pad,
+            },
+            (rule, ObjectRef::Named { name, pad }) if rule.is_text() => ObjectRef::Named {
+                name: self.sanitize_text(rule, &name),
+                pad: pad.map(|pad| self.sanitize_text(rule, &pad)),
+            },
+            (_, object) => object,
+        }
+    }
+
+    // Learn the host name from the uname logged by GStreamer at init, if @entry is
+    // this one. Call it on the whole log before sanitizing so mentions of the host
+    // preceding this entry are also replaced.
+    pub fn learn_hostname(&mut self, entry: &Entry) -> bool {
+        let has_hostnames = self.rules.iter().any(|r| matches!(r, Rule::Hostnames(_)));
+        if !has_hostnames || entry.category != "GST_INIT" {
+            return false;
+        }
+
+        match UNAME_RE.captures(&entry.message) {
+            Some(caps) => {
+                let host = caps[1].to_string();
+                self.add_hostname(&host);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn sanitize(&mut self, mut entry: Entry) -> Entry {
+        self.learn_hostname(&entry);
+
+        let rules = std::mem::take(&mut self.rules);
+
+        // Fields are handled in the order they are logged so pseudonyms are
+        // numbered by order of appearance.
+        for rule in rules.iter() {
+            if rule.is_text() {
+                entry.thread = self.sanitize_text(rule, &entry.thread);
+                entry.category = self.sanitize_text(rule, &entry.category);
+                entry.file = self.sanitize_text(rule, &entry.file);
+                entry.function = self.sanitize_text(rule, &entry.function);
+            } else if let Rule::Addresses = rule {
+                entry.thread = self.sanitize_text(rule, &entry.thread);
+            }
+
+            entry.object = entry.object.map(|o| self.sanitize_object(rule, o));
+            entry.message = self.sanitize_text(rule, &entry.message);
+        }
+
+        self.rules = rules;
+        entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use crate::writer::EntryWriter;
+    use std::fs::File;
+
+    #[test]
+    fn default_rules() {
+        let mut sanitizer = Sanitizer::with_default_rules();
+        let entries: Vec<_> = parse(File::open("test-logs/color.log").unwrap())
+            .map(|e| sanitizer.sanitize(e))
+            .collect();
+
+        assert_eq!(
+            entries[2].message,
+            "Linux host1 4.12.14-200.fc25.x86_64 #1 SMP Wed Sep 20 16:40:50 UTC 2017 x86_64"
+        );
+        // all threads and addresses are consistently replaced
+        assert_eq!(entries[0].thread, "0x10000001");
+        assert_eq!(entries[4].message, "init allocator 0x10000002");
+        assert_eq!(
+            entries[5].message,
+            "registering allocator 0x10000002 with name \"SystemMemory\""
+        );
+        assert_eq!(entries[14].thread, "0x10000003");
+        assert_eq!(
+            entries[14].object,
+            Some(ObjectRef::Named {
+                name: "obj1".to_string(),
+                pad: None
+            })
+        );
+
+        // the sanitized log can still be parsed
+        let mut writer = EntryWriter::new(Vec::new());
+        for entry in entries.iter() {
+            writer.write(entry).unwrap();
+        }
+        let output = writer.into_inner();
+        assert_eq!(parse(output.as_slice()).count(), entries.len());
+    }
+
+    #[test]
+    fn rules() {
+        let mut sanitizer = Sanitizer::new()
+            .rule(Rule::Uris)
+            .rule(Rule::HomePaths)
+            .rule(Rule::Hostnames(vec!["build-box".to_string()]))
+            .rule(Rule::Addresses)
+            .rule(Rule::ObjectNames)
+            .rule(Rule::Custom {
+                regex: Regex::new(r"serial=\w+").unwrap(),
+                replacement: "serial=XXX".to_string(),
+            });
+
+        let entry = Entry::new("0:00:00.007773544  8874 0x558951015c00 INFO                GST_INIT /home/bob/gst/gst.c:510:init_pre:<camera-front:src> playing rtsp://10.0.0.1/stream from /home/bob/videos/a.mp4 on build-box serial=A1B2 <camera-front> <GstObject@0x55895101d040>").unwrap();
+        let entry = sanitizer.sanitize(entry);
+
+        assert_eq!(entry.file, "/home/user/path1");
+        assert_eq!(entry.thread, "0x10000001");
+        assert_eq!(entry.object, Some(ObjectRef::from("obj1:src")));
+        assert_eq!(
+            entry.message,
+            "playing rtsp://uri1 from /home/user/path2 on host1 serial=XXX <obj1> <GstObject@0x10000002>"
+        );
+    }
+
+    #[test]
+    fn hostname_before_uname() {
+        let lines = [
+            "0:00:00.000100000 17267      0x2192200 INFO                GST_INIT gst.c:570:init_pre: Started on cass-x230",
+            "0:00:00.000325904 17267      0x2192200 INFO                GST_INIT gst.c:605:init_pre: Linux cass-x230 4.12.14-200.fc25.x86_64 #1 SMP Wed Sep 20 16:40:50 UTC 2017 x86_64",
+        ];
+        let entries: Vec<_> = lines.iter().map(|l| Entry::new(l).unwrap()).collect();
+
+        let mut sanitizer = Sanitizer::new().rule(Rule::Hostnames(Vec::new()));
+        assert!(!sanitizer.learn_hostname(&entries[0]));
+        assert!(sanitizer.learn_hostname(&entries[1]));
+
+        let entries: Vec<_> = entries.into_iter().map(|e| sanitizer.sanitize(e)).collect();
+        assert_eq!(entries[0].message, "Started on host1");
+        assert_eq!(
+            entries[1].message,
+            "Linux host1 4.12.14-200.fc25.x86_64 #1 SMP Wed Sep 20 16:40:50 UTC 2017 x86_64"
+        );
+    }
+
+    #[test]
+    fn all_fields() {
+        let mut sanitizer = Sanitizer::new()
+            .rule(Rule::Hostnames(vec!["build-box".to_string()]))
+            .rule(Rule::Custom {
+                regex: Regex::new(r"acme").unwrap(),
+                replacement: "vendor".to_string(),
+            });
+
+        let entry = Entry::new("0:00:00.007773544  8874 0x558951015c00 INFO                acmesrc acme/gstacmesrc.c:510:acme_open:<acmesrc0:build-box> connected to acme").unwrap();
+        let entry = sanitizer.sanitize(entry);
+
+        assert_eq!(entry.category, "vendorsrc");
+        assert_eq!(entry.file, "vendor/gstvendorsrc.c");
+        assert_eq!(entry.function, "vendor_open");
+        assert_eq!(entry.object, Some(ObjectRef::from("vendorsrc0:host1")));
+        assert_eq!(entry.message, "connected to vendor");
+    }
+}
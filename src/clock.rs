@@ -0,0 +1,96 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conversion between the running time of entries and wall-clock time.
+
+use std::convert::TryFrom;
+
+use chrono::{DateTime, Duration, FixedOffset};
+use gst::ClockTime;
+use gstreamer as gst;
+
+use crate::Entry;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ClockConverter {
+    running_time: ClockTime,
+    wall_clock: DateTime<FixedOffset>,
+}
+
+impl ClockConverter {
+    pub fn new(running_time: ClockTime, wall_clock: DateTime<FixedOffset>) -> Self {
+        Self {
+            running_time,
+            wall_clock,
+        }
+    }
+
+    // Use an entry having both its running and absolute time as anchor
+    pub fn from_entry(anchor: &Entry) -> Option<Self> {
+        anchor
+            .datetime
+            .map(|wall_clock| Self::new(anchor.ts, wall_clock))
+    }
+
+    pub fn to_wall_clock(&self, ts: ClockTime) -> DateTime<FixedOffset> {
+        if ts >= self.running_time {
+            self.wall_clock + nseconds(ts - self.running_time)
+        } else {
+            self.wall_clock - nseconds(self.running_time - ts)
+        }
+    }
+
+    // Return None if @wall_clock is before the start of the running time
+    pub fn to_running_time(&self, wall_clock: &DateTime<FixedOffset>) -> Option<ClockTime> {
+        let diff = wall_clock.signed_duration_since(self.wall_clock);
+        let diff_ns = diff.num_nanoseconds()?;
+
+        if diff_ns >= 0 {
+            self.running_time
+                .checked_add(ClockTime::from_nseconds(u64::try_from(diff_ns).ok()?))
+        } else {
+            self.running_time
+                .checked_sub(ClockTime::from_nseconds(diff_ns.unsigned_abs()))
+        }
+    }
+}
+
+pub(crate) fn nseconds(t: ClockTime) -> Duration {
+    Duration::nanoseconds(i64::try_from(t.nseconds()).unwrap_or(i64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversion() {
+        let anchor = Entry::new("2024-02-09T10:11:12.5+01:00 0:00:02.000000000  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Init").unwrap();
+        let conv = ClockConverter::from_entry(&anchor).unwrap();
+
+        let dt = conv.to_wall_clock(ClockTime::from_mseconds(3250));
+        assert_eq!(dt.to_rfc3339(), "2024-02-09T10:11:13.750+01:00");
+        assert_eq!(
+            conv.to_running_time(&dt),
+            Some(ClockTime::from_mseconds(3250))
+        );
+
+        let dt = conv.to_wall_clock(ClockTime::from_mseconds(500));
+        assert_eq!(dt.to_rfc3339(), "2024-02-09T10:11:11+01:00");
+        assert_eq!(
+            conv.to_running_time(&dt),
+            Some(ClockTime::from_mseconds(500))
+        );
+
+        let before = DateTime::parse_from_rfc3339("2024-02-09T09:11:00Z").unwrap();
+        assert_eq!(conv.to_running_time(&before), None);
+
+        let no_datetime = Entry::new("0:00:02.000000000  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Init").unwrap();
+        assert!(ClockConverter::from_entry(&no_datetime).is_none());
+    }
+}
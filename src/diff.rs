@@ -0,0 +1,441 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Compare the logs of two runs by aligning entries on what they log rather than on their text.
+
+use std::collections::HashMap;
+
+use crate::normalize::message_template;
+use crate::Entry;
+
+// Entries having the same key are considered as logging the same event
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntryKey {
+    pub category: String,
+    pub function: String,
+    pub template: String,
+}
+
+impl EntryKey {
+    pub fn new(entry: &Entry) -> Self {
+        Self {
+            category: entry.category.clone(),
+            function: entry.function.clone(),
+            template: message_template(&entry.message),
+        }
+    }
+}
+
+// Indexes refer to the position of the entries in the compared slices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Aligned { a: usize, b: usize },
+    OnlyA(usize),
+    OnlyB(usize),
+}
+
+// Shortest edit script between @a and @b, using the linear space variant of Myers' algorithm
+fn align<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Change> {
+    let max_d = (a.len() + b.len()).div_ceil(2) + 1;
+    let mut vf = V::new(max_d);
+    let mut vb = V::new(max_d);
+    let mut changes = Vec::with_capacity(a.len().max(b.len()));
+
+    conquer(a, 0, b, 0, &mut vf, &mut vb, &mut changes);
+    changes
+}
+
+// Furthest x reached on each diagonal k = x - y
+struct V {
+    offset: isize,
+    v: Vec<usize>,
+}
+
+impl V {
+    fn new(max_d: usize) -> Self {
+        Self {
+            offset: max_d as isize + 1,
+            v: vec![0; 2 * max_d + 3],
+        }
+    }
+}
+
+impl std::ops::Index<isize> for V {
+    type Output = usize;
+
+    fn index(&self, k: isize) -> &usize {
+        &self.v[(k + self.offset) as usize]
+    }
+}
+
+impl std::ops::IndexMut<isize> for V {
+    fn index_mut(&mut self, k: isize) -> &mut usize {
+        &mut self.v[(k + self.offset) as usize]
+    }
+}
+
+fn common_prefix<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    a.iter().zip(b.iter()).take_while(|(a, b)| a == b).count()
+}
+
+fn common_suffix<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    a.iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+// Start of the middle snake of the shortest edit script, searching it from both ends at once.
+// @a and @b are not empty and their first and last items differ.
+fn middle_snake<T: PartialEq>(a: &[T], b: &[T], vf: &mut V, vb: &mut V) -> Option<(usize, usize)> {
+    let (n, m) = (a.len(), b.len());
+    let delta = n as isize - m as isize;
+    let odd = delta & 1 == 1;
+    let max_d = ((n + m).div_ceil(2) + 1) as isize;
+
+    // vb stores the distance to the end of the sequences
+    vf[1] = 0;
+    vb[1] = 0;
+
+    for d in 0..max_d {
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && vf[k - 1] < vf[k + 1]) {
+                vf[k + 1]
+            } else {
+                vf[k - 1] + 1
+            };
+            let y = (x as isize - k) as usize;
+            let (x0, y0) = (x, y);
+            if x < n && y < m {
+                x += common_prefix(&a[x..], &b[y..]);
+            }
+            vf[k] = x;
+
+            if odd && (k - delta).abs() < d && vf[k] + vb[-(k - delta)] >= n {
+                return Some((x0, y0));
+            }
+        }
+
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && vb[k - 1] < vb[k + 1]) {
+                vb[k + 1]
+            } else {
+                vb[k - 1] + 1
+            };
+            let mut y = (x as isize - k) as usize;
+            if x < n && y < m {
+                let advance = common_suffix(&a[..n - x], &b[..m - y]);
+                x += advance;
+                y += advance;
+            }
+            vb[k] = x;
+
+            if !odd && (k - delta).abs() <= d && vb[k] + vf[-(k - delta)] >= n {
+                return Some((n - x, m - y));
+            }
+        }
+    }
+
+    None
+}
+
+fn conquer<T: PartialEq>(
+    a: &[T],
+    a_start: usize,
+    b: &[T],
+    b_start: usize,
+    vf: &mut V,
+    vb: &mut V,
+    changes: &mut Vec<Change>,
+) {
+    let prefix = common_prefix(a, b);
+    changes.extend((0..prefix).map(|i| Change::Aligned {
+        a: a_start + i,
+        b: b_start + i,
+    }));
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let (a_start, b_start) = (a_start + prefix, b_start + prefix);
+
+    let suffix = common_suffix(a, b);
+    let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+
+    if a.is_empty() || b.is_empty() {
+        changes.extend((0..a.len()).map(|i| Change::OnlyA(a_start + i)));
+        changes.extend((0..b.len()).map(|i| Change::OnlyB(b_start + i)));
+    } else if let Some((x, y)) = middle_snake(a, b, vf, vb) {
+        conquer(&a[..x], a_start, &b[..y], b_start, vf, vb, changes);
+        conquer(&a[x..], a_start + x, &b[y..], b_start + y, vf, vb, changes);
+    } else {
+        changes.extend((0..a.len()).map(|i| Change::OnlyA(a_start + i)));
+        changes.extend((0..b.len()).map(|i| Change::OnlyB(b_start + i)));
+    }
+
+    changes.extend((0..suffix).map(|i| Change::Aligned {
+        a: a_start + a.len() + i,
+        b: b_start + b.len() + i,
+    }));
+}
+
+#[derive(Debug)]
+pub struct ThreadDiff {
+    pub thread: String,
+    pub changes: Vec<Change>,
+}
+
+impl ThreadDiff {
+    pub fn first_divergence(&self) -> Option<Change> {
+        self.changes
+            .iter()
+            .find(|c| !matches!(c, Change::Aligned { .. }))
+            .cloned()
+    }
+}
+
+// Timing of aligned entries, relative to the first entry of their log.
+// @delta is how much later the entry of b has been logged compared to the one of a, in ns,
+// and @drift how much this delta changed since the previous aligned entry of the thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingDelta {
+    pub a: usize,
+    pub b: usize,
+    pub delta: i64,
+    pub drift: i64,
+}
+
+#[derive(Debug)]
+pub struct LogDiff {
+    pub threads: Vec<ThreadDiff>,
+}
+
+impl LogDiff {
+    // Threads are matched by name so @a and @b should be normalized, see normalize::Normalizer.
+    // Entries of each thread are aligned separately as the interleaving of threads changes between runs.
+    pub fn new(a: &[Entry], b: &[Entry]) -> Self {
+        let mut threads: Vec<String> = Vec::new();
+        let mut per_thread: HashMap<&str, (Vec<usize>, Vec<usize>)> = HashMap::new();
+
+        for (i, entry) in a.iter().enumerate() {
+            per_thread
+                .entry(&entry.thread)
+                .or_insert_with(|| {
+                    threads.push(entry.thread.clone());
+                    Default::default()
+                })
+                .0
+                .push(i);
+        }
+        for (i, entry) in b.iter().enumerate() {
+            per_thread
+                .entry(&entry.thread)
+                .or_insert_with(|| {
+                    threads.push(entry.thread.clone());
+                    Default::default()
+                })
+                .1
+                .push(i);
+        }
+
+        let threads = threads
+            .into_iter()
+            .map(|thread| {
+                let (a_idx, b_idx) = &per_thread[thread.as_str()];
+                let a_keys: Vec<_> = a_idx.iter().map(|i| EntryKey::new(&a[*i])).collect();
+                let b_keys: Vec<_> = b_idx.iter().map(|i| EntryKey::new(&b[*i])).collect();
+
+                let changes = align(&a_keys, &b_keys)
+                    .into_iter()
+                    .map(|c| match c {
+                        Change::Aligned { a, b } => Change::Aligned {
+                            a: a_idx[a],
+                            b: b_idx[b],
+                        },
+                        Change::OnlyA(a) => Change::OnlyA(a_idx[a]),
+                        Change::OnlyB(b) => Change::OnlyB(b_idx[b]),
+                    })
+                    .collect();
+
+                ThreadDiff { thread, changes }
+            })
+            .collect();
+
+        Self { threads }
+    }
+
+    pub fn changes(&self) -> impl Iterator<Item = &Change> {
+        self.threads.iter().flat_map(|t| t.changes.iter())
+    }
+
+    pub fn only_a(&self) -> impl Iterator<Item = usize> + '_ {
+        self.changes().filter_map(|c| match c {
+            Change::OnlyA(a) => Some(*a),
+            _ => None,
+        })
+    }
+
+    pub fn only_b(&self) -> impl Iterator<Item = usize> + '_ {
+        self.changes().filter_map(|c| match c {
+            Change::OnlyB(b) => Some(*b),
+            _ => None,
+        })
+    }
+
+    pub fn is_identical(&self) -> bool {
+        self.changes().all(|c| matches!(c, Change::Aligned { .. }))
+    }
+
+    // @a and @b have to be the slices the diff has been created from
+    pub fn timing_deltas(&self, a: &[Entry], b: &[Entry]) -> Vec<TimingDelta> {
+        let (a_start, b_start) = match (a.first(), b.first()) {
+            (Some(a), Some(b)) => (a.ts.nseconds() as i64, b.ts.nseconds() as i64),
+            _ => return Vec::new(),
+        };
+
+        let mut deltas = Vec::new();
+        for thread in self.threads.iter() {
+            let mut previous = None;
+            for change in thread.changes.iter() {
+                if let Change::Aligned { a: ia, b: ib } = *change {
+                    let delta = (b[ib].ts.nseconds() as i64 - b_start)
+                        - (a[ia].ts.nseconds() as i64 - a_start);
+                    deltas.push(TimingDelta {
+                        a: ia,
+                        b: ib,
+                        delta,
+                        drift: delta - previous.unwrap_or(0),
+                    });
+                    previous = Some(delta);
+                }
+            }
+        }
+        deltas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize::normalize;
+    use crate::parse;
+    use gst::ClockTime;
+    use gstreamer as gst;
+    use std::fs::File;
+
+    #[test]
+    fn myers() {
+        let a: Vec<_> = "ABCABBA".chars().collect();
+        let b: Vec<_> = "CBABAC".chars().collect();
+        let changes = align(&a, &b);
+
+        // shortest edit script has 5 insertions/deletions
+        assert_eq!(
+            changes
+                .iter()
+                .filter(|c| !matches!(c, Change::Aligned { .. }))
+                .count(),
+            5
+        );
+        for c in changes.iter() {
+            if let Change::Aligned { a: ia, b: ib } = c {
+                assert_eq!(a[*ia], b[*ib]);
+            }
+        }
+
+        assert_eq!(align::<char>(&[], &[]), vec![]);
+        assert_eq!(align(&['a'], &[]), vec![Change::OnlyA(0)]);
+        assert_eq!(
+            align(&['a', 'b'], &['b']),
+            vec![Change::OnlyA(0), Change::Aligned { a: 1, b: 0 }]
+        );
+    }
+
+    // Length of the longest common subsequence
+    fn lcs(a: &[u8], b: &[u8]) -> usize {
+        let mut prev = vec![0; b.len() + 1];
+        for x in a {
+            let mut cur = vec![0; b.len() + 1];
+            for (j, y) in b.iter().enumerate() {
+                cur[j + 1] = if x == y {
+                    prev[j] + 1
+                } else {
+                    prev[j + 1].max(cur[j])
+                };
+            }
+            prev = cur;
+        }
+        prev[b.len()]
+    }
+
+    #[test]
+    fn myers_random() {
+        let mut seed = 42u32;
+        let mut random = |len: usize| -> Vec<u8> {
+            (0..len)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                    (seed >> 16) as u8 % 4
+                })
+                .collect()
+        };
+
+        for i in 0..200 {
+            let a = random(i % 23);
+            let b = random(i % 17);
+            let changes = align(&a, &b);
+
+            let (mut next_a, mut next_b, mut aligned) = (0, 0, 0);
+            for c in changes.iter() {
+                match *c {
+                    Change::Aligned { a: ia, b: ib } => {
+                        assert_eq!((ia, ib), (next_a, next_b));
+                        assert_eq!(a[ia], b[ib]);
+                        next_a += 1;
+                        next_b += 1;
+                        aligned += 1;
+                    }
+                    Change::OnlyA(ia) => {
+                        assert_eq!(ia, next_a);
+                        next_a += 1;
+                    }
+                    Change::OnlyB(ib) => {
+                        assert_eq!(ib, next_b);
+                        next_b += 1;
+                    }
+                }
+            }
+            assert_eq!((next_a, next_b), (a.len(), b.len()));
+            assert_eq!(aligned, lcs(&a, &b), "{:?} {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn log_diff() {
+        let log = || normalize(parse(File::open("test-logs/nocolor.log").unwrap()));
+        let a: Vec<_> = log().collect();
+        let mut b: Vec<_> = log().collect();
+        // drop the 'init allocator' entry, change a pointer and delay the end of the log
+        b.remove(3);
+        b[3].message = "0x7f0000000000 ref 1->2".to_string();
+        b[4].ts += ClockTime::from_seconds(1);
+
+        let diff = LogDiff::new(&a, &b);
+        assert_eq!(diff.threads.len(), 1);
+        assert_eq!(diff.threads[0].first_divergence(), Some(Change::OnlyA(3)));
+        assert_eq!(diff.only_a().collect::<Vec<_>>(), vec![3]);
+        assert_eq!(diff.only_b().count(), 0);
+        assert!(!diff.is_identical());
+        assert!(LogDiff::new(&a, &a).is_identical());
+
+        let deltas = diff.timing_deltas(&a, &b);
+        assert_eq!(deltas.len(), 5);
+        let last = deltas.last().unwrap();
+        assert_eq!((last.a, last.b), (5, 4));
+        assert_eq!(last.delta, 1_000_000_000);
+        assert_eq!(last.drift, 1_000_000_000);
+    }
+}
@@ -0,0 +1,732 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Analysis of the buffers flowing through pads, as reported by the 'stats' tracer.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use gst::{BufferFlags, ClockTime};
+use gstreamer as gst;
+
+use crate::tracer::{BufferRecord, Record};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timestamp {
+    Pts,
+    Dts,
+}
+
+impl Timestamp {
+    pub fn get(self, buffer: &BufferRecord) -> Option<ClockTime> {
+        match self {
+            Timestamp::Pts => buffer.pts,
+            Timestamp::Dts => buffer.dts,
+        }
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Timestamp::Pts => write!(f, "pts"),
+            Timestamp::Dts => write!(f, "dts"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    // Lower than the timestamp of the previous buffer
+    Decreasing { previous: ClockTime },
+    // Same timestamp as the previous buffer
+    Duplicate,
+    // Not following the timestamp + duration of the previous buffer
+    Discontinuity { expected: ClockTime },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub pad_ix: u32,
+    // ts of the buffer record
+    pub ts: ClockTime,
+    pub timestamp: ClockTime,
+    pub kind: IssueKind,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PadTimestamps {
+    // Number of buffers having the checked timestamp
+    pub buffers: u64,
+    pub decreasing: u64,
+    pub duplicates: u64,
+    pub discontinuities: u64,
+    // Decreasing PTS tolerated because of frames reordering
+    pub reordered: u64,
+    // Number of flushes and new segments
+    pub resets: u64,
+    last: Option<ClockTime>,
+    expected: Option<ClockTime>,
+    // Highest DTS since the last reset
+    max_dts: Option<ClockTime>,
+}
+
+impl PadTimestamps {
+    pub fn issues(&self) -> u64 {
+        self.decreasing + self.duplicates + self.discontinuities
+    }
+
+    fn reset(&mut self) {
+        self.last = None;
+        self.expected = None;
+        self.max_dts = None;
+        self.resets += 1;
+    }
+}
+
+// Timestamps are expected to start over after those events
+pub fn is_reset_event(name: &str) -> bool {
+    name == "flush-stop" || name == "segment"
+}
+
+// Check that the PTS or DTS of the buffers pushed by each pad are increasing.
+//
+// On encoded streams, with a DTS, the PTS are not increasing in decoding order
+// if frames are reordered (B-frames). Decreasing PTS are then only reported if
+// the frame would be presented before being decoded.
+#[derive(Debug)]
+pub struct TimestampChecker {
+    timestamp: Timestamp,
+    tolerance: ClockTime,
+    pads: BTreeMap<u32, PadTimestamps>,
+}
+
+impl TimestampChecker {
+    pub fn new(timestamp: Timestamp) -> Self {
+        Self {
+            timestamp,
+            tolerance: ClockTime::MSECOND,
+            pads: BTreeMap::new(),
+        }
+    }
+
+    // Differences with the expected timestamp up to @tolerance are not reported as discontinuities
+    pub fn tolerance(mut self, tolerance: ClockTime) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    pub fn add(&mut self, record: &Record) -> Option<Issue> {
+        match record {
+            Record::Buffer(buffer) => self.add_buffer(buffer),
+            Record::Event(event) if is_reset_event(&event.name) => {
+                if let Some(pad) = self.pads.get_mut(&event.pad_ix) {
+                    pad.reset();
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn add_buffer(&mut self, buffer: &BufferRecord) -> Option<Issue> {
+        let current = self.timestamp.get(buffer)?;
+        let reordering = self.timestamp == Timestamp::Pts && buffer.dts.is_some();
+        let pad = self.pads.entry(buffer.pad_ix).or_default();
+
+        pad.buffers += 1;
+        if let Some(dts) = buffer.dts {
+            pad.max_dts = pad.max_dts.max(Some(dts));
+        }
+
+        let kind = match pad.last {
+            Some(last) if current == last => Some(IssueKind::Duplicate),
+            Some(last) if current < last => {
+                if reordering && pad.max_dts.is_some_and(|dts| current >= dts) {
+                    pad.reordered += 1;
+                    None
+                } else {
+                    Some(IssueKind::Decreasing { previous: last })
+                }
+            }
+            _ => match pad.expected {
+                Some(expected)
+                    if !reordering
+                        && current.nseconds().abs_diff(expected.nseconds())
+                            > self.tolerance.nseconds() =>
+                {
+                    Some(IssueKind::Discontinuity { expected })
+                }
+                _ => None,
+            },
+        };
+
+        match kind {
+            Some(IssueKind::Decreasing { .. }) => pad.decreasing += 1,
+            Some(IssueKind::Duplicate) => pad.duplicates += 1,
+            Some(IssueKind::Discontinuity { .. }) => pad.discontinuities += 1,
+            None => {}
+        }
+
+        pad.last = Some(current);
+        pad.expected = buffer.duration.map(|duration| current + duration);
+
+        kind.map(|kind| Issue {
+            pad_ix: buffer.pad_ix,
+            ts: buffer.ts,
+            timestamp: current,
+            kind,
+        })
+    }
+
+    pub fn pad(&self, ix: u32) -> Option<&PadTimestamps> {
+        self.pads.get(&ix)
+    }
+
+    pub fn pads(&self) -> impl Iterator<Item = (u32, &PadTimestamps)> {
+        self.pads.iter().map(|(ix, pad)| (*ix, pad))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Sample {
+    ts: ClockTime,
+    size: u32,
+    duration: Option<ClockTime>,
+}
+
+// Statistics of the buffers pushed by a pad from @start to @end
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThroughputStats {
+    pub start: ClockTime,
+    pub end: ClockTime,
+    pub buffers: u64,
+    pub bytes: u64,
+    // Sum of the durations of the buffers having one
+    pub duration: ClockTime,
+    pub with_duration: u64,
+    // Mean and standard deviation of the wall-clock interval between two buffers
+    pub mean_interval: Option<ClockTime>,
+    pub jitter: Option<ClockTime>,
+}
+
+impl ThroughputStats {
+    fn new(samples: &[Sample], start: ClockTime, end: ClockTime) -> Self {
+        let intervals: Vec<f64> = samples
+            .windows(2)
+            .map(|w| w[1].ts.saturating_sub(w[0].ts).nseconds() as f64)
+            .collect();
+        let (mean_interval, jitter) = if intervals.is_empty() {
+            (None, None)
+        } else {
+            let n = intervals.len() as f64;
+            let mean = intervals.iter().sum::<f64>() / n;
+            let variance = intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / n;
+            (
+                Some(ClockTime::from_nseconds(mean.round() as u64)),
+                Some(ClockTime::from_nseconds(variance.sqrt().round() as u64)),
+            )
+        };
+
+        Self {
+            start,
+            end,
+            buffers: samples.len() as u64,
+            bytes: samples.iter().map(|s| s.size as u64).sum(),
+            duration: samples.iter().filter_map(|s| s.duration).sum(),
+            with_duration: samples.iter().filter(|s| s.duration.is_some()).count() as u64,
+            mean_interval,
+            jitter,
+        }
+    }
+
+    pub fn span(&self) -> ClockTime {
+        self.end.saturating_sub(self.start)
+    }
+
+    fn per_sec(&self, value: u64) -> f64 {
+        let span = self.span().nseconds();
+        if span == 0 {
+            0.0
+        } else {
+            value as f64 * 1_000_000_000.0 / span as f64
+        }
+    }
+
+    pub fn buffers_per_sec(&self) -> f64 {
+        self.per_sec(self.buffers)
+    }
+
+    pub fn bytes_per_sec(&self) -> f64 {
+        self.per_sec(self.bytes)
+    }
+
+    // In bits per second
+    pub fn bitrate(&self) -> f64 {
+        self.bytes_per_sec() * 8.0
+    }
+
+    pub fn mean_duration(&self) -> Option<ClockTime> {
+        if self.with_duration == 0 {
+            None
+        } else {
+            Some(self.duration / self.with_duration)
+        }
+    }
+
+    // Ratio between the duration of the buffers and the wall-clock time,
+    // lower than 1 if the pad is slower than real time
+    pub fn realtime(&self) -> Option<f64> {
+        let span = self.span().nseconds();
+        if self.with_duration == 0 || span == 0 {
+            None
+        } else {
+            Some(self.duration.nseconds() as f64 / span as f64)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PadThroughput {
+    samples: Vec<Sample>,
+}
+
+impl PadThroughput {
+    pub fn buffers(&self) -> usize {
+        self.samples.len()
+    }
+
+    // The last buffer is considered to last its duration, or the mean interval if it has none
+    fn end(&self, mean_interval: Option<ClockTime>) -> Option<ClockTime> {
+        let last = self.samples.last()?;
+        Some(last.ts + last.duration.or(mean_interval).unwrap_or(ClockTime::ZERO))
+    }
+
+    pub fn total(&self) -> Option<ThroughputStats> {
+        let start = self.samples.first()?.ts;
+        let mut stats = ThroughputStats::new(&self.samples, start, start);
+        stats.end = self.end(stats.mean_interval)?;
+        Some(stats)
+    }
+
+    // Statistics over windows of @window length, starting every @step
+    pub fn windows(&self, window: ClockTime, step: ClockTime) -> Vec<ThroughputStats> {
+        assert!(window > ClockTime::ZERO && step > ClockTime::ZERO);
+
+        let total = match self.total() {
+            Some(total) => total,
+            None => return Vec::new(),
+        };
+
+        let mut windows = Vec::new();
+        let mut start = total.start;
+        while start + window <= total.end {
+            let end = start + window;
+            let first = self.samples.partition_point(|s| s.ts < start);
+            let last = self.samples.partition_point(|s| s.ts < end);
+            windows.push(ThroughputStats::new(&self.samples[first..last], start, end));
+            start += step;
+        }
+
+        windows
+    }
+}
+
+// Throughput of the buffers pushed by each pad
+#[derive(Debug, Default)]
+pub struct Throughput {
+    pads: BTreeMap<u32, PadThroughput>,
+}
+
+impl Throughput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, record: &Record) {
+        if let Record::Buffer(buffer) = record {
+            self.pads
+                .entry(buffer.pad_ix)
+                .or_default()
+                .samples
+                .push(Sample {
+                    ts: buffer.ts,
+                    size: buffer.size,
+                    duration: buffer.duration,
+                });
+        }
+    }
+
+    pub fn pad(&self, ix: u32) -> Option<&PadThroughput> {
+        self.pads.get(&ix)
+    }
+
+    pub fn pads(&self) -> impl Iterator<Item = (u32, &PadThroughput)> {
+        self.pads.iter().map(|(ix, pad)| (*ix, pad))
+    }
+}
+
+// Delta units received after a discontinuity, until the next keyframe
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscontRecovery {
+    pub discont: ClockTime,
+    pub keyframe: Option<ClockTime>,
+    pub delta_units: u64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PadFlags {
+    pub buffers: u64,
+    // ts of the buffers having those flags
+    pub discont: Vec<ClockTime>,
+    pub gap: Vec<ClockTime>,
+    pub corrupted: Vec<ClockTime>,
+    pub delta_units: u64,
+    pub droppable: u64,
+    pub headers: u64,
+    // ts of the buffers which are not delta units, on encoded pads
+    pub keyframes: Vec<ClockTime>,
+    // Number of frames from one keyframe to the next one
+    pub gops: Vec<u64>,
+    pub recoveries: Vec<DiscontRecovery>,
+    // Frames since the last keyframe
+    frames: Option<u64>,
+    recovering: bool,
+}
+
+impl PadFlags {
+    // Only encoded streams have delta units
+    pub fn is_encoded(&self) -> bool {
+        self.delta_units > 0
+    }
+
+    fn add(&mut self, buffer: &BufferRecord) {
+        let flags = buffer.flags;
+        let ts = buffer.ts;
+
+        self.buffers += 1;
+        if flags.contains(BufferFlags::DISCONT) {
+            self.discont.push(ts);
+        }
+        if flags.contains(BufferFlags::GAP) {
+            self.gap.push(ts);
+        }
+        if flags.contains(BufferFlags::CORRUPTED) {
+            self.corrupted.push(ts);
+        }
+        if flags.contains(BufferFlags::DROPPABLE) {
+            self.droppable += 1;
+        }
+        // Codec data are not frames
+        if flags.contains(BufferFlags::HEADER) {
+            self.headers += 1;
+            return;
+        }
+
+        if flags.contains(BufferFlags::DISCONT) {
+            self.recovering = true;
+            self.recoveries.push(DiscontRecovery {
+                discont: ts,
+                keyframe: None,
+                delta_units: 0,
+            });
+        }
+
+        if flags.contains(BufferFlags::DELTA_UNIT) {
+            self.delta_units += 1;
+            if let Some(frames) = self.frames.as_mut() {
+                *frames += 1;
+            }
+            if self.recovering {
+                self.recoveries.last_mut().unwrap().delta_units += 1;
+            }
+        } else {
+            self.keyframes.push(ts);
+            if let Some(frames) = self.frames.replace(1) {
+                self.gops.push(frames);
+            }
+            if self.recovering {
+                self.recoveries.last_mut().unwrap().keyframe = Some(ts);
+                self.recovering = false;
+            }
+        }
+    }
+}
+
+// Flags of the buffers pushed by each pad
+#[derive(Debug, Default)]
+pub struct Flags {
+    pads: BTreeMap<u32, PadFlags>,
+}
+
+impl Flags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, record: &Record) {
+        if let Record::Buffer(buffer) = record {
+            self.pads.entry(buffer.pad_ix).or_default().add(buffer);
+        }
+    }
+
+    pub fn pad(&self, ix: u32) -> Option<&PadFlags> {
+        self.pads.get(&ix)
+    }
+
+    pub fn pads(&self) -> impl Iterator<Item = (u32, &PadFlags)> {
+        self.pads.iter().map(|(ix, pad)| (*ix, pad))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::buffer;
+    use crate::tracer::EventRecord;
+
+    fn event(pad_ix: u32, name: &str) -> Record {
+        Record::Event(EventRecord {
+            ts: ClockTime::ZERO,
+            pad_ix,
+            element_ix: 0,
+            name: name.to_string(),
+        })
+    }
+
+    // Buffers described as (pad, pts, dts, duration), pushed at their pts
+    fn kinds(
+        checker: &mut TimestampChecker,
+        buffers: &[(u32, u64, Option<u64>, Option<u64>)],
+    ) -> Vec<Option<IssueKind>> {
+        let ms = ClockTime::from_mseconds;
+        buffers
+            .iter()
+            .map(|&(pad_ix, pts, dts, duration)| {
+                let record = buffer(ms(pts), pad_ix, 0)
+                    .pts(ms(pts))
+                    .dts(dts.map(ms))
+                    .duration(duration.map(ms))
+                    .record();
+                checker.add(&record).map(|issue| issue.kind)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn timestamps() {
+        let ms = ClockTime::from_mseconds;
+        let mut checker = TimestampChecker::new(Timestamp::Pts);
+        let issues = kinds(
+            &mut checker,
+            &[
+                (0, 0, None, Some(40)),
+                (0, 40, None, Some(40)),
+                // within tolerance
+                (0, 81, None, Some(40)),
+                (0, 200, None, Some(40)),
+                (0, 200, None, None),
+                (0, 100, None, None),
+                // another pad
+                (1, 0, None, None),
+            ],
+        );
+        assert_eq!(
+            issues,
+            vec![
+                None,
+                None,
+                None,
+                Some(IssueKind::Discontinuity { expected: ms(121) }),
+                Some(IssueKind::Duplicate),
+                Some(IssueKind::Decreasing { previous: ms(200) }),
+                None,
+            ]
+        );
+
+        assert!(checker.add(&event(0, "flush-start")).is_none());
+        assert!(checker.add(&event(0, "flush-stop")).is_none());
+        assert_eq!(kinds(&mut checker, &[(0, 0, None, None)]), vec![None]);
+        // not a reset
+        assert!(checker.add(&event(0, "eos")).is_none());
+        assert_eq!(
+            kinds(&mut checker, &[(0, 0, None, None)]),
+            vec![Some(IssueKind::Duplicate)]
+        );
+
+        let pad = checker.pad(0).unwrap();
+        assert_eq!(pad.buffers, 8);
+        assert_eq!(pad.decreasing, 1);
+        assert_eq!(pad.duplicates, 2);
+        assert_eq!(pad.discontinuities, 1);
+        assert_eq!(pad.resets, 1);
+        assert_eq!(pad.issues(), 4);
+        assert_eq!(checker.pads().count(), 2);
+
+        // I0 P3 B1 B2 in decoding order, then a B-frame presented before being decoded
+        let mut checker = TimestampChecker::new(Timestamp::Pts);
+        let issues = kinds(
+            &mut checker,
+            &[
+                (0, 40, Some(0), Some(40)),
+                (0, 160, Some(40), Some(40)),
+                (0, 80, Some(80), Some(40)),
+                (0, 120, Some(120), Some(40)),
+                (0, 100, Some(160), Some(40)),
+            ],
+        );
+        assert_eq!(
+            issues,
+            vec![
+                None,
+                None,
+                None,
+                None,
+                Some(IssueKind::Decreasing { previous: ms(120) })
+            ]
+        );
+        assert_eq!(checker.pad(0).unwrap().reordered, 1);
+
+        // DTS are always checked strictly
+        let mut checker = TimestampChecker::new(Timestamp::Dts).tolerance(ClockTime::ZERO);
+        let issues = kinds(
+            &mut checker,
+            &[
+                (0, 40, Some(0), Some(40)),
+                (0, 160, Some(40), Some(40)),
+                (0, 80, Some(20), Some(40)),
+                (0, 80, Some(61), Some(40)),
+                // no DTS
+                (0, 80, None, Some(40)),
+            ],
+        );
+        assert_eq!(
+            issues,
+            vec![
+                None,
+                None,
+                Some(IssueKind::Decreasing { previous: ms(40) }),
+                Some(IssueKind::Discontinuity { expected: ms(60) }),
+                None,
+            ]
+        );
+        assert_eq!(checker.pad(0).unwrap().buffers, 4);
+    }
+
+    #[test]
+    fn throughput() {
+        let ms = ClockTime::from_mseconds;
+        let mut throughput = Throughput::new();
+        // 25 fps during 400ms, then 12.5 fps
+        for ts in (0..400).step_by(40).chain((440..800).step_by(80)) {
+            throughput.add(&buffer(ms(ts), 0, 0).size(1000).duration(ms(40)).record());
+        }
+        throughput.add(&buffer(ClockTime::ZERO, 1, 0).size(10).record());
+        throughput.add(&event(0, "eos"));
+
+        let pad = throughput.pad(0).unwrap();
+        assert_eq!(pad.buffers(), 15);
+        let total = pad.total().unwrap();
+        assert_eq!(total.span(), ms(800));
+        assert_eq!(total.buffers_per_sec(), 18.75);
+        assert_eq!(total.bytes_per_sec(), 18750.0);
+        assert_eq!(total.bitrate(), 150000.0);
+        assert_eq!(total.mean_duration(), Some(ms(40)));
+        assert_eq!(total.realtime(), Some(0.75));
+        assert!(total.jitter.unwrap() > ms(19));
+
+        let windows = pad.windows(ms(400), ms(400));
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].buffers, 10);
+        assert_eq!(windows[0].buffers_per_sec(), 25.0);
+        assert_eq!(windows[0].realtime(), Some(1.0));
+        assert_eq!(windows[1].start, ms(400));
+        assert_eq!(windows[1].buffers_per_sec(), 12.5);
+        assert_eq!(windows[1].realtime(), Some(0.5));
+        assert_eq!(windows[1].mean_interval, Some(ms(80)));
+        assert_eq!(windows[1].jitter, Some(ClockTime::ZERO));
+        assert_eq!(pad.windows(ms(400), ms(100)).len(), 5);
+        assert!(pad.windows(ms(1000), ms(100)).is_empty());
+
+        // a single buffer without duration
+        let total = throughput.pad(1).unwrap().total().unwrap();
+        assert_eq!(total.span(), ClockTime::ZERO);
+        assert_eq!(total.buffers_per_sec(), 0.0);
+        assert_eq!(total.realtime(), None);
+        assert_eq!(throughput.pads().count(), 2);
+    }
+
+    #[test]
+    fn flags() {
+        let ms = ClockTime::from_mseconds;
+        let delta = BufferFlags::DELTA_UNIT;
+        let discont = BufferFlags::DISCONT;
+        let mut flags = Flags::new();
+        for (ts, f) in [
+            (0, BufferFlags::HEADER),
+            (0, discont),
+            (1, delta),
+            (2, delta | BufferFlags::DROPPABLE),
+            (3, BufferFlags::empty()),
+            (4, delta),
+            // stream resumes on a delta unit
+            (5, discont | delta | BufferFlags::CORRUPTED),
+            (6, delta),
+            (7, BufferFlags::empty()),
+            (8, delta),
+            (9, discont | delta),
+        ] {
+            flags.add(&buffer(ms(ts), 0, 0).flags(f).record());
+        }
+        flags.add(
+            &buffer(ClockTime::ZERO, 1, 0)
+                .flags(BufferFlags::GAP)
+                .record(),
+        );
+
+        let pad = flags.pad(0).unwrap();
+        assert!(pad.is_encoded());
+        assert_eq!(pad.buffers, 11);
+        assert_eq!(pad.headers, 1);
+        assert_eq!(pad.discont, vec![ms(0), ms(5), ms(9)]);
+        assert_eq!(pad.corrupted, vec![ms(5)]);
+        assert_eq!(pad.delta_units, 7);
+        assert_eq!(pad.droppable, 1);
+        assert_eq!(pad.keyframes, vec![ms(0), ms(3), ms(7)]);
+        assert_eq!(pad.gops, vec![3, 4]);
+        assert_eq!(
+            pad.recoveries,
+            vec![
+                DiscontRecovery {
+                    discont: ms(0),
+                    keyframe: Some(ms(0)),
+                    delta_units: 0
+                },
+                DiscontRecovery {
+                    discont: ms(5),
+                    keyframe: Some(ms(7)),
+                    delta_units: 2
+                },
+                DiscontRecovery {
+                    discont: ms(9),
+                    keyframe: None,
+                    delta_units: 1
+                },
+            ]
+        );
+
+        let pad = flags.pad(1).unwrap();
+        assert!(!pad.is_encoded());
+        assert_eq!(pad.gap, vec![ms(0)]);
+        assert_eq!(flags.pads().count(), 2);
+    }
+}
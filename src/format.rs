@@ -0,0 +1,977 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Formats of the lines containing GStreamer log entries.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::DateTime;
+use gst::DebugLevel;
+use gstreamer as gst;
+use lazy_static::lazy_static;
+use regex::Regex;
+use thiserror::Error;
+
+use crate::{
+    parse_datetime, parse_debug_level, parse_time, split_location, strip_colors, Entry, ObjectRef,
+    ParsingError, Token,
+};
+
+pub trait LineFormat {
+    fn name(&self) -> &str;
+
+    // Check if @line is using this format, used to auto-detect the format of logs
+    fn detect(&self, line: &str) -> bool;
+
+    // Parse a line of the log. Formats spreading entries over several lines
+    // return None until the entry is complete.
+    fn parse_line(&mut self, line: &str) -> Option<Result<Entry, ParsingError>>;
+
+    // Called at the end of the log to retrieve the last pending entry, if any
+    fn finish(&mut self) -> Option<Result<Entry, ParsingError>> {
+        None
+    }
+
+    // Formats embedding binary data return true when the previous line
+    // announced a binary field. Its payload is then passed to parse_binary()
+    // instead of reading the next line.
+    fn expects_binary(&self) -> bool {
+        false
+    }
+
+    fn parse_binary(&mut self, _data: &[u8]) -> Option<Result<Entry, ParsingError>> {
+        None
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Format {
+    // Default GStreamer debug output
+    Native,
+    // Android logcat, as produced by the gst-android debug handler
+    Logcat,
+    // journald export format, as produced by 'journalctl -o export'
+    Journald,
+    // syslog-wrapped lines, also produced by 'journalctl -o short-iso'
+    Syslog,
+}
+
+impl Format {
+    pub fn all() -> [Format; 4] {
+        // Formats with the most specific detection first
+        [
+            Format::Journald,
+            Format::Logcat,
+            Format::Syslog,
+            Format::Native,
+        ]
+    }
+
+    pub fn line_format(self) -> Box<dyn LineFormat> {
+        match self {
+            Format::Native => Box::new(NativeFormat),
+            Format::Logcat => Box::new(LogcatFormat),
+            Format::Journald => Box::new(JournaldFormat::new()),
+            Format::Syslog => Box::new(SyslogFormat),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "native" => Ok(Format::Native),
+            "logcat" => Ok(Format::Logcat),
+            "journald" => Ok(Format::Journald),
+            "syslog" => Ok(Format::Syslog),
+            _ => Err(format!("unknown format: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Format::Native => "native",
+            Format::Logcat => "logcat",
+            Format::Journald => "journald",
+            Format::Syslog => "syslog",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+fn mismatch(format: &dyn LineFormat) -> ParsingError {
+    ParsingError::FormatMismatch {
+        format: format.name().to_string(),
+    }
+}
+
+pub struct NativeFormat;
+
+impl LineFormat for NativeFormat {
+    fn name(&self) -> &str {
+        "native"
+    }
+
+    fn detect(&self, line: &str) -> bool {
+        Entry::new(line).is_ok()
+    }
+
+    fn parse_line(&mut self, line: &str) -> Option<Result<Entry, ParsingError>> {
+        Some(Entry::new(line))
+    }
+}
+
+lazy_static! {
+    // threadtime ("02-09 10:11:12.345  1234  5678 I GStreamer+GST_INIT: ..."),
+    // brief ("I/GStreamer+GST_INIT( 1234): ...") and time ("02-09 10:11:12.345 I/GStreamer+GST_INIT( 1234): ...")
+    static ref LOGCAT_RE: Regex = Regex::new(
+        r"^(?:\d\d-\d\d \d\d:\d\d:\d\d\.\d+\s+)?(?:(?P<pid>\d+)\s+\d+\s+(?P<level>[VDIWEF])\s+GStreamer\+(?P<category>\S+?)\s*:|(?P<level2>[VDIWEF])/GStreamer\+(?P<category2>[^(]+?)\(\s*(?P<pid2>\d+)\):) (?P<payload>.*)$"
+    )
+    .unwrap();
+}
+
+pub struct LogcatFormat;
+
+impl LogcatFormat {
+    fn parse_level(s: &str) -> Result<DebugLevel, ParsingError> {
+        // Android has less levels than GStreamer, gst-android maps all levels
+        // more verbose than DEBUG to VERBOSE.
+        match s {
+            "E" | "F" => Ok(DebugLevel::Error),
+            "W" => Ok(DebugLevel::Warning),
+            "I" => Ok(DebugLevel::Info),
+            "D" => Ok(DebugLevel::Debug),
+            "V" => Ok(DebugLevel::Log),
+            _ => Err(ParsingError::InvalidDebugLevel {
+                name: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl LineFormat for LogcatFormat {
+    fn name(&self) -> &str {
+        "logcat"
+    }
+
+    fn detect(&self, line: &str) -> bool {
+        LOGCAT_RE.is_match(line)
+    }
+
+    fn parse_line(&mut self, line: &str) -> Option<Result<Entry, ParsingError>> {
+        let len = line.len();
+        let caps = match LOGCAT_RE.captures(line) {
+            Some(caps) => caps,
+            None => return Some(Err(mismatch(self))),
+        };
+        let get = |a: &str, b: &str| caps.name(a).or_else(|| caps.name(b)).unwrap().as_str();
+
+        let res = (|| {
+            let level = Self::parse_level(get("level", "level2"))?;
+            let category = get("category", "category2").to_string();
+            let pid_str = get("pid", "pid2");
+            let pid = pid_str.parse().map_err(|_e| ParsingError::InvalidPID {
+                pid: pid_str.to_string(),
+            })?;
+
+            // "<ts> <thread> <file>:<line>:<function>[:<object>] <message>"
+            let mut it = caps["payload"].splitn(4, ' ');
+            let ts = parse_time(it.next().ok_or(ParsingError::MissingToken {
+                t: Token::Timestamp { field: None },
+            })?)?;
+            let thread = it
+                .next()
+                .ok_or(ParsingError::MissingToken { t: Token::Thread })?
+                .to_string();
+            let location = it.next().ok_or(ParsingError::MissingLocation)?;
+            // the object part is omitted, with its separator, if there is no object
+            let (file, line, function, object) = if location.matches(':').count() == 2 {
+                split_location(&format!("{}:", location))?
+            } else {
+                split_location(location)?
+            };
+            let message = it.next().unwrap_or_default().to_string();
+
+            Ok(Entry {
+                ts,
+                datetime: None,
+                pid,
+                thread,
+                level,
+                category,
+                file,
+                line,
+                function,
+                message,
+                object,
+                len,
+            })
+        })();
+
+        Some(res)
+    }
+}
+
+pub struct JournaldFormat {
+    message: Option<String>,
+    pid: Option<String>,
+    realtime: Option<String>,
+    // name of the binary field whose payload is expected next
+    binary: Option<String>,
+}
+
+impl JournaldFormat {
+    pub fn new() -> Self {
+        Self {
+            message: None,
+            pid: None,
+            realtime: None,
+            binary: None,
+        }
+    }
+
+    fn set_field(&mut self, key: &str, value: &str) {
+        match key {
+            "MESSAGE" => self.message = Some(value.to_string()),
+            "_PID" => self.pid = Some(value.to_string()),
+            "__REALTIME_TIMESTAMP" => self.realtime = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    fn entry(&mut self) -> Option<Result<Entry, ParsingError>> {
+        let pid = self.pid.take();
+        let realtime = self.realtime.take();
+        let message = self.message.take()?;
+
+        let res = Entry::new(&message).map(|mut entry| {
+            if let Some(pid) = pid.and_then(|pid| pid.parse().ok()) {
+                entry.pid = pid;
+            }
+            if entry.datetime.is_none() {
+                // microseconds since the epoch
+                entry.datetime = realtime
+                    .and_then(|t| t.parse::<i64>().ok())
+                    .and_then(DateTime::from_timestamp_micros)
+                    .map(|dt| dt.fixed_offset());
+            }
+            entry
+        });
+
+        Some(res)
+    }
+}
+
+impl Default for JournaldFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LineFormat for JournaldFormat {
+    fn name(&self) -> &str {
+        "journald"
+    }
+
+    fn detect(&self, line: &str) -> bool {
+        line.starts_with("__CURSOR=") || line.starts_with("__REALTIME_TIMESTAMP=")
+    }
+
+    fn parse_line(&mut self, line: &str) -> Option<Result<Entry, ParsingError>> {
+        // Entries are separated by empty lines
+        if line.is_empty() {
+            return self.entry();
+        }
+
+        // Fields containing control characters, such as colored messages,
+        // are serialized in binary form: their name alone on a line.
+        match line.split_once('=') {
+            Some((key, value)) => self.set_field(key, value),
+            None => self.binary = Some(line.to_string()),
+        }
+
+        None
+    }
+
+    fn expects_binary(&self) -> bool {
+        self.binary.is_some()
+    }
+
+    fn parse_binary(&mut self, data: &[u8]) -> Option<Result<Entry, ParsingError>> {
+        if let Some(key) = self.binary.take() {
+            self.set_field(&key, &String::from_utf8_lossy(data));
+        }
+
+        None
+    }
+
+    fn finish(&mut self) -> Option<Result<Entry, ParsingError>> {
+        self.entry()
+    }
+}
+
+lazy_static! {
+    // "Feb  9 10:11:12 host prog[1234]: ..." or "2024-02-09T10:11:12+0100 host prog[1234]: ..."
+    static ref SYSLOG_RE: Regex = Regex::new(
+        r"^(?:<\d+>)?(?:(?P<iso>\d{4}-\d\d-\d\dT\S+)|[A-Z][a-z]{2} [ \d]\d \d\d:\d\d:\d\d(?:\.\d+)?) \S+ [^:\[\s]+(?:\[\d+\])?: (?P<message>.*)$"
+    )
+    .unwrap();
+}
+
+pub struct SyslogFormat;
+
+impl LineFormat for SyslogFormat {
+    fn name(&self) -> &str {
+        "syslog"
+    }
+
+    fn detect(&self, line: &str) -> bool {
+        SYSLOG_RE.is_match(line)
+    }
+
+    fn parse_line(&mut self, line: &str) -> Option<Result<Entry, ParsingError>> {
+        let caps = match SYSLOG_RE.captures(line) {
+            Some(caps) => caps,
+            None => return Some(Err(mismatch(self))),
+        };
+
+        let res = Entry::new(&caps["message"]).map(|mut entry| {
+            // Only ISO dates contain the year
+            if entry.datetime.is_none() {
+                entry.datetime = caps.name("iso").and_then(|s| parse_datetime(s.as_str()));
+            }
+            entry
+        });
+
+        Some(res)
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TemplateError {
+    #[error("unknown field: {}", name)]
+    UnknownField { name: String },
+    #[error("unterminated field starting at position {}", pos)]
+    UnterminatedField { pos: usize },
+    #[error("field {:?} is used more than once", t)]
+    DuplicateField { t: Token },
+    #[error("fields {:?} and {:?} are not separated", a, b)]
+    AdjacentFields { a: Token, b: Token },
+    #[error("missing field: {:?}", t)]
+    MissingField { t: Token },
+}
+
+#[derive(Debug)]
+enum Part {
+    Literal(String),
+    Field(Token),
+    // "<{object}>", omitted with its brackets if the entry has no object
+    BracketedObject,
+}
+
+fn field_token(name: &str) -> Option<Token> {
+    match name {
+        "ts" => Some(Token::Timestamp { field: None }),
+        "pid" => Some(Token::PID),
+        "thread" => Some(Token::Thread),
+        "level" => Some(Token::Level),
+        "category" => Some(Token::Category),
+        "file" => Some(Token::File),
+        "line" => Some(Token::LineNumber),
+        "function" => Some(Token::Function),
+        "message" => Some(Token::Message),
+        "object" => Some(Token::Object),
+        _ => None,
+    }
+}
+
+// Return the length of the start of @s matching @literal, spaces in
+// literals matching any number of spaces as GStreamer pads its columns.
+fn match_literal(literal: &str, s: &str) -> Option<usize> {
+    let mut pos = 0;
+
+    for c in literal.chars() {
+        if c == ' ' {
+            let n = s[pos..].len() - s[pos..].trim_start_matches(' ').len();
+            if n == 0 {
+                return None;
+            }
+            pos += n;
+        } else if s[pos..].starts_with(c) {
+            pos += c.len_utf8();
+        } else {
+            return None;
+        }
+    }
+
+    Some(pos)
+}
+
+// Format described by a template such as
+// "{ts} {level} {category} {file}:{line}:{function}:<{object}> {message}",
+// used to parse logs from custom log functions.
+// Only the 'ts' field is mandatory, missing fields are left empty.
+#[derive(Debug)]
+pub struct TemplateFormat {
+    template: String,
+    parts: Vec<Part>,
+}
+
+impl TemplateFormat {
+    pub fn new(template: &str) -> Result<Self, TemplateError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.char_indices();
+
+        while let Some((pos, c)) = chars.next() {
+            if c != '{' {
+                // consecutive spaces are matched by a single one anyway
+                if c != ' ' || !literal.ends_with(' ') {
+                    literal.push(c);
+                }
+                continue;
+            }
+
+            let mut name = String::new();
+            loop {
+                match chars.next() {
+                    None => return Err(TemplateError::UnterminatedField { pos }),
+                    Some((_, '}')) => break,
+                    Some((_, c)) => name.push(c),
+                }
+            }
+
+            let t = field_token(&name).ok_or(TemplateError::UnknownField { name })?;
+            if parts.iter().any(|p| matches!(p, Part::Field(f) if *f == t)) {
+                return Err(TemplateError::DuplicateField { t });
+            }
+
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(Part::Field(t));
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        // Detect objects surrounded by brackets
+        let object = parts
+            .iter()
+            .position(|p| matches!(p, Part::Field(Token::Object)));
+        if let Some(i) = object {
+            if let (Some(Part::Literal(before)), Some(Part::Literal(after))) =
+                (parts.get(i.wrapping_sub(1)), parts.get(i + 1))
+            {
+                if before.ends_with('<') && after.starts_with('>') {
+                    let before = before[..before.len() - 1].to_string();
+                    let after = after[1..].to_string();
+                    parts.splice(
+                        i - 1..=i + 1,
+                        vec![
+                            Part::Literal(before),
+                            Part::BracketedObject,
+                            Part::Literal(after),
+                        ],
+                    );
+                    parts.retain(|p| !matches!(p, Part::Literal(l) if l.is_empty()));
+                }
+            }
+        }
+
+        for w in parts.windows(2) {
+            let token = |p: &Part| match p {
+                Part::Field(t) => Some(t.clone()),
+                Part::BracketedObject => Some(Token::Object),
+                Part::Literal(_) => None,
+            };
+            if let (Some(a), Some(b)) = (token(&w[0]), token(&w[1])) {
+                return Err(TemplateError::AdjacentFields { a, b });
+            }
+        }
+
+        if !parts
+            .iter()
+            .any(|p| matches!(p, Part::Field(Token::Timestamp { .. })))
+        {
+            return Err(TemplateError::MissingField {
+                t: Token::Timestamp { field: None },
+            });
+        }
+
+        Ok(Self {
+            template: template.to_string(),
+            parts,
+        })
+    }
+
+    // The token of the first field from @i
+    fn next_token(&self, i: usize) -> Token {
+        self.parts[i..]
+            .iter()
+            .find_map(|p| match p {
+                Part::Field(t) => Some(t.clone()),
+                Part::BracketedObject => Some(Token::Object),
+                Part::Literal(_) => None,
+            })
+            .unwrap_or(Token::Message)
+    }
+
+    fn parse(&self, line: &str) -> Result<Entry, ParsingError> {
+        let len = line.len();
+        let line = strip_colors(line);
+        let mut rest: &str = &line;
+
+        let mut entry = Entry {
+            ts: gst::ClockTime::ZERO,
+            datetime: None,
+            pid: 0,
+            thread: String::new(),
+            level: DebugLevel::None,
+            category: String::new(),
+            file: String::new(),
+            line: 0,
+            function: String::new(),
+            message: String::new(),
+            object: None,
+            len,
+        };
+
+        for (i, part) in self.parts.iter().enumerate() {
+            match part {
+                Part::Literal(literal) => {
+                    let n =
+                        match_literal(literal, rest).ok_or_else(|| ParsingError::MissingToken {
+                            t: self.next_token(i),
+                        })?;
+                    rest = &rest[n..];
+                }
+                Part::BracketedObject => {
+                    if let Some(obj) = rest.strip_prefix('<') {
+                        let end = obj
+                            .find('>')
+                            .ok_or(ParsingError::MissingToken { t: Token::Object })?;
+                        entry.object = Some(ObjectRef::from(&obj[..end]));
+                        rest = &obj[end + 1..];
+                    }
+                }
+                Part::Field(t) => {
+                    if rest.is_empty() && *t != Token::Message {
+                        return Err(ParsingError::MissingToken { t: t.clone() });
+                    }
+
+                    let value = match self.parts.get(i + 1) {
+                        Some(Part::Literal(literal)) => {
+                            let end = rest
+                                .char_indices()
+                                .map(|(p, _)| p)
+                                .find(|p| match_literal(literal, &rest[*p..]).is_some())
+                                .ok_or_else(|| ParsingError::MissingToken {
+                                    t: self.next_token(i + 1),
+                                })?;
+                            let (value, r) = rest.split_at(end);
+                            rest = r;
+                            value
+                        }
+                        _ => std::mem::take(&mut rest),
+                    };
+
+                    self.set_field(&mut entry, t, value)?;
+                }
+            }
+        }
+
+        Ok(entry)
+    }
+
+    fn set_field(&self, entry: &mut Entry, t: &Token, value: &str) -> Result<(), ParsingError> {
+        if value.is_empty() && *t != Token::Message && *t != Token::Object {
+            return Err(ParsingError::MissingToken { t: t.clone() });
+        }
+
+        match t {
+            Token::Timestamp { .. } => entry.ts = parse_time(value)?,
+            Token::PID => {
+                entry.pid = value.parse().map_err(|_e| ParsingError::InvalidPID {
+                    pid: value.to_string(),
+                })?
+            }
+            Token::Thread => entry.thread = value.to_string(),
+            Token::Level => entry.level = parse_debug_level(value)?,
+            Token::Category => entry.category = value.to_string(),
+            Token::File => entry.file = value.to_string(),
+            Token::LineNumber => {
+                entry.line = value
+                    .parse()
+                    .map_err(|_e| ParsingError::InvalidLineNumber {
+                        line: value.to_string(),
+                    })?
+            }
+            Token::Function => entry.function = value.to_string(),
+            Token::Message => entry.message = value.to_string(),
+            Token::Object => {
+                let object = value.trim_start_matches('<').trim_end_matches('>');
+                if !object.is_empty() {
+                    entry.object = Some(ObjectRef::from(object));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for TemplateFormat {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TemplateFormat::new(s)
+    }
+}
+
+impl LineFormat for TemplateFormat {
+    fn name(&self) -> &str {
+        &self.template
+    }
+
+    fn detect(&self, line: &str) -> bool {
+        self.parse(line).is_ok()
+    }
+
+    fn parse_line(&mut self, line: &str) -> Option<Result<Entry, ParsingError>> {
+        Some(self.parse(line))
+    }
+}
+
+// Pick the first format from @formats detecting one of @lines
+pub fn detect_format<'a>(
+    lines: impl IntoIterator<Item = &'a str>,
+    formats: Vec<Box<dyn LineFormat>>,
+) -> Option<Box<dyn LineFormat>> {
+    let mut formats: Vec<Option<Box<dyn LineFormat>>> = formats.into_iter().map(Some).collect();
+
+    for line in lines {
+        if let Some(format) = formats
+            .iter_mut()
+            .find(|f| f.as_ref().is_some_and(|f| f.detect(line)))
+        {
+            return format.take();
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_auto, parse_with_format};
+    use gst::ClockTime;
+
+    #[test]
+    fn logcat() {
+        let mut format = LogcatFormat;
+
+        let line = "02-09 10:11:12.345  1234  5678 I GStreamer+GST_INIT: 0:00:00.007773544 0x7f8c00bd90 gst.c:510:init_pre Initializing GStreamer";
+        assert!(format.detect(line));
+        let entry = format.parse_line(line).unwrap().unwrap();
+        assert_eq!(entry.ts.nseconds(), 7773544);
+        assert_eq!(entry.pid, 1234);
+        assert_eq!(entry.thread, "0x7f8c00bd90");
+        assert_eq!(entry.level, DebugLevel::Info);
+        assert_eq!(entry.category, "GST_INIT");
+        assert_eq!(entry.file, "gst.c");
+        assert_eq!(entry.line, 510);
+        assert_eq!(entry.function, "init_pre");
+        assert_eq!(entry.object, None);
+        assert_eq!(entry.message, "Initializing GStreamer");
+
+        let line = "V/GStreamer+GST_REFCOUNTING( 1234): 0:00:01.000000000 0x7f8c00bd90 gstobject.c:249:gst_object_ref:<allocatorsysmem0> 0x55895101d040 ref 1->2";
+        assert!(format.detect(line));
+        let entry = format.parse_line(line).unwrap().unwrap();
+        assert_eq!(entry.ts, ClockTime::from_seconds(1));
+        assert_eq!(entry.pid, 1234);
+        assert_eq!(entry.level, DebugLevel::Log);
+        assert_eq!(entry.category, "GST_REFCOUNTING");
+        assert_eq!(entry.function, "gst_object_ref");
+        assert_eq!(entry.object, Some(ObjectRef::from("allocatorsysmem0")));
+        assert_eq!(entry.message, "0x55895101d040 ref 1->2");
+
+        let line = "I/ActivityManager( 1234): Start proc";
+        assert!(!format.detect(line));
+        assert_eq!(
+            format.parse_line(line).unwrap().unwrap_err(),
+            ParsingError::FormatMismatch {
+                format: "logcat".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn journald() {
+        let mut format = JournaldFormat::new();
+        let record = "__CURSOR=s=739ad463348b4ceca5a9e69c95a3c93f;i=4ece7;b=6c7c6013a8194ab9b06d0c83d8b5b0d0
+__REALTIME_TIMESTAMP=1707473472123456
+__MONOTONIC_TIMESTAMP=413741
+_PID=8874
+_COMM=gst-launch-1.0
+MESSAGE=0:00:00.007773544  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Initializing GStreamer
+
+__CURSOR=s=739ad463348b4ceca5a9e69c95a3c93f;i=4ece8
+_PID=8874
+MESSAGE=0:00:01.007927372  8874 0x558951015c00 DEBUG             GST_MEMORY gstallocator.c:592:_priv_gst_allocator_initialize: memory alignment: 7";
+
+        assert!(format.detect(record.lines().next().unwrap()));
+
+        let mut entries = Vec::new();
+        for line in record.lines() {
+            if let Some(entry) = format.parse_line(line) {
+                entries.push(entry.unwrap());
+            }
+        }
+        assert_eq!(entries.len(), 1);
+        entries.push(format.finish().unwrap().unwrap());
+        assert!(format.finish().is_none());
+
+        assert_eq!(entries[0].ts.nseconds(), 7773544);
+        assert_eq!(entries[0].pid, 8874);
+        assert_eq!(entries[0].message, "Initializing GStreamer");
+        assert_eq!(
+            entries[0].datetime.unwrap().to_rfc3339(),
+            "2024-02-09T10:11:12.123456+00:00"
+        );
+        assert_eq!(entries[1].category, "GST_MEMORY");
+        assert_eq!(entries[1].datetime, None);
+    }
+
+    #[test]
+    fn journald_binary() {
+        let message = "0:00:00.000208614 \x1b[336m17267\x1b[00m      0x2192200 \x1b[36mINFO   \x1b[00m \x1b[00;01;31m            GST_INIT gst.c:584:init_pre:\x1b[00m Initializing GStreamer Core Library version 1.13.0.1";
+
+        let mut log =
+            b"__CURSOR=s=739ad463348b4ceca5a9e69c95a3c93f;i=4ece7\n_PID=17267\nMESSAGE\n".to_vec();
+        log.extend_from_slice(&(message.len() as u64).to_le_bytes());
+        log.extend_from_slice(message.as_bytes());
+        log.extend_from_slice(b"\n_COMM=gst-launch-1.0\n\n__CURSOR=s=739ad463348b4ceca5a9e69c95a3c93f;i=4ece8\nMESSAGE=0:00:01.007927372  8874 0x558951015c00 DEBUG             GST_MEMORY gstallocator.c:592:_priv_gst_allocator_initialize: memory alignment: 7\n");
+
+        let mut parsed = parse_auto(log.as_slice());
+        assert_eq!(parsed.format_name(), "journald");
+        let results: Vec<_> = parsed.results().collect();
+        assert_eq!(results.len(), 2);
+
+        let entry = results[0].as_ref().unwrap();
+        assert_eq!(entry.pid, 17267);
+        assert_eq!(entry.category, "GST_INIT");
+        assert_eq!(entry.function, "init_pre");
+        assert_eq!(
+            entry.message,
+            "Initializing GStreamer Core Library version 1.13.0.1"
+        );
+        assert_eq!(results[1].as_ref().unwrap().category, "GST_MEMORY");
+    }
+
+    #[test]
+    fn syslog() {
+        let mut format = SyslogFormat;
+
+        let line = "Feb  9 10:11:12 cass-x230 gst-launch-1.0[8874]: 0:00:00.007773544  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Initializing GStreamer";
+        assert!(format.detect(line));
+        let entry = format.parse_line(line).unwrap().unwrap();
+        assert_eq!(entry.ts.nseconds(), 7773544);
+        assert_eq!(entry.category, "GST_INIT");
+        assert_eq!(entry.datetime, None);
+
+        let line = "2024-02-09T10:11:12+0100 cass-x230 gst-launch-1.0[8874]: 0:00:00.007773544  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Initializing GStreamer";
+        assert!(format.detect(line));
+        let entry = format.parse_line(line).unwrap().unwrap();
+        assert_eq!(entry.pid, 8874);
+        assert_eq!(entry.message, "Initializing GStreamer");
+        assert_eq!(
+            entry.datetime.unwrap().to_rfc3339(),
+            "2024-02-09T10:11:12+01:00"
+        );
+
+        assert!(!format.detect(
+            "0:00:00.007773544  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Init"
+        ));
+    }
+
+    const NATIVE: &str =
+        "{ts} {pid} {thread} {level} {category} {file}:{line}:{function}:<{object}> {message}";
+
+    #[test]
+    fn template_native() {
+        for log in &["nocolor.log", "color.log", "corrupted-nocolor.log"] {
+            let path = format!("test-logs/{}", log);
+            let native: Vec<_> = crate::parse(std::fs::File::open(&path).unwrap()).collect();
+            let format = Box::new(TemplateFormat::new(NATIVE).unwrap());
+            let template: Vec<_> =
+                parse_with_format(std::fs::File::open(&path).unwrap(), format).collect();
+
+            assert_eq!(native.len(), template.len());
+            for (a, b) in native.iter().zip(template.iter()) {
+                assert_eq!(a.to_string(), b.to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn template_custom() {
+        let mut format =
+            TemplateFormat::new("[{level}] {ts} {category} - {function}@{file}:{line} {message}")
+                .unwrap();
+
+        let entry = format
+            .parse_line("[WARN] 0:00:00.007773544 GST_INIT - init_pre@gst.c:510 Some warning")
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.ts.nseconds(), 7773544);
+        assert_eq!(entry.level, DebugLevel::Warning);
+        assert_eq!(entry.category, "GST_INIT");
+        assert_eq!(entry.function, "init_pre");
+        assert_eq!(entry.file, "gst.c");
+        assert_eq!(entry.line, 510);
+        assert_eq!(entry.message, "Some warning");
+        assert_eq!(entry.pid, 0);
+        assert_eq!(entry.object, None);
+
+        let mut format = TemplateFormat::new("{ts}|{object}|{message}").unwrap();
+        let entry = format
+            .parse_line("0:00:01.000000000|v4l2src0:src|pushing")
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.object, Some(ObjectRef::from("v4l2src0:src")));
+        let entry = format
+            .parse_line("0:00:01.000000000||pushing")
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.object, None);
+    }
+
+    #[test]
+    fn template_parsing_errors() {
+        let mut format = TemplateFormat::new(NATIVE).unwrap();
+        let mut parse = |line| format.parse_line(line).unwrap().unwrap_err();
+
+        assert_eq!(
+            parse("0:00:00.007773544 "),
+            ParsingError::MissingToken { t: Token::PID }
+        );
+        assert_eq!(
+            parse(
+                "0:00:00.007773544  8fuz874 0x558951015c00 INFO GST_INIT gst.c:510:init_pre: Init"
+            ),
+            ParsingError::InvalidPID {
+                pid: "8fuz874".to_string()
+            }
+        );
+        assert_eq!(
+            parse("0:00:00.007773544  8874 0x558951015c00 INFO GST_INIT gst.c"),
+            ParsingError::MissingToken {
+                t: Token::LineNumber
+            }
+        );
+        assert_eq!(
+            parse("0:00:00.007773544  8874 0x558951015c00 INFO GST_INIT gst.c:fuzz:init_pre: Init"),
+            ParsingError::InvalidLineNumber {
+                line: "fuzz".to_string()
+            }
+        );
+        assert_eq!(
+            parse("0:00:00.007773544  8874 0x558951015c00 FUZZ GST_INIT gst.c:510:init_pre: Init"),
+            ParsingError::InvalidDebugLevel {
+                name: "FUZZ".to_string()
+            }
+        );
+        assert_eq!(
+            parse(
+                "0:00:00.007773544  8874 0x558951015c00 INFO GST_INIT gst.c:510:init_pre:<obj Init"
+            ),
+            ParsingError::MissingToken { t: Token::Object }
+        );
+    }
+
+    #[test]
+    fn template_errors() {
+        assert_eq!(
+            TemplateFormat::new("{ts} {foo}").unwrap_err(),
+            TemplateError::UnknownField {
+                name: "foo".to_string()
+            }
+        );
+        assert_eq!(
+            TemplateFormat::new("{ts} {level").unwrap_err(),
+            TemplateError::UnterminatedField { pos: 5 }
+        );
+        assert_eq!(
+            TemplateFormat::new("{ts} {level} {level}").unwrap_err(),
+            TemplateError::DuplicateField { t: Token::Level }
+        );
+        assert_eq!(
+            TemplateFormat::new("{ts}{level}").unwrap_err(),
+            TemplateError::AdjacentFields {
+                a: Token::Timestamp { field: None },
+                b: Token::Level
+            }
+        );
+        assert_eq!(
+            TemplateFormat::new("{level} {message}").unwrap_err(),
+            TemplateError::MissingField {
+                t: Token::Timestamp { field: None }
+            }
+        );
+    }
+
+    #[test]
+    fn detect_template() {
+        let input = "[WARN] 0:00:00.007773544 GST_INIT - init_pre@gst.c:510 Some warning\n";
+        let mut formats: Vec<Box<dyn LineFormat>> = vec![Box::new(
+            TemplateFormat::new("[{level}] {ts} {category} - {function}@{file}:{line} {message}")
+                .unwrap(),
+        )];
+        formats.extend(Format::all().iter().map(|f| f.line_format()));
+
+        let mut parsed = crate::parse_auto_with(input.as_bytes(), formats);
+        assert_eq!(
+            parsed.format_name(),
+            "[{level}] {ts} {category} - {function}@{file}:{line} {message}"
+        );
+        assert_eq!(parsed.next().unwrap().category, "GST_INIT");
+    }
+
+    #[test]
+    fn detect() {
+        let lines = [
+            "Hello world, I am here to fuzz",
+            "I/GStreamer+GST_INIT( 1234): 0:00:00.007773544 0x7f8c00bd90 gst.c:510:init_pre Init",
+        ];
+        let formats = Format::all().iter().map(|f| f.line_format()).collect();
+        let format = detect_format(lines.iter().copied(), formats).unwrap();
+        assert_eq!(format.name(), "logcat");
+
+        let formats = vec![Format::Syslog.line_format()];
+        assert!(detect_format(lines.iter().copied(), formats).is_none());
+    }
+
+    #[test]
+    fn parse_files() {
+        let f = std::fs::File::open("test-logs/corrupted-nocolor.log").unwrap();
+        assert_eq!(parse_auto(f).count(), 6);
+
+        let f = std::fs::File::open("test-logs/color.log").unwrap();
+        assert_eq!(
+            parse_with_format(f, Format::Native.line_format()).count(),
+            15
+        );
+    }
+}
@@ -0,0 +1,330 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Tracking of frames through a pipeline, using the 'buffer' records of the 'stats' tracer.
+//!
+//! Frames are identified by their PTS: a buffer pushed by an element is
+//! considered to be the frame it received earlier with the same PTS.
+//! Elements pushing buffers with a PTS they did not receive, or already pushed
+//! on the same pad, are the source of new frames.
+
+use std::collections::{BTreeMap, HashMap};
+
+use gst::ClockTime;
+use gstreamer as gst;
+
+use crate::flow::is_reset_event;
+use crate::tracer::Record;
+
+// A frame going through an element
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hop {
+    pub element_ix: u32,
+    // Sink pad of the element receiving the frame
+    pub pad_ix: u32,
+    pub enter: ClockTime,
+    pub leave: Option<ClockTime>,
+    // Order in which the element received its frames
+    seq: u64,
+}
+
+impl Hop {
+    pub fn residence(&self) -> Option<ClockTime> {
+        self.leave.map(|leave| leave.saturating_sub(self.enter))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub pts: ClockTime,
+    pub source: u32,
+    // ts when the frame has been pushed by its source
+    pub start: ClockTime,
+    pub hops: Vec<Hop>,
+}
+
+// Frames pushed by an element from its last buffer, and the src pads it has been pushed on
+#[derive(Debug)]
+struct Pushed {
+    pts: ClockTime,
+    frames: Vec<usize>,
+    pads: Vec<u32>,
+}
+
+#[derive(Debug, Default)]
+struct ElementFrames {
+    // Frames received for each PTS and not pushed yet: (frame, hop)
+    received: HashMap<ClockTime, Vec<(usize, usize)>>,
+    // Pushed again on other src pads by elements such as tee
+    last_pushed: Option<Pushed>,
+    n_received: u64,
+    pushed: bool,
+    // Most recently received frame which has been pushed
+    last_pushed_seq: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct FrameTracker {
+    frames: Vec<Frame>,
+    elements: HashMap<u32, ElementFrames>,
+    // Element receiving what is pushed by each src pad
+    peers: HashMap<u32, u32>,
+}
+
+impl FrameTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, record: &Record) {
+        let buffer = match record {
+            Record::Buffer(buffer) => buffer,
+            Record::Event(event) if is_reset_event(&event.name) => {
+                // Frames received before a flush or a new segment are not pushed anymore
+                let peer = self.peers.get(&event.pad_ix).copied();
+                if let Some(element) = peer.and_then(|peer| self.elements.get_mut(&peer)) {
+                    element.received.clear();
+                    element.last_pushed = None;
+                }
+                return;
+            }
+            _ => return,
+        };
+        let pts = match buffer.pts {
+            Some(pts) => pts,
+            None => return,
+        };
+
+        let Self {
+            frames: all_frames,
+            elements,
+            peers,
+        } = self;
+
+        let element = elements.entry(buffer.element_ix).or_default();
+        element.pushed = true;
+
+        let mut frames = Vec::new();
+        if let Some(received) = element.received.remove(&pts) {
+            for (frame, hop) in received {
+                let hop = &mut all_frames[frame].hops[hop];
+                hop.leave = Some(buffer.ts);
+                element.last_pushed_seq = element.last_pushed_seq.max(Some(hop.seq));
+                frames.push(frame);
+            }
+            element.last_pushed = Some(Pushed {
+                pts,
+                frames: frames.clone(),
+                pads: vec![buffer.pad_ix],
+            });
+        } else if let Some(pushed) = element
+            .last_pushed
+            .as_mut()
+            .filter(|p| p.pts == pts && !p.pads.contains(&buffer.pad_ix))
+        {
+            // Already pushed, by another src pad (tee)
+            pushed.pads.push(buffer.pad_ix);
+            frames.extend_from_slice(&pushed.frames);
+        } else {
+            element.last_pushed = None;
+            frames.push(all_frames.len());
+            all_frames.push(Frame {
+                pts,
+                source: buffer.element_ix,
+                start: buffer.ts,
+                hops: Vec::new(),
+            });
+        }
+
+        // Buffer pushed to an unlinked pad
+        if buffer.peer_element_ix == u32::MAX {
+            return;
+        }
+        peers.insert(buffer.pad_ix, buffer.peer_element_ix);
+
+        let peer = elements.entry(buffer.peer_element_ix).or_default();
+        for frame in frames {
+            let hops = &mut all_frames[frame].hops;
+            hops.push(Hop {
+                element_ix: buffer.peer_element_ix,
+                pad_ix: buffer.peer_pad_ix,
+                enter: buffer.ts,
+                leave: None,
+                seq: peer.n_received,
+            });
+            peer.received
+                .entry(pts)
+                .or_default()
+                .push((frame, hops.len() - 1));
+        }
+        peer.n_received += 1;
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    // Elements receiving buffers without pushing any
+    pub fn is_sink(&self, element_ix: u32) -> bool {
+        self.elements
+            .get(&element_ix)
+            .is_some_and(|e| e.n_received > 0 && !e.pushed)
+    }
+
+    // Time from the source of the frame to the last sink it reached
+    pub fn latency(&self, frame: &Frame) -> Option<ClockTime> {
+        frame
+            .hops
+            .iter()
+            .filter(|hop| self.is_sink(hop.element_ix))
+            .map(|hop| hop.enter.saturating_sub(frame.start))
+            .max()
+    }
+
+    // Element which dropped the frame: it never pushed it but pushed frames it received after it.
+    // Frames being processed when the log ends are not considered as dropped.
+    pub fn dropped(&self, frame: &Frame) -> Option<u32> {
+        frame
+            .hops
+            .iter()
+            .find(|hop| {
+                hop.leave.is_none()
+                    && self
+                        .elements
+                        .get(&hop.element_ix)
+                        .and_then(|e| e.last_pushed_seq)
+                        .is_some_and(|seq| seq > hop.seq)
+            })
+            .map(|hop| hop.element_ix)
+    }
+
+    // Time spent by frames in each element
+    pub fn residence_times(&self) -> BTreeMap<u32, Vec<ClockTime>> {
+        let mut times: BTreeMap<u32, Vec<ClockTime>> = BTreeMap::new();
+        for hop in self.frames.iter().flat_map(|f| f.hops.iter()) {
+            if let Some(residence) = hop.residence() {
+                times.entry(hop.element_ix).or_default().push(residence);
+            }
+        }
+        times
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{buffer, event};
+
+    #[test]
+    fn frames() {
+        let ms = ClockTime::from_mseconds;
+        let mut tracker = FrameTracker::new();
+        // src (0) -> dec (1) -> tee (2) -> sink (3)
+        //                              -> sink (4)
+        // (element, src pad, peer element, ts, pts), the n-th src pad of an element being
+        // 'element * 10 + n' and its sink pad 'element * 10'
+        for (element_ix, pad, peer_element_ix, ts, pts) in [
+            (0, 1, 1, 0, 0),
+            (0, 1, 1, 10, 40),
+            (1, 1, 2, 15, 0),
+            (2, 1, 3, 16, 0),
+            (2, 2, 4, 17, 0),
+            // dropped by dec
+            (0, 1, 1, 20, 80),
+            (1, 1, 2, 30, 40),
+            (2, 1, 3, 31, 40),
+            (2, 2, 4, 32, 40),
+            (0, 1, 1, 40, 120),
+            (1, 1, 2, 45, 120),
+            // still being decoded
+            (0, 1, 1, 50, 160),
+            // unlinked pad
+            (3, 1, u32::MAX, 50, 1000),
+        ] {
+            tracker.add(
+                &buffer(ms(ts), element_ix * 10 + pad, element_ix)
+                    .peer(peer_element_ix.wrapping_mul(10), peer_element_ix)
+                    .pts(ms(pts))
+                    .record(),
+            );
+        }
+
+        let frames = tracker.frames();
+        assert_eq!(frames.len(), 6);
+        assert_eq!(frames[0].pts, ms(0));
+        assert_eq!(frames[0].source, 0);
+        assert_eq!(
+            frames[0]
+                .hops
+                .iter()
+                .map(|h| (h.element_ix, h.residence()))
+                .collect::<Vec<_>>(),
+            vec![(1, Some(ms(15))), (2, Some(ms(1))), (3, None), (4, None)]
+        );
+        assert_eq!(frames[0].hops[1].pad_ix, 20);
+
+        assert!(tracker.is_sink(4));
+        assert!(!tracker.is_sink(2));
+        assert_eq!(tracker.latency(&frames[0]), Some(ms(17)));
+        assert_eq!(tracker.latency(&frames[1]), Some(ms(22)));
+        assert_eq!(tracker.latency(&frames[2]), None);
+        assert_eq!(tracker.dropped(&frames[0]), None);
+        assert_eq!(tracker.dropped(&frames[2]), Some(1));
+        assert_eq!(tracker.dropped(&frames[3]), None);
+        assert_eq!(tracker.dropped(&frames[4]), None);
+        // sink 3 pushed a new frame to nowhere
+        assert_eq!(frames[5].source, 3);
+        assert!(frames[5].hops.is_empty());
+
+        let residence = tracker.residence_times();
+        assert_eq!(residence[&1], vec![ms(15), ms(20), ms(5)]);
+        assert_eq!(residence[&2], vec![ms(1), ms(1)]);
+        assert!(!residence.contains_key(&3));
+
+        // only the frames not pushed yet are kept
+        assert_eq!(tracker.elements[&1].received.len(), 2);
+        assert_eq!(tracker.elements[&2].received.len(), 1);
+    }
+
+    #[test]
+    fn resets() {
+        let ms = ClockTime::from_mseconds;
+        let mut tracker = FrameTracker::new();
+        // src (0) -> dec (1) -> sink (2)
+        let push = |tracker: &mut FrameTracker, element_ix: u32, ts, pts| {
+            tracker.add(
+                &buffer(ms(ts), element_ix * 10 + 1, element_ix)
+                    .peer((element_ix + 1) * 10, element_ix + 1)
+                    .pts(ms(pts))
+                    .record(),
+            );
+        };
+
+        push(&mut tracker, 0, 0, 0);
+        push(&mut tracker, 1, 5, 0);
+        // same pts pushed again by dec, a new frame
+        push(&mut tracker, 1, 10, 0);
+        assert_eq!(tracker.frames().len(), 2);
+        assert_eq!(tracker.frames()[1].source, 1);
+        assert_eq!(tracker.frames()[1].hops.len(), 1);
+
+        // dec is flushed while decoding pts 40 which is then received again
+        push(&mut tracker, 0, 20, 40);
+        tracker.add(&event(ms(25), 1, 0, "flush-stop"));
+        push(&mut tracker, 0, 30, 40);
+        push(&mut tracker, 1, 35, 40);
+
+        let frames = tracker.frames();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[2].hops[0].leave, None);
+        assert_eq!(tracker.dropped(&frames[2]), Some(1));
+        assert_eq!(frames[3].hops[0].residence(), Some(ms(5)));
+        assert_eq!(tracker.latency(&frames[3]), Some(ms(5)));
+        assert!(tracker.elements[&1].received.is_empty());
+    }
+}
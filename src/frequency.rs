@@ -0,0 +1,241 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Count how often each debug statement is logged to find the chatty ones.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use gst::ClockTime;
+use gstreamer as gst;
+
+use crate::normalize::message_template;
+use crate::Entry;
+
+// Location of the debug statement in the code
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CallSite {
+    pub category: String,
+    pub file: String,
+    pub line: u32,
+    pub function: String,
+}
+
+impl fmt::Display for CallSite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {}:{}:{}",
+            self.category, self.file, self.line, self.function
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct TemplateStats {
+    pub site: CallSite,
+    pub template: String,
+    pub count: u64,
+    // Size of the lines of the entries, including the line break
+    pub bytes: u64,
+    pub first: ClockTime,
+    pub last: ClockTime,
+    // Number of entries logged in each window, by index since the beginning of the log.
+    // Windows without entries are not stored.
+    pub windows: BTreeMap<u64, u64>,
+}
+
+impl TemplateStats {
+    // Average number of entries per second over @duration
+    pub fn rate(&self, duration: ClockTime) -> f64 {
+        if duration == ClockTime::ZERO {
+            return 0.0;
+        }
+        self.count as f64 / (duration.nseconds() as f64 / ClockTime::SECOND.nseconds() as f64)
+    }
+
+    // Highest number of entries per second logged during a single window of @window
+    pub fn peak_rate(&self, window: ClockTime) -> f64 {
+        let max = self.windows.values().max().cloned().unwrap_or(0);
+        max as f64 / (window.nseconds() as f64 / ClockTime::SECOND.nseconds() as f64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Count,
+    Bytes,
+    // Time of first appearance, most recent first
+    First,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "count" => Ok(SortKey::Count),
+            "bytes" => Ok(SortKey::Bytes),
+            "first" => Ok(SortKey::First),
+            _ => Err(format!("invalid sort key: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Frequencies {
+    window: ClockTime,
+    templates: HashMap<(CallSite, String), TemplateStats>,
+    start: Option<ClockTime>,
+    end: ClockTime,
+    total: u64,
+}
+
+impl Frequencies {
+    // @window is the duration used to compute the evolution of the rate over time
+    pub fn new(window: ClockTime) -> Self {
+        assert!(window > ClockTime::ZERO);
+
+        Self {
+            window,
+            templates: HashMap::new(),
+            start: None,
+            end: ClockTime::ZERO,
+            total: 0,
+        }
+    }
+
+    pub fn add(&mut self, entry: &Entry) {
+        let start = *self.start.get_or_insert(entry.ts);
+        self.end = self.end.max(entry.ts);
+        self.total += 1;
+
+        let site = CallSite {
+            category: entry.category.clone(),
+            file: entry.file.clone(),
+            line: entry.line,
+            function: entry.function.clone(),
+        };
+        let template = message_template(&entry.message);
+        let window = entry
+            .ts
+            .checked_sub(start)
+            .unwrap_or(ClockTime::ZERO)
+            .nseconds()
+            / self.window.nseconds();
+
+        let stats = self
+            .templates
+            .entry((site.clone(), template.clone()))
+            .or_insert_with(|| TemplateStats {
+                site,
+                template,
+                count: 0,
+                bytes: 0,
+                first: entry.ts,
+                last: entry.ts,
+                windows: BTreeMap::new(),
+            });
+
+        stats.count += 1;
+        stats.bytes += entry.len as u64 + 1;
+        stats.first = stats.first.min(entry.ts);
+        stats.last = stats.last.max(entry.ts);
+        *stats.windows.entry(window).or_default() += 1;
+    }
+
+    pub fn window(&self) -> ClockTime {
+        self.window
+    }
+
+    // Number of entries
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn duration(&self) -> ClockTime {
+        self.start
+            .and_then(|start| self.end.checked_sub(start))
+            .unwrap_or(ClockTime::ZERO)
+    }
+
+    pub fn contains(&self, site: &CallSite, template: &str) -> bool {
+        self.templates
+            .contains_key(&(site.clone(), template.to_string()))
+    }
+
+    pub fn stats(&self, sort: SortKey) -> Vec<&TemplateStats> {
+        let mut stats: Vec<_> = self.templates.values().collect();
+
+        match sort {
+            SortKey::Count => {
+                stats.sort_by(|a, b| b.count.cmp(&a.count).then(a.first.cmp(&b.first)))
+            }
+            SortKey::Bytes => {
+                stats.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.first.cmp(&b.first)))
+            }
+            SortKey::First => stats.sort_by_key(|s| std::cmp::Reverse(s.first)),
+        }
+        stats
+    }
+}
+
+impl<'a> Extend<&'a Entry> for Frequencies {
+    fn extend<I: IntoIterator<Item = &'a Entry>>(&mut self, iter: I) {
+        for entry in iter {
+            self.add(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use std::fs::File;
+
+    #[test]
+    fn frequencies() {
+        let mut freq = Frequencies::new(ClockTime::from_seconds(60));
+        let entries: Vec<_> = parse(File::open("test-logs/nocolor.log").unwrap()).collect();
+        freq.extend(entries.iter());
+
+        assert_eq!(freq.total(), 6);
+        assert_eq!(freq.duration(), ClockTime::from_nseconds(5_447_000_304_659));
+
+        let stats = freq.stats(SortKey::Count);
+        assert_eq!(stats.len(), 6);
+
+        // the same statement logged twice with different values
+        freq.add(&Entry::new("1:31:00.000000000  8874 0x558951015c00 TRACE        GST_REFCOUNTING gstobject.c:249:gst_object_ref:<allocatorsysmem0> 0x55895101d040 ref 2->3").unwrap());
+        let stats = freq.stats(SortKey::Count);
+        assert_eq!(stats.len(), 6);
+        let top = stats[0];
+        assert_eq!(top.count, 2);
+        assert_eq!(top.template, "<ptr> ref <num>-><num>");
+        assert_eq!(
+            top.site.to_string(),
+            "GST_REFCOUNTING gstobject.c:249:gst_object_ref"
+        );
+        assert_eq!(top.first, ClockTime::from_nseconds(680_008_067_915));
+        assert_eq!(top.windows.len(), 2);
+        assert_eq!(top.windows[&11], 1);
+        assert_eq!(top.windows[&90], 1);
+        assert_eq!(top.peak_rate(freq.window()), 1.0 / 60.0);
+        assert!(freq.contains(&top.site, "<ptr> ref <num>-><num>"));
+
+        let stats = freq.stats(SortKey::Bytes);
+        assert_eq!(stats[0].template, "<ptr> ref <num>-><num>");
+        assert_eq!(stats[0].bytes, 2 * 142);
+
+        let stats = freq.stats(SortKey::First);
+        assert_eq!(stats[0].site.function, "gst_allocator_register");
+        assert_eq!(stats[1].template, "<ptr> ref <num>-><num>");
+    }
+}
@@ -0,0 +1,783 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Tracking of frames through hardware components (OMX, V4L2, VA-API, ...).
+//!
+//! Each kind of hardware logs the buffers it exchanges with its components
+//! differently, so a [`Profile`] decodes the relevant entries into [`HwEvent`]s:
+//! a frame entering or leaving a component. Frames are identified by a key
+//! provided by the profile, such as the buffer timestamp or the surface id,
+//! which is what the [`HwTracker`] uses to follow them across components.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use gst::ClockTime;
+use gstreamer as gst;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::omx::{ComponentNaming, OmxCall, OmxEvent, OmxParser, Skipped};
+use crate::{parse_time, Entry};
+
+lazy_static! {
+    static ref V4L2_QUEUE_RE: Regex = Regex::new(r"^queuing buffer (?P<index>\d+)").unwrap();
+    static ref V4L2_DEQUEUE_RE: Regex = Regex::new(
+        r"^dequeued buffer \S+ seq:\d+ \(ix=(?P<index>\d+)\), mem \S+ used (?P<used>\d+),.* ts (?P<ts>[0-9:.]+)"
+    )
+    .unwrap();
+    static ref VAAPI_DECODE_RE: Regex = Regex::new(r"^decode picture (?P<surface>0x[0-9a-fA-F]+)").unwrap();
+    static ref VAAPI_PUSH_RE: Regex =
+        Regex::new(r"^push frame \d+ \(surface (?P<surface>0x[0-9a-fA-F]+)\)").unwrap();
+}
+
+// Names of the profiles accepted by profile()
+pub const PROFILES: &[&str] = &["omx", "v4l2", "vaapi"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    // Frame given to the component
+    Input,
+    // Frame produced by the component
+    Output,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HwEvent {
+    pub ts: ClockTime,
+    pub component: String,
+    pub direction: Direction,
+    // Identifier of the frame, its meaning depends on the profile
+    pub frame: u64,
+}
+
+impl HwEvent {
+    // OMX buffer entering or leaving its component, ignoring empty output buffers
+    pub fn from_omx(event: &OmxEvent) -> Option<Self> {
+        let direction = match (event.call, event.finished) {
+            (OmxCall::EmptyThisBuffer, false) => Direction::Input,
+            (OmxCall::FillBufferDone, false) if !event.is_empty() => Direction::Output,
+            _ => return None,
+        };
+
+        Some(HwEvent {
+            ts: event.ts,
+            component: event.component.clone(),
+            direction,
+            frame: event.timestamp?.nseconds(),
+        })
+    }
+}
+
+pub trait Profile {
+    fn name(&self) -> &'static str;
+    // Decode @entry, None if it is not a non-empty buffer entering or leaving a component
+    fn event(&mut self, entry: &Entry) -> Option<HwEvent>;
+    // Human readable identifier of @frame
+    fn frame_name(&self, frame: u64) -> String {
+        ClockTime::from_nseconds(frame).to_string()
+    }
+    // Entries of the profile which could not be decoded, if they are counted
+    fn skipped(&self) -> Option<&Skipped> {
+        None
+    }
+}
+
+pub fn profile(name: &str) -> Option<Box<dyn Profile>> {
+    match name {
+        "omx" => Some(Box::new(OmxProfile::default())),
+        "v4l2" => Some(Box::new(V4l2Profile::default())),
+        "vaapi" => Some(Box::new(VaapiProfile)),
+        _ => None,
+    }
+}
+
+// OMX_API_TRACE logs of gst-omx, frames are identified by their OMX timestamp
+#[derive(Debug, Default)]
+pub struct OmxProfile {
+    parser: OmxParser,
+}
+
+impl OmxProfile {
+    pub fn new(naming: ComponentNaming) -> Self {
+        Self {
+            parser: OmxParser::new(naming),
+        }
+    }
+}
+
+impl Profile for OmxProfile {
+    fn name(&self) -> &'static str {
+        "omx"
+    }
+
+    fn event(&mut self, entry: &Entry) -> Option<HwEvent> {
+        HwEvent::from_omx(&self.parser.parse(entry)?)
+    }
+
+    fn skipped(&self) -> Option<&Skipped> {
+        Some(self.parser.skipped())
+    }
+}
+
+// v4l2bufferpool logs, frames are identified by their timestamp which V4L2 m2m
+// devices copy from input to output buffers.
+// Input buffers only log their timestamp when they are dequeued, once consumed
+// by the device, so the time they have been queued is kept by index.
+#[derive(Debug, Default)]
+pub struct V4l2Profile {
+    // (pool, index) -> ts
+    queued: HashMap<(String, u32), ClockTime>,
+}
+
+impl Profile for V4l2Profile {
+    fn name(&self) -> &'static str {
+        "v4l2"
+    }
+
+    fn event(&mut self, entry: &Entry) -> Option<HwEvent> {
+        if entry.category != "v4l2bufferpool" {
+            return None;
+        }
+
+        // Pools are named after their element and queue: "v4l2h264dec0:pool0:sink"
+        let object = entry.object.as_ref()?;
+        let component = object.element()?;
+        let direction = match object.pad()?.rsplit(':').next()? {
+            "sink" => Direction::Input,
+            "src" => Direction::Output,
+            _ => return None,
+        };
+        let pool = object.to_string();
+
+        if let Some(cap) = V4L2_QUEUE_RE.captures(&entry.message) {
+            let index = cap["index"].parse().ok()?;
+            self.queued.insert((pool, index), entry.ts);
+            return None;
+        }
+
+        let cap = V4L2_DEQUEUE_RE.captures(&entry.message)?;
+        let index: u32 = cap["index"].parse().ok()?;
+        let queued = self.queued.remove(&(pool, index));
+        let frame = parse_time(&cap["ts"]).ok()?;
+
+        let ts = match direction {
+            Direction::Input => queued.unwrap_or(entry.ts),
+            // Ignore empty output buffers
+            Direction::Output if &cap["used"] == "0" => return None,
+            Direction::Output => entry.ts,
+        };
+
+        Some(HwEvent {
+            ts,
+            component: component.to_string(),
+            direction,
+            frame: frame.nseconds(),
+        })
+    }
+}
+
+// gstreamer-vaapi decoders, frames are identified by the VA surface they are decoded to.
+// A frame enters the decoder when its picture is submitted to the hardware and leaves it
+// once decoded and queued for output.
+// These entries are logged without object so all decoders are reported as 'vaapidecode'.
+#[derive(Debug, Default)]
+pub struct VaapiProfile;
+
+impl Profile for VaapiProfile {
+    fn name(&self) -> &'static str {
+        "vaapi"
+    }
+
+    fn event(&mut self, entry: &Entry) -> Option<HwEvent> {
+        if entry.category != "vaapi" {
+            return None;
+        }
+
+        let (direction, cap) = match VAAPI_DECODE_RE.captures(&entry.message) {
+            Some(cap) => (Direction::Input, cap),
+            None => (Direction::Output, VAAPI_PUSH_RE.captures(&entry.message)?),
+        };
+
+        Some(HwEvent {
+            ts: entry.ts,
+            component: "vaapidecode".to_string(),
+            direction,
+            frame: parse_frame(&cap["surface"])?,
+        })
+    }
+
+    fn frame_name(&self, frame: u64) -> String {
+        format!("{:#x}", frame)
+    }
+}
+
+// Profile matching the messages of a category with regular expressions capturing the
+// frame identifier in a 'frame' group, in decimal or in hexadecimal with a 0x prefix.
+// Components are named after the object of the entries.
+#[derive(Debug)]
+pub struct PatternProfile {
+    name: &'static str,
+    category: Regex,
+    input: Regex,
+    output: Regex,
+}
+
+impl PatternProfile {
+    pub fn new(
+        name: &'static str,
+        category: &str,
+        input: &str,
+        output: &str,
+    ) -> Result<Self, regex::Error> {
+        Ok(Self {
+            name,
+            category: Regex::new(category)?,
+            input: Regex::new(input)?,
+            output: Regex::new(output)?,
+        })
+    }
+}
+
+fn parse_frame(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+impl Profile for PatternProfile {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn event(&mut self, entry: &Entry) -> Option<HwEvent> {
+        if !self.category.is_match(&entry.category) {
+            return None;
+        }
+
+        let (direction, cap) = match self.input.captures(&entry.message) {
+            Some(cap) => (Direction::Input, cap),
+            None => (Direction::Output, self.output.captures(&entry.message)?),
+        };
+
+        Some(HwEvent {
+            ts: entry.ts,
+            component: entry.object.as_ref()?.to_string(),
+            direction,
+            frame: parse_frame(cap.name("frame")?.as_str())?,
+        })
+    }
+
+    fn frame_name(&self, frame: u64) -> String {
+        format!("{:#x}", frame)
+    }
+}
+
+// A frame going through a component, which may split it into several buffers
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentTiming {
+    pub component: String,
+    pub enter: Vec<ClockTime>,
+    pub leave: Vec<ClockTime>,
+}
+
+impl ComponentTiming {
+    // ts when the first buffer of the frame entered the component
+    pub fn first_enter(&self) -> Option<ClockTime> {
+        self.enter.first().copied()
+    }
+
+    // ts when the last buffer of the frame entered the component
+    pub fn last_enter(&self) -> Option<ClockTime> {
+        self.enter.last().copied()
+    }
+
+    // ts when the first buffer of the frame left the component
+    pub fn first_leave(&self) -> Option<ClockTime> {
+        self.leave.first().copied()
+    }
+
+    // ts when the last buffer of the frame left the component
+    pub fn last_leave(&self) -> Option<ClockTime> {
+        self.leave.last().copied()
+    }
+
+    // ts of the first buffer seen, components such as sources only produce frames
+    pub fn start(&self) -> ClockTime {
+        self.first_enter()
+            .or_else(|| self.first_leave())
+            .unwrap_or(ClockTime::ZERO)
+    }
+
+    // Time between the first buffer of the frame entering the component and the last one leaving it
+    pub fn processing(&self) -> Option<ClockTime> {
+        Some(self.last_leave()?.saturating_sub(self.start()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HwFrame {
+    pub frame: u64,
+    // Sorted by time the frame reached them
+    pub components: Vec<ComponentTiming>,
+}
+
+impl HwFrame {
+    pub fn start(&self) -> ClockTime {
+        self.components
+            .iter()
+            .map(|c| c.start())
+            .min()
+            .unwrap_or(ClockTime::ZERO)
+    }
+
+    // Frames still in a component when the log ends are not complete
+    pub fn is_complete(&self) -> bool {
+        self.components.iter().all(|c| !c.leave.is_empty())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentStats {
+    pub frames: u64,
+    pub processing: ClockTime,
+    pub max: ClockTime,
+    // when the first/last frame has been produced
+    pub first_out: ClockTime,
+    pub last_out: ClockTime,
+}
+
+impl ComponentStats {
+    pub fn mean_processing(&self) -> ClockTime {
+        ClockTime::from_nseconds(self.processing.nseconds() / self.frames)
+    }
+
+    // Frames produced per second
+    pub fn fps(&self) -> Option<f64> {
+        let interval = self.last_out.saturating_sub(self.first_out);
+        if interval.is_zero() {
+            return None;
+        }
+
+        Some(self.frames as f64 / (interval.nseconds() as f64 / 1_000_000_000.0))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct HwTracker {
+    frames: Vec<HwFrame>,
+    // frame id -> index of the most recent frame using it
+    current: HashMap<u64, usize>,
+}
+
+impl HwTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, event: HwEvent) {
+        let frames = &self.frames;
+        // Ids such as surfaces are recycled: a component receiving an id it already produced is a new frame
+        let ix = self.current.get(&event.frame).copied().filter(|ix| {
+            event.direction == Direction::Output
+                || !frames[*ix]
+                    .components
+                    .iter()
+                    .any(|c| c.component == event.component && !c.leave.is_empty())
+        });
+        let ix = match ix {
+            Some(ix) => ix,
+            None => {
+                self.frames.push(HwFrame {
+                    frame: event.frame,
+                    components: Vec::new(),
+                });
+                self.current.insert(event.frame, self.frames.len() - 1);
+                self.frames.len() - 1
+            }
+        };
+
+        let components = &mut self.frames[ix].components;
+        let timing = match components
+            .iter()
+            .position(|c| c.component == event.component)
+        {
+            Some(i) => &mut components[i],
+            None => {
+                components.push(ComponentTiming {
+                    component: event.component,
+                    enter: Vec::new(),
+                    leave: Vec::new(),
+                });
+                components.last_mut().unwrap()
+            }
+        };
+
+        match event.direction {
+            Direction::Input => timing.enter.push(event.ts),
+            Direction::Output => timing.leave.push(event.ts),
+        }
+    }
+
+    pub fn frames(&self) -> &[HwFrame] {
+        &self.frames
+    }
+
+    // Frames which left all their components, sorted by ts
+    pub fn completed(&self) -> Vec<&HwFrame> {
+        let mut frames: Vec<&HwFrame> = self.frames.iter().filter(|f| f.is_complete()).collect();
+        frames.sort_by_key(|f| f.start());
+        frames
+    }
+
+    // Processing time and throughput of each component, computed from completed frames
+    pub fn component_stats(&self) -> BTreeMap<String, ComponentStats> {
+        let mut stats: BTreeMap<String, ComponentStats> = BTreeMap::new();
+
+        for c in self.completed().iter().flat_map(|f| f.components.iter()) {
+            let (processing, out) = match (c.processing(), c.last_leave()) {
+                (Some(processing), Some(out)) => (processing, out),
+                _ => continue,
+            };
+            let s = stats
+                .entry(c.component.clone())
+                .or_insert_with(|| ComponentStats {
+                    frames: 0,
+                    processing: ClockTime::ZERO,
+                    max: ClockTime::ZERO,
+                    first_out: out,
+                    last_out: out,
+                });
+            s.frames += 1;
+            s.processing += processing;
+            s.max = s.max.max(processing);
+            s.first_out = s.first_out.min(out);
+            s.last_out = s.last_out.max(out);
+        }
+
+        stats
+    }
+
+    // Completed frames and component statistics, naming frames with @frame_name
+    pub fn report<F: Fn(u64) -> String>(&self, frame_name: F) -> HwReport<'_, F> {
+        HwReport {
+            tracker: self,
+            frame_name,
+        }
+    }
+}
+
+// Time spent by each completed frame in its components, followed by the statistics of each component
+pub struct HwReport<'a, F> {
+    tracker: &'a HwTracker,
+    frame_name: F,
+}
+
+impl<'a, F: Fn(u64) -> String> fmt::Display for HwReport<'a, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for frame in self.tracker.completed() {
+            write!(f, "Frame: {} ", (self.frame_name)(frame.frame))?;
+            for c in frame.components.iter() {
+                write!(f, "\n\t[{}", c.component)?;
+                if let Some(first_enter) = c.first_enter() {
+                    write!(f, " fst-in: {}", first_enter)?;
+                }
+                if let (Some(last_leave), Some(processing)) = (c.last_leave(), c.processing()) {
+                    write!(f, " lst-out: {} 𝚫: {}", last_leave, processing)?;
+                }
+
+                if let [first_enter, .., last_enter] = c.enter[..] {
+                    write!(
+                        f,
+                        " lst-in:  {} 𝚫(fst-in): {}",
+                        last_enter,
+                        last_enter - first_enter
+                    )?;
+                }
+
+                if let [first_leave, .., last_leave] = c.leave[..] {
+                    write!(
+                        f,
+                        " fst-out: {} 𝚫(lst-out): {} 𝚫(fst-in): {}",
+                        first_leave,
+                        last_leave - first_leave,
+                        first_leave.saturating_sub(c.start())
+                    )?;
+                }
+
+                write!(f, "]")?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f)?;
+        for (name, stats) in self.tracker.component_stats() {
+            write!(
+                f,
+                "{} : nb-frames: {} avg-time: {} max-time: {}",
+                name,
+                stats.frames,
+                stats.mean_processing(),
+                stats.max
+            )?;
+            match stats.fps() {
+                Some(fps) => writeln!(f, " rate: {:.2} fps", fps)?,
+                None => writeln!(f)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(ts: &str, category: &str, object: &str, message: &str) -> Entry {
+        Entry::new(&format!(
+            "{}  8874 0x558951015c00 LOG {} gst.c:1:f:<{}> {}",
+            ts, category, object, message
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn omx() {
+        let ms = ClockTime::from_mseconds;
+        let event = |call, finished, filled_len| OmxEvent {
+            ts: ms(10),
+            component: "dec0".to_string(),
+            call,
+            finished,
+            timestamp: Some(ms(40)),
+            filled_len,
+            flags: 0,
+        };
+
+        assert_eq!(
+            HwEvent::from_omx(&event(OmxCall::EmptyThisBuffer, false, Some(1024))),
+            Some(HwEvent {
+                ts: ms(10),
+                component: "dec0".to_string(),
+                direction: Direction::Input,
+                frame: ms(40).nseconds(),
+            })
+        );
+        assert_eq!(
+            HwEvent::from_omx(&event(OmxCall::FillBufferDone, false, Some(1024)))
+                .unwrap()
+                .direction,
+            Direction::Output
+        );
+        for e in [
+            event(OmxCall::FillBufferDone, false, Some(0)),
+            event(OmxCall::FillBufferDone, true, None),
+            event(OmxCall::FillThisBuffer, false, Some(0)),
+        ] {
+            assert_eq!(HwEvent::from_omx(&e), None);
+        }
+    }
+
+    #[test]
+    fn v4l2() {
+        let ms = ClockTime::from_mseconds;
+        let mut profile = V4l2Profile::default();
+        let mut tracker = HwTracker::new();
+
+        for e in [
+            entry("0:00:00.010000000", "v4l2bufferpool", "v4l2h264dec0:pool0:sink", "queuing buffer 2, previous-state = 0, pending = 0"),
+            entry("0:00:00.012000000", "v4l2bufferpool", "v4l2h264dec0:pool0:sink", "dequeued buffer 0x7f2c seq:0 (ix=2), mem 0x7f3c used 1024, plane=0, flags 00004003, ts 0:00:00.000000000, pool-queued=1, buffer=0x7f2c, previous-state=1"),
+            entry("0:00:00.020000000", "v4l2bufferpool", "v4l2h264dec0:pool0:sink", "queuing buffer 0, previous-state = 0, pending = 0"),
+            // empty output buffer
+            entry("0:00:00.025000000", "v4l2bufferpool", "v4l2h264dec0:pool1:src", "dequeued buffer 0x7f4c seq:0 (ix=0), mem 0x7f5c used 0, plane=0, flags 00004001, ts 0:00:00.000000000, pool-queued=3, buffer=0x7f4c, previous-state=1"),
+            entry("0:00:00.030000000", "v4l2bufferpool", "v4l2h264dec0:pool1:src", "dequeued buffer 0x7f4c seq:1 (ix=1), mem 0x7f5c used 3110400, plane=0, flags 00004001, ts 0:00:00.000000000, pool-queued=3, buffer=0x7f4c, previous-state=1"),
+            // still being decoded
+            entry("0:00:00.031000000", "v4l2bufferpool", "v4l2h264dec0:pool0:sink", "dequeued buffer 0x7f2c seq:1 (ix=0), mem 0x7f3c used 1024, plane=0, flags 00004003, ts 0:00:00.033333333, pool-queued=1, buffer=0x7f2c, previous-state=1"),
+            entry("0:00:00.032000000", "GST_BUFFER", "v4l2h264dec0:pool0:sink", "queuing buffer 1"),
+        ] {
+            if let Some(event) = profile.event(&e) {
+                tracker.add(event);
+            }
+        }
+
+        assert_eq!(tracker.frames().len(), 2);
+        let frames = tracker.completed();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].frame, 0);
+        assert_eq!(
+            frames[0].components,
+            vec![ComponentTiming {
+                component: "v4l2h264dec0".to_string(),
+                enter: vec![ms(10)],
+                leave: vec![ms(30)],
+            }]
+        );
+        assert_eq!(profile.frame_name(33_333_333), "0:00:00.033333333");
+        assert!(profile.queued.is_empty());
+
+        let stats = tracker.component_stats();
+        assert_eq!(stats["v4l2h264dec0"].frames, 1);
+        assert_eq!(stats["v4l2h264dec0"].mean_processing(), ms(20));
+        assert_eq!(stats["v4l2h264dec0"].fps(), None);
+    }
+
+    #[test]
+    fn vaapi() {
+        let ms = ClockTime::from_mseconds;
+        let mut profile = VaapiProfile;
+        let mut tracker = HwTracker::new();
+
+        for line in [
+            "0:00:00.010000000 12842 0x7f1c3c0058a0 DEBUG                  vaapi gstvaapidecoder_objects.c:281:gst_vaapi_picture_decode_with_surface_id: decode picture 0x04000001",
+            "0:00:00.012000000 12842 0x7f1c3c0058a0 DEBUG                  vaapi gstvaapidecoder_objects.c:281:gst_vaapi_picture_decode_with_surface_id: decode picture 0x04000002",
+            "0:00:00.015000000 12842 0x7f1c3c0058a0 DEBUG                  vaapi gstvaapidecoder.c:286:push_frame: push frame 0 (surface 0x04000001)",
+            "0:00:00.016000000 12842 0x7f1c3c0058a0 DEBUG                  vaapi gstvaapidecoder.c:308:pop_frame: pop frame 0 (surface 0x04000001)",
+            // surface recycled for a new frame
+            "0:00:00.020000000 12842 0x7f1c3c0058a0 DEBUG                  vaapi gstvaapidecoder_objects.c:281:gst_vaapi_picture_decode_with_surface_id: decode picture 0x04000001",
+            "0:00:00.024000000 12842 0x7f1c3c0058a0 DEBUG                  vaapi gstvaapidecoder.c:286:push_frame: push frame 1 (surface 0x04000001)",
+        ] {
+            if let Some(event) = profile.event(&Entry::new(line).unwrap()) {
+                tracker.add(event);
+            }
+        }
+
+        assert_eq!(tracker.frames().len(), 3);
+        let frames = tracker.completed();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].frame, 0x0400_0001);
+        assert_eq!(
+            frames[0].components,
+            vec![ComponentTiming {
+                component: "vaapidecode".to_string(),
+                enter: vec![ms(10)],
+                leave: vec![ms(15)],
+            }]
+        );
+        assert_eq!(frames[1].start(), ms(20));
+        assert_eq!(profile.frame_name(0x0400_0001), "0x4000001");
+
+        let stats = tracker.component_stats();
+        assert_eq!(stats["vaapidecode"].frames, 2);
+        assert_eq!(
+            stats["vaapidecode"].mean_processing(),
+            ClockTime::from_useconds(4500)
+        );
+        assert_eq!(stats["vaapidecode"].fps(), Some(2.0 / 0.009));
+
+        let report = tracker
+            .report(|frame| profile.frame_name(frame))
+            .to_string();
+        assert!(report.starts_with("Frame: 0x4000001 \n\t[vaapidecode fst-in: 0:00:00.010000000 lst-out: 0:00:00.015000000 𝚫: 0:00:00.005000000]\n"));
+        assert!(report.ends_with("\nvaapidecode : nb-frames: 2 avg-time: 0:00:00.004500000 max-time: 0:00:00.005000000 rate: 222.22 fps\n"));
+    }
+
+    #[test]
+    fn pattern() {
+        let mut profile = PatternProfile::new(
+            "surfaces",
+            "^hwdec",
+            r"decode to surface (?P<frame>0x[0-9a-fA-F]+)",
+            r"output surface (?P<frame>0x[0-9a-fA-F]+)",
+        )
+        .unwrap();
+        let e = entry(
+            "0:00:00.010000000",
+            "hwdecoder",
+            "hwdec0",
+            "decode to surface 0x4000001",
+        );
+        assert_eq!(
+            profile.event(&e),
+            Some(HwEvent {
+                ts: ClockTime::from_mseconds(10),
+                component: "hwdec0".to_string(),
+                direction: Direction::Input,
+                frame: 0x4000001,
+            })
+        );
+        let e = entry(
+            "0:00:00.012000000",
+            "hwdecoder",
+            "hwdec0",
+            "output surface 0x4000001",
+        );
+        assert_eq!(profile.event(&e).unwrap().direction, Direction::Output);
+        let e = entry(
+            "0:00:00.012000000",
+            "GST_BUFFER",
+            "hwdec0",
+            "output surface 0x4000001",
+        );
+        assert_eq!(profile.event(&e), None);
+        assert_eq!(profile.frame_name(0x4000001), "0x4000001");
+
+        let mut profile = PatternProfile::new(
+            "custom",
+            "^mydec$",
+            r"in (?P<frame>\d+)",
+            r"out (?P<frame>\d+)",
+        )
+        .unwrap();
+        let e = entry("0:00:00.012000000", "mydec", "dec0", "out 42");
+        assert_eq!(profile.event(&e).unwrap().frame, 42);
+        assert!(PatternProfile::new("custom", "(", "", "").is_err());
+    }
+
+    #[test]
+    fn tracker() {
+        let ms = ClockTime::from_mseconds;
+        let event = |ts, component: &str, direction, frame| HwEvent {
+            ts: ms(ts),
+            component: component.to_string(),
+            direction,
+            frame,
+        };
+        let mut tracker = HwTracker::new();
+
+        for e in [
+            event(0, "dec", Direction::Input, 1),
+            event(1, "dec", Direction::Input, 1),
+            event(10, "dec", Direction::Output, 1),
+            event(11, "enc", Direction::Input, 1),
+            event(30, "enc", Direction::Output, 1),
+            // id recycled by dec
+            event(40, "dec", Direction::Input, 1),
+            event(50, "dec", Direction::Output, 1),
+            event(41, "dec", Direction::Input, 2),
+            event(70, "dec", Direction::Output, 2),
+            event(71, "enc", Direction::Input, 2),
+        ] {
+            tracker.add(e);
+        }
+
+        assert_eq!(tracker.frames().len(), 3);
+        let frames = tracker.completed();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].start(), ms(0));
+        assert_eq!(
+            frames[0]
+                .components
+                .iter()
+                .map(|c| (c.component.as_str(), c.processing()))
+                .collect::<Vec<_>>(),
+            vec![("dec", Some(ms(10))), ("enc", Some(ms(19)))]
+        );
+        assert_eq!(frames[0].components[0].last_enter(), Some(ms(1)));
+        assert_eq!(frames[1].start(), ms(40));
+        assert!(!tracker.frames()[2].is_complete());
+
+        let stats = tracker.component_stats();
+        assert_eq!(stats["dec"].frames, 2);
+        assert_eq!(stats["dec"].mean_processing(), ms(10));
+        assert_eq!(stats["dec"].max, ms(10));
+        assert_eq!(stats["dec"].fps(), Some(50.0));
+        assert_eq!(stats["enc"].frames, 1);
+    }
+}
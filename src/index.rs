@@ -0,0 +1,169 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Random access to the lines of large logs without loading them in memory.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::{Entry, ParsingError};
+
+// Offsets of lines are recorded as they are read so only the part of the log
+// which has been accessed is indexed.
+#[derive(Debug)]
+pub struct LogIndex<R: Read + Seek> {
+    reader: BufReader<R>,
+    // offsets[i] is the start of line i, the last one is the end of the indexed part
+    offsets: Vec<u64>,
+    complete: bool,
+}
+
+impl LogIndex<File> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> LogIndex<R> {
+    pub fn new(r: R) -> Self {
+        Self {
+            reader: BufReader::new(r),
+            offsets: vec![0],
+            complete: false,
+        }
+    }
+
+    // Number of lines indexed so far
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.complete && self.len() == 0
+    }
+
+    // Whether the whole log has been indexed
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    // Size of the indexed part of the log, in bytes
+    pub fn indexed_bytes(&self) -> u64 {
+        *self.offsets.last().unwrap()
+    }
+
+    fn read(&mut self, start: usize, count: usize, keep: bool) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+        let mut pos = self.offsets[start];
+        let mut buf = Vec::new();
+
+        self.reader.seek(SeekFrom::Start(pos))?;
+        for i in start..start + count {
+            buf.clear();
+            let n = self.reader.read_until(b'\n', &mut buf)?;
+            if n == 0 {
+                self.complete = true;
+                break;
+            }
+
+            pos += n as u64;
+            if i + 1 == self.offsets.len() {
+                self.offsets.push(pos);
+            }
+            if keep {
+                let line = String::from_utf8_lossy(&buf);
+                lines.push(line.trim_end_matches(['\n', '\r']).to_string());
+            }
+        }
+
+        Ok(lines)
+    }
+
+    // Index up to @count more lines, return the number of lines which have been indexed
+    pub fn index_more(&mut self, count: usize) -> io::Result<usize> {
+        if self.complete {
+            return Ok(0);
+        }
+
+        let len = self.len();
+        self.read(len, count, false)?;
+        Ok(self.len() - len)
+    }
+
+    // Read up to @count lines starting from line @start, indexing them if needed
+    pub fn lines(&mut self, start: usize, count: usize) -> io::Result<Vec<String>> {
+        if start > self.len() {
+            self.index_more(start - self.len())?;
+            if start > self.len() {
+                return Ok(Vec::new());
+            }
+        }
+
+        self.read(start, count, true)
+    }
+
+    pub fn line(&mut self, n: usize) -> io::Result<Option<String>> {
+        Ok(self.lines(n, 1)?.pop())
+    }
+
+    // Parse up to @count lines starting from line @start using the native format
+    pub fn entries(
+        &mut self,
+        start: usize,
+        count: usize,
+    ) -> io::Result<Vec<Result<Entry, ParsingError>>> {
+        Ok(self
+            .lines(start, count)?
+            .iter()
+            .map(|line| Entry::new(line))
+            .collect())
+    }
+
+    pub fn entry(&mut self, n: usize) -> io::Result<Option<Result<Entry, ParsingError>>> {
+        Ok(self.entries(n, 1)?.pop())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn index() {
+        let mut index = LogIndex::new(Cursor::new("a\nbb\r\n\nccc"));
+        assert_eq!(index.len(), 0);
+        assert!(!index.is_complete());
+
+        assert_eq!(index.line(1).unwrap(), Some("bb".to_string()));
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.indexed_bytes(), 6);
+        assert_eq!(index.line(0).unwrap(), Some("a".to_string()));
+        assert_eq!(
+            index.lines(2, 5).unwrap(),
+            vec!["".to_string(), "ccc".to_string()]
+        );
+        assert!(index.is_complete());
+        assert_eq!(index.len(), 4);
+        assert_eq!(index.line(4).unwrap(), None);
+        assert_eq!(index.line(10).unwrap(), None);
+        assert_eq!(index.index_more(10).unwrap(), 0);
+
+        let mut index = LogIndex::open("test-logs/nocolor.log").unwrap();
+        assert_eq!(index.index_more(3).unwrap(), 3);
+        let entry = index.entry(4).unwrap().unwrap().unwrap();
+        assert_eq!(entry.function, "gst_object_ref");
+        assert_eq!(index.index_more(100).unwrap(), 1);
+        let entries = index.entries(4, 10).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|e| e.is_ok()));
+        assert!(index.is_complete());
+        assert!(!index.is_empty());
+    }
+}
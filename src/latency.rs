@@ -0,0 +1,743 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Statistics of the latencies measured by the 'latency' tracer.
+//!
+//! Generate logs with: GST_DEBUG="GST_TRACER:7" GST_TRACERS="latency(flags=pipeline+element)"
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use gst::ClockTime;
+use gstreamer as gst;
+
+use crate::tracer::Record;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyStats {
+    pub count: usize,
+    pub min: ClockTime,
+    pub max: ClockTime,
+    // ts of the highest latency
+    pub max_ts: ClockTime,
+    pub mean: ClockTime,
+    pub stddev: ClockTime,
+    pub p50: ClockTime,
+    pub p90: ClockTime,
+    pub p99: ClockTime,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bin {
+    pub start: ClockTime,
+    pub end: ClockTime,
+    pub count: usize,
+}
+
+// Latencies higher than a threshold during a period
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    // ts of the first and last latencies of the period
+    pub start: ClockTime,
+    pub end: ClockTime,
+    pub samples: usize,
+    pub mean: ClockTime,
+    pub max: ClockTime,
+}
+
+impl Violation {
+    fn new(values: &[(ClockTime, ClockTime)]) -> Self {
+        let sum: u64 = values.iter().map(|(_, l)| l.nseconds()).sum();
+
+        Self {
+            start: values[0].0,
+            end: values[values.len() - 1].0,
+            samples: values.len(),
+            mean: ClockTime::from_nseconds(sum / values.len() as u64),
+            max: values.iter().map(|(_, l)| *l).max().unwrap(),
+        }
+    }
+
+    pub fn duration(&self) -> ClockTime {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+// Latencies measured by an element or on a path, with the ts of their measurement
+#[derive(Debug, Default, Clone)]
+pub struct Samples {
+    values: Vec<(ClockTime, ClockTime)>,
+}
+
+impl Samples {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, ts: ClockTime, latency: ClockTime) {
+        self.values.push((ts, latency));
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn values(&self) -> &[(ClockTime, ClockTime)] {
+        &self.values
+    }
+
+    fn sorted(&self) -> Vec<ClockTime> {
+        let mut sorted: Vec<ClockTime> = self.values.iter().map(|(_, l)| *l).collect();
+        sorted.sort();
+        sorted
+    }
+
+    pub fn stats(&self) -> Option<LatencyStats> {
+        let (max_ts, max) = *self.values.iter().max_by_key(|(_, l)| *l)?;
+        let sorted = self.sorted();
+        let n = sorted.len() as f64;
+        let mean = sorted.iter().map(|l| l.nseconds() as f64).sum::<f64>() / n;
+        let variance = sorted
+            .iter()
+            .map(|l| (l.nseconds() as f64 - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        // nearest-rank method
+        let percentile =
+            |p: f64| sorted[((p / 100.0 * n).ceil() as usize).clamp(1, sorted.len()) - 1];
+
+        Some(LatencyStats {
+            count: sorted.len(),
+            min: sorted[0],
+            max,
+            max_ts,
+            mean: ClockTime::from_nseconds(mean.round() as u64),
+            stddev: ClockTime::from_nseconds(variance.sqrt().round() as u64),
+            p50: percentile(50.0),
+            p90: percentile(90.0),
+            p99: percentile(99.0),
+        })
+    }
+
+    // Split the range of latencies in @bins intervals of the same size
+    pub fn histogram(&self, bins: usize) -> Vec<Bin> {
+        let sorted = self.sorted();
+        let (min, max) = match (sorted.first(), sorted.last()) {
+            (Some(min), Some(max)) if bins > 0 => (min.nseconds(), max.nseconds()),
+            _ => return Vec::new(),
+        };
+        let width = ((max - min) / bins as u64).max(1);
+
+        let mut histogram: Vec<Bin> = (0..bins as u64)
+            .map(|i| Bin {
+                start: ClockTime::from_nseconds(min + i * width),
+                end: ClockTime::from_nseconds(min + (i + 1) * width),
+                count: 0,
+            })
+            .collect();
+        for l in sorted {
+            let i = ((l.nseconds() - min) / width) as usize;
+            histogram[i.min(bins - 1)].count += 1;
+        }
+        // The last bin includes the highest latency
+        histogram.last_mut().unwrap().end =
+            ClockTime::from_nseconds(max.max(min + bins as u64 * width));
+
+        histogram
+    }
+
+    // Runs of at least @count consecutive latencies higher than or equal to
+    // @threshold, lasting at least @duration
+    pub fn sustained(
+        &self,
+        threshold: ClockTime,
+        count: usize,
+        duration: ClockTime,
+    ) -> Vec<Violation> {
+        self.values
+            .split(|(_, l)| *l < threshold)
+            .filter(|run| !run.is_empty() && run.len() >= count)
+            .map(Violation::new)
+            .filter(|v| v.duration() >= duration)
+            .collect()
+    }
+
+    // Consecutive windows of @window during which the mean latency is higher than or equal to @threshold
+    pub fn windowed(&self, threshold: ClockTime, window: ClockTime) -> Vec<Violation> {
+        let first = match self.values.first() {
+            Some((ts, _)) => *ts,
+            None => return Vec::new(),
+        };
+        let window = window.nseconds().max(1);
+        let index = |ts: ClockTime| ts.saturating_sub(first).nseconds() / window;
+
+        let mut violations = Vec::new();
+        // (start of the period in values, index of its last window)
+        let mut period: Option<(usize, u64)> = None;
+        let mut start = 0;
+        while start < self.values.len() {
+            let i = index(self.values[start].0);
+            let len = self.values[start..]
+                .iter()
+                .take_while(|(ts, _)| index(*ts) == i)
+                .count();
+            let end = start + len;
+
+            let violated = Violation::new(&self.values[start..end]).mean >= threshold;
+            match period {
+                Some((_, last)) if violated && last + 1 == i => {
+                    period = period.map(|(s, _)| (s, i))
+                }
+                _ => {
+                    if let Some((s, _)) = period.take() {
+                        violations.push(Violation::new(&self.values[s..start]));
+                    }
+                    if violated {
+                        period = Some((start, i));
+                    }
+                }
+            }
+            start = end;
+        }
+        if let Some((s, _)) = period {
+            violations.push(Violation::new(&self.values[s..]));
+        }
+
+        violations
+    }
+
+    // The @n highest latencies, with their ts
+    pub fn worst(&self, n: usize) -> Vec<(ClockTime, ClockTime)> {
+        let mut values = self.values.clone();
+        values.sort_by_key(|(ts, l)| (std::cmp::Reverse(*l), *ts));
+        values.truncate(n);
+        values
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+// Remove the instance numbers of element names ('queue12_src' -> 'queue_src') so
+// elements can be matched between runs of different pipelines
+pub fn normalize_name(name: &str) -> String {
+    name.split(' ')
+        .map(|part| {
+            part.split('_')
+                .map(|token| {
+                    if token.chars().all(|c| c.is_ascii_digit()) {
+                        token
+                    } else {
+                        token.trim_end_matches(|c: char| c.is_ascii_digit())
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("_")
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Change from @baseline to @candidate, in percent
+fn change(baseline: ClockTime, candidate: ClockTime) -> f64 {
+    let (baseline, candidate) = (baseline.nseconds() as f64, candidate.nseconds() as f64);
+    if baseline == 0.0 {
+        if candidate == 0.0 {
+            0.0
+        } else {
+            f64::INFINITY
+        }
+    } else {
+        (candidate - baseline) * 100.0 / baseline
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompareThresholds {
+    // Minimum change of the mean to report, in percent
+    pub mean: f64,
+    // Minimum change of the 90th and 99th percentiles to report, in percent
+    pub percentile: f64,
+    // Minimum Welch's t statistic for a change of the mean to be significant
+    pub t: f64,
+    // Minimum number of samples in both runs to compare them
+    pub min_samples: usize,
+}
+
+impl Default for CompareThresholds {
+    fn default() -> Self {
+        Self {
+            mean: 10.0,
+            percentile: 20.0,
+            t: 1.96,
+            min_samples: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Unchanged,
+    Regression,
+    Improvement,
+    NotEnoughSamples,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub name: String,
+    pub baseline: LatencyStats,
+    pub candidate: LatencyStats,
+}
+
+impl Comparison {
+    pub fn mean_change(&self) -> f64 {
+        change(self.baseline.mean, self.candidate.mean)
+    }
+
+    pub fn p90_change(&self) -> f64 {
+        change(self.baseline.p90, self.candidate.p90)
+    }
+
+    pub fn p99_change(&self) -> f64 {
+        change(self.baseline.p99, self.candidate.p99)
+    }
+
+    // Welch's t statistic of the difference of the means, positive if the candidate is slower
+    pub fn t(&self) -> f64 {
+        let (a, b) = (&self.baseline, &self.candidate);
+        let var = |s: &LatencyStats| (s.stddev.nseconds() as f64).powi(2) / s.count as f64;
+        let diff = b.mean.nseconds() as f64 - a.mean.nseconds() as f64;
+        let se = (var(a) + var(b)).sqrt();
+
+        if se == 0.0 {
+            if diff == 0.0 {
+                0.0
+            } else {
+                diff.signum() * f64::INFINITY
+            }
+        } else {
+            diff / se
+        }
+    }
+
+    pub fn verdict(&self, thresholds: &CompareThresholds) -> Verdict {
+        if self.baseline.count < thresholds.min_samples
+            || self.candidate.count < thresholds.min_samples
+        {
+            return Verdict::NotEnoughSamples;
+        }
+
+        let t = self.t();
+        let mean = self.mean_change();
+        let percentile = self.p90_change().max(self.p99_change());
+        if (t >= thresholds.t && mean > thresholds.mean) || percentile > thresholds.percentile {
+            return Verdict::Regression;
+        }
+
+        let percentile = self.p90_change().min(self.p99_change());
+        if (t <= -thresholds.t && mean < -thresholds.mean) || percentile < -thresholds.percentile {
+            return Verdict::Improvement;
+        }
+
+        Verdict::Unchanged
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LatencyComparison {
+    pub comparisons: Vec<Comparison>,
+    // Elements or paths measured in only one of the runs
+    pub only_baseline: Vec<String>,
+    pub only_candidate: Vec<String>,
+}
+
+impl LatencyComparison {
+    pub fn new(
+        baseline: &BTreeMap<String, Samples>,
+        candidate: &BTreeMap<String, Samples>,
+    ) -> Self {
+        let mut comparison = Self::default();
+
+        for (name, samples) in baseline.iter() {
+            match (samples.stats(), candidate.get(name).and_then(|c| c.stats())) {
+                (Some(baseline), Some(candidate)) => comparison.comparisons.push(Comparison {
+                    name: name.clone(),
+                    baseline,
+                    candidate,
+                }),
+                _ => comparison.only_baseline.push(name.clone()),
+            }
+        }
+        comparison.only_candidate = candidate
+            .keys()
+            .filter(|name| !baseline.contains_key(*name))
+            .cloned()
+            .collect();
+
+        comparison
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LatencyReport {
+    // Latency of each element, or of its src pad with older GStreamer versions
+    pub elements: BTreeMap<String, Samples>,
+    // Latency of each 'src -> sink' path of the pipeline
+    pub paths: BTreeMap<String, Samples>,
+}
+
+impl LatencyReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // @entry_ts is used if the record does not have a ts field
+    pub fn add(&mut self, record: &Record, entry_ts: ClockTime) {
+        match record {
+            Record::ElementLatency(latency) => {
+                self.elements
+                    .entry(latency.name().to_string())
+                    .or_default()
+                    .add(latency.ts.unwrap_or(entry_ts), latency.time);
+            }
+            Record::Latency(latency) => {
+                self.paths
+                    .entry(latency.path())
+                    .or_default()
+                    .add(latency.ts.unwrap_or(entry_ts), latency.time);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty() && self.paths.is_empty()
+    }
+
+    // Merge the samples of elements and paths having the same normalized name
+    pub fn normalized(&self) -> Self {
+        let normalize = |samples: &BTreeMap<String, Samples>| {
+            let mut normalized: BTreeMap<String, Samples> = BTreeMap::new();
+            for (name, s) in samples.iter() {
+                normalized
+                    .entry(normalize_name(name))
+                    .or_default()
+                    .values
+                    .extend_from_slice(&s.values);
+            }
+            normalized
+        };
+
+        Self {
+            elements: normalize(&self.elements),
+            paths: normalize(&self.paths),
+        }
+    }
+
+    // Statistics of all elements and paths, including their @worst highest latencies. Times are in ns.
+    pub fn to_json(&self, worst: usize) -> String {
+        let section = |samples: &BTreeMap<String, Samples>| -> String {
+            let items: Vec<String> = samples
+                .iter()
+                .filter_map(|(name, samples)| {
+                    let stats = samples.stats()?;
+                    let worst: Vec<String> = samples
+                        .worst(worst)
+                        .iter()
+                        .map(|(ts, l)| {
+                            format!("{{\"ts\": {}, \"latency\": {}}}", ts.nseconds(), l.nseconds())
+                        })
+                        .collect();
+                    Some(format!(
+                        "{}: {{\"count\": {}, \"min\": {}, \"max\": {}, \"max_ts\": {}, \"mean\": {}, \"stddev\": {}, \"p50\": {}, \"p90\": {}, \"p99\": {}, \"worst\": [{}]}}",
+                        json_string(name),
+                        stats.count,
+                        stats.min.nseconds(),
+                        stats.max.nseconds(),
+                        stats.max_ts.nseconds(),
+                        stats.mean.nseconds(),
+                        stats.stddev.nseconds(),
+                        stats.p50.nseconds(),
+                        stats.p90.nseconds(),
+                        stats.p99.nseconds(),
+                        worst.join(", ")
+                    ))
+                })
+                .collect();
+            format!("{{{}}}", items.join(", "))
+        };
+
+        format!(
+            "{{\"elements\": {}, \"paths\": {}}}",
+            section(&self.elements),
+            section(&self.paths)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracer::{ElementLatencyRecord, LatencyRecord};
+
+    #[test]
+    fn stats() {
+        let ms = ClockTime::from_mseconds;
+        let mut samples = Samples::new();
+        assert_eq!(samples.stats(), None);
+        assert!(samples.histogram(4).is_empty());
+
+        for i in 1..=100 {
+            samples.add(ms(i), ms(i % 10 + 1));
+        }
+        samples.add(ms(200), ms(50));
+
+        let stats = samples.stats().unwrap();
+        assert_eq!(stats.count, 101);
+        assert_eq!(stats.min, ms(1));
+        assert_eq!(stats.max, ms(50));
+        assert_eq!(stats.max_ts, ms(200));
+        assert_eq!(stats.p50, ms(6));
+        assert_eq!(stats.p90, ms(10));
+        assert_eq!(stats.p99, ms(10));
+        assert_eq!(stats.mean, ClockTime::from_nseconds(5_940_594));
+        assert!(stats.stddev > ms(4) && stats.stddev < ms(6));
+
+        assert_eq!(
+            samples.worst(3),
+            vec![(ms(200), ms(50)), (ms(9), ms(10)), (ms(19), ms(10))]
+        );
+
+        let histogram = samples.histogram(7);
+        assert_eq!(histogram.len(), 7);
+        assert_eq!(histogram[0].start, ms(1));
+        assert_eq!(histogram[0].end, ms(8));
+        assert_eq!(histogram[0].count, 70);
+        assert_eq!(histogram[1].count, 30);
+        assert_eq!(histogram[6].count, 1);
+        assert_eq!(histogram[6].end, ms(50));
+        assert_eq!(histogram.iter().map(|b| b.count).sum::<usize>(), 101);
+    }
+
+    #[test]
+    fn report() {
+        let mut report = LatencyReport::new();
+        assert!(report.is_empty());
+        report.add(
+            &Record::ElementLatency(ElementLatencyRecord {
+                ts: None,
+                element: Some("dec\"0".to_string()),
+                src: "dec0_src".to_string(),
+                time: ClockTime::from_nseconds(10),
+            }),
+            ClockTime::from_nseconds(100),
+        );
+        report.add(
+            &Record::Latency(LatencyRecord {
+                ts: Some(ClockTime::from_nseconds(200)),
+                src: "src_src".to_string(),
+                sink: "sink_sink".to_string(),
+                time: ClockTime::from_nseconds(20),
+            }),
+            ClockTime::from_nseconds(300),
+        );
+
+        assert_eq!(
+            report.elements["dec\"0"].values(),
+            &[(ClockTime::from_nseconds(100), ClockTime::from_nseconds(10))]
+        );
+        assert_eq!(report.paths["src_src -> sink_sink"].len(), 1);
+
+        // element is not logged by GStreamer < 1.16
+        let record = Record::ElementLatency(ElementLatencyRecord {
+            ts: None,
+            element: None,
+            src: "dec0_src".to_string(),
+            time: ClockTime::from_nseconds(30),
+        });
+        assert_eq!(
+            record.latency(),
+            Some(("dec0_src".to_string(), ClockTime::from_nseconds(30)))
+        );
+        assert_eq!(
+            report.to_json(1),
+            r#"{"elements": {"dec\"0": {"count": 1, "min": 10, "max": 10, "max_ts": 100, "mean": 10, "stddev": 0, "p50": 10, "p90": 10, "p99": 10, "worst": [{"ts": 100, "latency": 10}]}}, "paths": {"src_src -> sink_sink": {"count": 1, "min": 20, "max": 20, "max_ts": 200, "mean": 20, "stddev": 0, "p50": 20, "p90": 20, "p99": 20, "worst": [{"ts": 200, "latency": 20}]}}}"#
+        );
+    }
+
+    #[test]
+    fn normalize() {
+        assert_eq!(normalize_name("queue12"), "queue");
+        assert_eq!(normalize_name("x264enc0"), "x264enc");
+        assert_eq!(
+            normalize_name("videotestsrc0_src -> mp4mux1_video_0"),
+            "videotestsrc_src -> mp4mux_video_0"
+        );
+
+        let mut report = LatencyReport::new();
+        for (name, time) in [("queue0", 1), ("queue1", 2), ("dec0", 3)] {
+            report.add(
+                &Record::ElementLatency(ElementLatencyRecord {
+                    ts: None,
+                    element: Some(name.to_string()),
+                    src: String::new(),
+                    time: ClockTime::from_nseconds(time),
+                }),
+                ClockTime::ZERO,
+            );
+        }
+        let normalized = report.normalized();
+        assert_eq!(
+            normalized.elements.keys().collect::<Vec<_>>(),
+            vec!["dec", "queue"]
+        );
+        assert_eq!(normalized.elements["queue"].len(), 2);
+    }
+
+    fn samples(values: &[u64]) -> Samples {
+        let mut samples = Samples::new();
+        for (i, v) in values.iter().enumerate() {
+            samples.add(
+                ClockTime::from_mseconds(i as u64),
+                ClockTime::from_mseconds(*v),
+            );
+        }
+        samples
+    }
+
+    #[test]
+    fn compare() {
+        let baseline: Vec<u64> = (0..100).map(|i| 10 + i % 3).collect();
+        let slower: Vec<u64> = (0..100).map(|i| 12 + i % 3).collect();
+        let spikes: Vec<u64> = (0..100)
+            .map(|i| if i % 20 == 0 { 30 } else { 10 + i % 3 })
+            .collect();
+
+        let mut a = BTreeMap::new();
+        let mut b = BTreeMap::new();
+        for (name, base, candidate) in [
+            ("same", &baseline, &baseline),
+            ("slower", &baseline, &slower),
+            ("faster", &slower, &baseline),
+            ("spikes", &baseline, &spikes),
+        ] {
+            a.insert(name.to_string(), samples(base));
+            b.insert(name.to_string(), samples(candidate));
+        }
+        a.insert("few".to_string(), samples(&[1, 2]));
+        b.insert("few".to_string(), samples(&[10, 20]));
+        a.insert("removed".to_string(), samples(&[1]));
+        b.insert("added".to_string(), samples(&[1]));
+
+        let comparison = LatencyComparison::new(&a, &b);
+        assert_eq!(comparison.only_baseline, vec!["removed".to_string()]);
+        assert_eq!(comparison.only_candidate, vec!["added".to_string()]);
+
+        let thresholds = CompareThresholds::default();
+        let verdicts: Vec<(&str, Verdict)> = comparison
+            .comparisons
+            .iter()
+            .map(|c| (c.name.as_str(), c.verdict(&thresholds)))
+            .collect();
+        assert_eq!(
+            verdicts,
+            vec![
+                ("faster", Verdict::Improvement),
+                ("few", Verdict::NotEnoughSamples),
+                ("same", Verdict::Unchanged),
+                ("slower", Verdict::Regression),
+                ("spikes", Verdict::Regression),
+            ]
+        );
+
+        let slower = &comparison.comparisons[3];
+        assert!(slower.t() > 10.0);
+        assert!((slower.mean_change() - 18.2).abs() < 0.1);
+        let spikes = &comparison.comparisons[4];
+        assert_eq!(spikes.p99_change(), 150.0);
+        // regression detected on the percentiles only
+        assert!(spikes.mean_change() < thresholds.mean);
+    }
+
+    #[test]
+    fn violations() {
+        let ms = ClockTime::from_mseconds;
+        let mut samples = Samples::new();
+        assert!(samples.sustained(ms(10), 1, ClockTime::ZERO).is_empty());
+        assert!(samples.windowed(ms(10), ms(100)).is_empty());
+
+        for (ts, l) in [
+            (0, 5),
+            (10, 20),
+            (20, 30),
+            (30, 5),
+            (40, 20),
+            (50, 20),
+            (60, 10),
+            (200, 1),
+            (250, 40),
+            (310, 9),
+            (420, 5),
+        ] {
+            samples.add(ms(ts), ms(l));
+        }
+
+        let sustained = samples.sustained(ms(10), 2, ClockTime::ZERO);
+        assert_eq!(
+            sustained,
+            vec![
+                Violation {
+                    start: ms(10),
+                    end: ms(20),
+                    samples: 2,
+                    mean: ms(25),
+                    max: ms(30)
+                },
+                Violation {
+                    start: ms(40),
+                    end: ms(60),
+                    samples: 3,
+                    mean: ClockTime::from_nseconds(16_666_666),
+                    max: ms(20)
+                },
+            ]
+        );
+        assert_eq!(sustained[1].duration(), ms(20));
+        assert_eq!(samples.sustained(ms(10), 2, ms(15)).len(), 1);
+        assert_eq!(samples.sustained(ms(10), 3, ClockTime::ZERO).len(), 1);
+        assert_eq!(samples.sustained(ms(10), 1, ClockTime::ZERO).len(), 3);
+
+        // windows: [0, 100) mean 15.7, [200, 300) mean 20.5, [300, 400) 9, [400, 500) 5
+        let windowed = samples.windowed(ms(10), ms(100));
+        assert_eq!(windowed.len(), 2);
+        assert_eq!(windowed[0].start, ms(0));
+        assert_eq!(windowed[0].end, ms(60));
+        assert_eq!(windowed[1].start, ms(200));
+        assert_eq!(windowed[1].end, ms(250));
+        assert_eq!(windowed[1].samples, 2);
+        assert_eq!(windowed[1].max, ms(40));
+        assert_eq!(samples.windowed(ms(20), ms(100)).len(), 1);
+    }
+}
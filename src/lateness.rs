@@ -0,0 +1,344 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Lateness of the buffers reaching sinks.
+//!
+//! Generate logs with: GST_DEBUG="GST_TRACER:7,basesink:5,GST_CLOCK:5,GST_BIN:5" GST_TRACERS=stats
+//!
+//! The running time of the buffers is computed from the segments configured
+//! on sinks ('basesink' category) and compared with the running time of the
+//! pipeline when they reach the sink, deduced from the base time of the sink
+//! ('GST_CLOCK' category) and the latency of the pipeline ('GST_BIN' category).
+
+use std::collections::{BTreeMap, HashMap};
+
+use gst::ClockTime;
+use gstreamer as gst;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::tracer::{BufferRecord, Record, Topology};
+use crate::{parse_time, Entry};
+
+lazy_static! {
+    static ref SEGMENT_RE: Regex = Regex::new(
+        r"time segment start=(?P<start>[0-9:.]+), offset=(?P<offset>[0-9:.]+), stop=(?P<stop>[0-9:.]+), rate=(?P<rate>-?[0-9.]+), applied_rate=-?[0-9.]+, flags=0x[0-9a-fA-F]+, time=(?P<time>[0-9:.]+), base=(?P<base>[0-9:.]+)"
+    )
+    .unwrap();
+    static ref BASE_TIME_RE: Regex = Regex::new(r"set base_time=(?P<time>[0-9:.]+)").unwrap();
+    static ref LATENCY_RE: Regex =
+        Regex::new(r"configured latency of (?P<time>[0-9:.]+)").unwrap();
+}
+
+// GST_CLOCK_TIME_NONE is displayed as 99:99:99.999999999
+fn parse_clock_time(s: &str) -> Option<Option<ClockTime>> {
+    if s.starts_with("99:99:99") {
+        Some(None)
+    } else {
+        parse_time(s).ok().map(Some)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    pub start: ClockTime,
+    pub offset: ClockTime,
+    pub stop: Option<ClockTime>,
+    pub rate: f64,
+    pub time: ClockTime,
+    pub base: ClockTime,
+}
+
+impl Segment {
+    // Parse a time segment displayed using GST_SEGMENT_FORMAT
+    pub fn parse(s: &str) -> Option<Self> {
+        let caps = SEGMENT_RE.captures(s)?;
+        let time = |name: &str| parse_clock_time(&caps[name]);
+
+        Some(Self {
+            start: time("start")??,
+            offset: time("offset")??,
+            stop: time("stop")?,
+            rate: caps["rate"].parse().ok()?,
+            time: time("time")??,
+            base: time("base")??,
+        })
+    }
+
+    // Same as gst_segment_to_running_time(), None if @position is outside of the segment
+    pub fn to_running_time(&self, position: ClockTime) -> Option<ClockTime> {
+        if position < self.start || self.stop.is_some_and(|stop| position > stop) {
+            return None;
+        }
+
+        let result = if self.rate > 0.0 {
+            position.checked_sub(self.start + self.offset)?
+        } else {
+            self.stop?.checked_sub(self.offset)?.checked_sub(position)?
+        };
+
+        let rate = self.rate.abs();
+        let result = if rate != 1.0 {
+            ClockTime::from_nseconds((result.nseconds() as f64 / rate) as u64)
+        } else {
+            result
+        };
+
+        Some(result + self.base)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferLateness {
+    pub ts: ClockTime,
+    pub pts: ClockTime,
+    pub running_time: ClockTime,
+    // In ns, positive if the buffer reached the sink after the time it should have been rendered
+    pub lateness: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QosEvent {
+    pub ts: ClockTime,
+    // Lateness of the last buffer received by the sink
+    pub lateness: Option<i64>,
+}
+
+#[derive(Debug, Default)]
+pub struct SinkLateness {
+    pub buffers: Vec<BufferLateness>,
+    pub qos: Vec<QosEvent>,
+    // Buffers whose lateness could not be computed (no PTS, outside of the segment, no base time)
+    pub unknown: u64,
+    segment: Option<Segment>,
+}
+
+impl SinkLateness {
+    pub fn max(&self) -> Option<i64> {
+        self.buffers.iter().map(|b| b.lateness).max()
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        if self.buffers.is_empty() {
+            None
+        } else {
+            let sum: i64 = self.buffers.iter().map(|b| b.lateness).sum();
+            Some(sum as f64 / self.buffers.len() as f64)
+        }
+    }
+
+    // Number of buffers later than @threshold
+    pub fn late(&self, threshold: ClockTime) -> usize {
+        self.buffers
+            .iter()
+            .filter(|b| b.lateness > threshold.nseconds() as i64)
+            .count()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Lateness {
+    sinks: BTreeMap<String, SinkLateness>,
+    base_times: HashMap<String, ClockTime>,
+    // Difference between the clock time and the ts of the entries. Assume the
+    // running time is 0 when the base time is set for the first time.
+    clock_offset: Option<i64>,
+    latency: ClockTime,
+}
+
+impl Lateness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Handle the entries configuring the segments, base times and latency
+    pub fn add_entry(&mut self, entry: &Entry) {
+        let element = entry.object.as_ref().and_then(|o| o.element());
+
+        if entry.category == "basesink" {
+            if let (Some(element), Some(segment)) = (element, Segment::parse(&entry.message)) {
+                self.sinks.entry(element.to_string()).or_default().segment = Some(segment);
+            }
+        } else if let Some(caps) = BASE_TIME_RE.captures(&entry.message) {
+            if let (Some(element), Ok(time)) = (element, parse_time(&caps["time"])) {
+                if self.clock_offset.is_none() && time > ClockTime::ZERO {
+                    self.clock_offset = Some(time.nseconds() as i64 - entry.ts.nseconds() as i64);
+                }
+                self.base_times.insert(element.to_string(), time);
+            }
+        } else if let Some(caps) = LATENCY_RE.captures(&entry.message) {
+            if let Ok(latency) = parse_time(&caps["time"]) {
+                self.latency = latency;
+            }
+        }
+    }
+
+    fn buffer_lateness(
+        &self,
+        sink: &str,
+        segment: Option<Segment>,
+        buffer: &BufferRecord,
+    ) -> Option<BufferLateness> {
+        let pts = buffer.pts?;
+        let running_time = segment?.to_running_time(pts)?;
+        let base_time = self.base_times.get(sink)?;
+        let now = buffer.ts.nseconds() as i64 + self.clock_offset? - base_time.nseconds() as i64;
+
+        Some(BufferLateness {
+            ts: buffer.ts,
+            pts,
+            running_time,
+            lateness: now - (running_time + self.latency).nseconds() as i64,
+        })
+    }
+
+    // Handle the buffers reaching sinks and the QoS events they send
+    pub fn add_record(&mut self, record: &Record, topology: &Topology) {
+        match record {
+            Record::Buffer(buffer) => {
+                let name = topology.element_name(buffer.peer_element_ix);
+                let segment = match self.sinks.get(&name) {
+                    Some(sink) => sink.segment,
+                    None => return,
+                };
+                let lateness = self.buffer_lateness(&name, segment, buffer);
+                let sink = self.sinks.get_mut(&name).unwrap();
+
+                match lateness {
+                    Some(lateness) => sink.buffers.push(lateness),
+                    None => sink.unknown += 1,
+                }
+            }
+            Record::Event(event) if event.name == "qos" => {
+                let name = topology.element_name(event.element_ix);
+                if let Some(sink) = self.sinks.get_mut(&name) {
+                    let lateness = sink.buffers.last().map(|b| b.lateness);
+                    sink.qos.push(QosEvent {
+                        ts: event.ts,
+                        lateness,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn latency(&self) -> ClockTime {
+        self.latency
+    }
+
+    pub fn sink(&self, name: &str) -> Option<&SinkLateness> {
+        self.sinks.get(name)
+    }
+
+    pub fn sinks(&self) -> impl Iterator<Item = (&str, &SinkLateness)> {
+        self.sinks.iter().map(|(name, sink)| (name.as_str(), sink))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::buffer;
+    use crate::tracer::EventRecord;
+
+    const SEGMENT: &str = "time segment start=0:00:01.000000000, offset=0:00:00.000000000, stop=99:99:99.999999999, rate=1.000000, applied_rate=1.000000, flags=0x00, time=0:00:00.000000000, base=0:00:00.500000000, position 0:00:00.000000000, duration 99:99:99.999999999";
+
+    #[test]
+    fn segment() {
+        let ms = ClockTime::from_mseconds;
+        let segment = Segment::parse(SEGMENT).unwrap();
+        assert_eq!(segment.start, ms(1000));
+        assert_eq!(segment.stop, None);
+        assert_eq!(segment.rate, 1.0);
+        assert_eq!(segment.base, ms(500));
+        assert_eq!(segment.to_running_time(ms(1200)), Some(ms(700)));
+        assert_eq!(segment.to_running_time(ms(900)), None);
+
+        let segment = Segment {
+            stop: Some(ms(3000)),
+            rate: 2.0,
+            ..segment
+        };
+        assert_eq!(segment.to_running_time(ms(1200)), Some(ms(600)));
+        assert_eq!(segment.to_running_time(ms(3001)), None);
+
+        let segment = Segment {
+            rate: -1.0,
+            ..segment
+        };
+        assert_eq!(segment.to_running_time(ms(2000)), Some(ms(1500)));
+
+        assert_eq!(Segment::parse("bytes segment start=0"), None);
+    }
+
+    #[test]
+    fn lateness() {
+        let mut topology = Topology::new();
+        for (ix, name) in [(0, "src"), (1, "sink")] {
+            topology.add(&Record::NewElement {
+                ix,
+                parent_ix: None,
+                name: name.to_string(),
+                type_name: String::new(),
+                is_bin: false,
+            });
+        }
+
+        let mut lateness = Lateness::new();
+        for line in [
+            "0:00:01.000000000  8874 0x558951015c00 DEBUG                basesink gstbasesink.c:4453:gst_base_sink_default_event:<sink> configured segment time segment start=0:00:00.000000000, offset=0:00:00.000000000, stop=99:99:99.999999999, rate=1.000000, applied_rate=1.000000, flags=0x00, time=0:00:00.000000000, base=0:00:00.000000000, position 0:00:00.000000000, duration 99:99:99.999999999",
+            "0:00:02.000000000  8874 0x558951015c00 DEBUG               GST_CLOCK gstelement.c:767:gst_element_set_base_time:<sink> set base_time=1:00:00.000000000, old 0:00:00.000000000",
+            "0:00:02.000000000  8874 0x558951015c00 DEBUG                 GST_BIN gstbin.c:2775:gst_bin_do_latency_func:<pipeline0> configured latency of 0:00:00.100000000",
+        ] {
+            lateness.add_entry(&Entry::new(line).unwrap());
+        }
+        assert_eq!(lateness.latency(), ClockTime::from_mseconds(100));
+
+        let ms = ClockTime::from_mseconds;
+        for (peer_element_ix, ts, pts) in [
+            // not sent to a sink
+            (0, 2000, Some(0)),
+            // early
+            (1, 2050, Some(0)),
+            (1, 2300, Some(100)),
+            (1, 2310, None),
+        ] {
+            let record = buffer(ms(ts), 2, 0)
+                .peer(3, peer_element_ix)
+                .pts(pts.map(ms))
+                .record();
+            lateness.add_record(&record, &topology);
+        }
+        lateness.add_record(
+            &Record::Event(EventRecord {
+                ts: ClockTime::from_mseconds(2320),
+                pad_ix: 3,
+                element_ix: 1,
+                name: "qos".to_string(),
+            }),
+            &topology,
+        );
+
+        let sink = lateness.sink("sink").unwrap();
+        let values: Vec<_> = sink.buffers.iter().map(|b| b.lateness).collect();
+        assert_eq!(values, vec![-50_000_000, 100_000_000]);
+        assert_eq!(sink.unknown, 1);
+        assert_eq!(sink.max(), Some(100_000_000));
+        assert_eq!(sink.mean(), Some(25_000_000.0));
+        assert_eq!(sink.late(ClockTime::from_mseconds(20)), 1);
+        assert_eq!(
+            sink.qos,
+            vec![QosEvent {
+                ts: ClockTime::from_mseconds(2320),
+                lateness: Some(100_000_000)
+            }]
+        );
+        assert_eq!(lateness.sinks().count(), 1);
+    }
+}
@@ -7,21 +7,51 @@
 // except according to those terms.
 
 use itertools::join;
+use std::borrow::Cow;
 use std::fmt;
 use std::io::BufRead;
 use std::io::BufReader;
-use std::io::Lines;
+use std::io::Chain;
+use std::io::Cursor;
 use std::io::Read;
 use std::str;
 use std::str::FromStr;
 
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
 use gst::{ClockTime, DebugLevel, Structure};
 use gstreamer as gst;
 use lazy_static::lazy_static;
 use regex::Regex;
 use thiserror::Error;
 
-#[derive(Debug, PartialEq)]
+use clock::ClockConverter;
+use format::{detect_format, Format, LineFormat, NativeFormat};
+
+pub mod anonymize;
+pub mod clock;
+pub mod diff;
+pub mod flow;
+pub mod format;
+pub mod frames;
+pub mod frequency;
+pub mod hwtrace;
+pub mod index;
+pub mod latency;
+pub mod lateness;
+pub mod merge;
+pub mod normalize;
+pub mod omx;
+pub mod query;
+pub mod report;
+pub mod summary;
+pub mod threshold;
+pub mod tracer;
+pub mod writer;
+
+#[cfg(test)]
+mod test_support;
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum TimestampField {
     Hour,
     Minute,
@@ -29,7 +59,7 @@ pub enum TimestampField {
     SubSecond,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     Timestamp { field: Option<TimestampField> },
     PID,
@@ -57,11 +87,35 @@ pub enum ParsingError {
     MissingLocation,
     #[error("invalid line number: {}", line)]
     InvalidLineNumber { line: String },
+    #[error("invalid date: {}", datetime)]
+    InvalidDateTime { datetime: String },
+    #[error("line does not match {} format", format)]
+    FormatMismatch { format: String },
+}
+
+impl ParsingError {
+    // Name of the variant, to group errors regardless of their details
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ParsingError::InvalidDebugLevel { .. } => "InvalidDebugLevel",
+            ParsingError::InvalidTimestamp { .. } => "InvalidTimestamp",
+            ParsingError::MissingToken { .. } => "MissingToken",
+            ParsingError::InvalidPID { .. } => "InvalidPID",
+            ParsingError::MissingLocation => "MissingLocation",
+            ParsingError::InvalidLineNumber { .. } => "InvalidLineNumber",
+            ParsingError::InvalidDateTime { .. } => "InvalidDateTime",
+            ParsingError::FormatMismatch { .. } => "FormatMismatch",
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Entry {
+    // running time of the entry; for logs only having absolute times, the offset from the
+    // first entry computed by the parser (zero when parsed from a single line)
     pub ts: ClockTime,
+    // absolute time of the entry, if logged
+    pub datetime: Option<DateTime<FixedOffset>>,
     pub pid: u32,
     pub thread: String,
     pub level: DebugLevel,
@@ -70,7 +124,120 @@ pub struct Entry {
     pub line: u32,
     pub function: String,
     pub message: String,
-    pub object: Option<String>,
+    pub object: Option<ObjectRef>,
+    // size of the GStreamer log line the entry has been parsed from, without the line break
+    pub len: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum ObjectRef {
+    // Named object such as an element ("v4l2src0") or a pad and its parent ("v4l2src0:src")
+    Named {
+        name: String,
+        pad: Option<String>,
+    },
+    // Unnamed object, logged with its address and optionally its type ("GstObject@0x55895101d040").
+    // The address is kept as text so it can be replaced by a symbolic one ("GstObject@obj#1").
+    Address {
+        type_name: Option<String>,
+        address: String,
+    },
+}
+
+fn is_address(s: &str) -> bool {
+    s.strip_prefix("0x")
+        .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+        .is_some()
+}
+
+impl ObjectRef {
+    // Name of the object, or of the parent of the pad
+    pub fn element(&self) -> Option<&str> {
+        match self {
+            ObjectRef::Named { name, .. } => Some(name),
+            ObjectRef::Address { .. } => None,
+        }
+    }
+
+    pub fn pad(&self) -> Option<&str> {
+        match self {
+            ObjectRef::Named { pad, .. } => pad.as_deref(),
+            ObjectRef::Address { .. } => None,
+        }
+    }
+
+    pub fn address(&self) -> Option<&str> {
+        match self {
+            ObjectRef::Named { .. } => None,
+            ObjectRef::Address { address, .. } => Some(address),
+        }
+    }
+
+    pub fn type_name(&self) -> Option<&str> {
+        match self {
+            ObjectRef::Named { .. } => None,
+            ObjectRef::Address { type_name, .. } => type_name.as_deref(),
+        }
+    }
+}
+
+impl From<&str> for ObjectRef {
+    fn from(s: &str) -> Self {
+        if is_address(s) {
+            return ObjectRef::Address {
+                type_name: None,
+                address: s.to_string(),
+            };
+        }
+
+        if let Some((type_name, address)) = s.split_once('@') {
+            if is_address(address) {
+                return ObjectRef::Address {
+                    type_name: Some(type_name.to_string()),
+                    address: address.to_string(),
+                };
+            }
+        }
+
+        match s.split_once(':') {
+            Some((name, pad)) => ObjectRef::Named {
+                name: name.to_string(),
+                pad: Some(pad.to_string()),
+            },
+            None => ObjectRef::Named {
+                name: s.to_string(),
+                pad: None,
+            },
+        }
+    }
+}
+
+impl FromStr for ObjectRef {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(ObjectRef::from(s))
+    }
+}
+
+impl fmt::Display for ObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjectRef::Named { name, pad: None } => write!(f, "{}", name),
+            ObjectRef::Named {
+                name,
+                pad: Some(pad),
+            } => write!(f, "{}:{}", name, pad),
+            ObjectRef::Address {
+                type_name: None,
+                address,
+            } => write!(f, "{}", address),
+            ObjectRef::Address {
+                type_name: Some(type_name),
+                address,
+            } => write!(f, "{}@{}", type_name, address),
+        }
+    }
 }
 
 fn parse_debug_level(s: &str) -> Result<DebugLevel, ParsingError> {
@@ -154,7 +321,36 @@ fn parse_time(ts: &str) -> Result<ClockTime, ParsingError> {
     Ok(ClockTime::from_seconds(h * 60 * 60 + m * 60 + secs) + ClockTime::from_nseconds(subsecs))
 }
 
-fn split_location(location: &str) -> Result<(String, u32, String, Option<String>), ParsingError> {
+fn is_date(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() >= 10
+        && b[4] == b'-'
+        && b[7] == b'-'
+        && b[..10]
+            .iter()
+            .enumerate()
+            .all(|(i, c)| i == 4 || i == 7 || c.is_ascii_digit())
+}
+
+// Parse ISO-8601 dates such as 2024-02-09T10:11:12.123456+01:00 or
+// 2024-02-09 10:11:12.123456. Dates without time zone are considered as UTC.
+fn parse_datetime(s: &str) -> Option<DateTime<FixedOffset>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(dt);
+    }
+    if let Ok(dt) = DateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f%z") {
+        return Some(dt);
+    }
+
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+        .map(|dt| dt.and_utc().fixed_offset())
+}
+
+type Location = (String, u32, String, Option<ObjectRef>);
+
+fn split_location(location: &str) -> Result<Location, ParsingError> {
     let mut split = location.splitn(4, ':');
     let file = split
         .next()
@@ -178,13 +374,9 @@ fn split_location(location: &str) -> Result<(String, u32, String, Option<String>
 
     let object_name = {
         if !object.is_empty() {
-            let object = object
-                .to_string()
-                .trim_start_matches('<')
-                .trim_end_matches('>')
-                .to_string();
+            let object = object.trim_start_matches('<').trim_end_matches('>');
 
-            Some(object)
+            Some(ObjectRef::from(object))
         } else {
             None
         }
@@ -193,19 +385,53 @@ fn split_location(location: &str) -> Result<(String, u32, String, Option<String>
     Ok((file.to_string(), line, function.to_string(), object_name))
 }
 
+fn strip_colors(line: &str) -> Cow<'_, str> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new("\x1b\\[[0-9;]*m").unwrap();
+    }
+    RE.replace_all(line, "")
+}
+
 impl Entry {
     fn new(line: &str) -> Result<Entry, ParsingError> {
-        // Strip color codes
-        lazy_static! {
-            static ref RE: Regex = Regex::new("\x1b\\[[0-9;]*m").unwrap();
-        }
-        let line = RE.replace_all(line, "");
+        let len = line.len();
+        let line = strip_colors(line);
 
         let mut it = line.split(' ');
         let ts_str = it.next().ok_or(ParsingError::MissingToken {
             t: Token::Timestamp { field: None },
         })?;
-        let ts = parse_time(ts_str)?;
+
+        let (ts, datetime) = if is_date(ts_str) {
+            // Absolute time, either as a single ISO-8601 token or as separated date and time
+            let datetime_str = if ts_str.contains('T') {
+                ts_str.to_string()
+            } else {
+                let time = it
+                    .find(|x| !x.is_empty())
+                    .ok_or(ParsingError::MissingToken {
+                        t: Token::Timestamp { field: None },
+                    })?;
+                format!("{} {}", ts_str, time)
+            };
+            let datetime = parse_datetime(&datetime_str).ok_or(ParsingError::InvalidDateTime {
+                datetime: datetime_str,
+            })?;
+
+            // The running time may follow, otherwise leave it to zero and let the parser
+            // compute it from the datetime of the first entry.
+            let ts = match it.clone().find(|x| !x.is_empty()) {
+                Some(ts_str) if ts_str.contains(':') => {
+                    it.find(|x| !x.is_empty());
+                    parse_time(ts_str)?
+                }
+                _ => ClockTime::ZERO,
+            };
+
+            (ts, Some(datetime))
+        } else {
+            (parse_time(ts_str)?, None)
+        };
 
         let mut it = it.skip_while(|x| x.is_empty());
         let pid_str = it
@@ -240,6 +466,7 @@ impl Entry {
 
         Ok(Entry {
             ts,
+            datetime,
             pid,
             thread,
             level,
@@ -249,6 +476,7 @@ impl Entry {
             function,
             object,
             message,
+            len,
         })
     }
 
@@ -257,32 +485,112 @@ impl Entry {
     }
 }
 
+// Written using the native GStreamer layout
 impl fmt::Display for Entry {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{}  {} {} {:?} {} {}:{}:{}:<{}> {}",
-            self.ts,
-            self.pid,
-            self.thread,
-            self.level,
-            self.category,
-            self.file,
-            self.line,
-            self.function,
-            self.object.clone().unwrap_or_default(),
-            self.message
-        )
+        write!(f, "{}", writer::format_entry(self, false))
     }
 }
 
 pub struct ParserIterator<R: Read> {
-    lines: Lines<BufReader<R>>,
+    // lines read while detecting the format are replayed first
+    reader: Chain<Cursor<Vec<u8>>, BufReader<R>>,
+    format: Box<dyn LineFormat>,
+    finished: bool,
+    // datetime of the first entry, origin of the running time of entries not logging it
+    start: Option<ClockConverter>,
 }
 
 impl<R: Read> ParserIterator<R> {
-    fn new(lines: Lines<BufReader<R>>) -> Self {
-        Self { lines }
+    fn new(reader: Chain<Cursor<Vec<u8>>, BufReader<R>>, format: Box<dyn LineFormat>) -> Self {
+        Self {
+            reader,
+            format,
+            finished: false,
+            start: None,
+        }
+    }
+
+    pub fn format_name(&self) -> &str {
+        self.format.name()
+    }
+
+    // Iterate over the parsing result of each line, including the ones which could not be parsed
+    pub fn results(&mut self) -> impl Iterator<Item = Result<Entry, ParsingError>> + '_ {
+        std::iter::from_fn(move || self.next_result())
+    }
+
+    fn read_line(&mut self) -> Option<String> {
+        let mut line = Vec::new();
+        if self.reader.read_until(b'\n', &mut line).unwrap() == 0 {
+            return None;
+        }
+
+        if line.ends_with(b"\n") {
+            line.pop();
+            if line.ends_with(b"\r") {
+                line.pop();
+            }
+        }
+
+        Some(String::from_utf8_lossy(&line).into_owned())
+    }
+
+    // Binary fields are a 64-bit little endian length followed by the payload and a newline
+    fn read_binary(&mut self) -> Vec<u8> {
+        let mut len = [0; 8];
+        let mut data = Vec::new();
+
+        if self.reader.read_exact(&mut len).is_ok() {
+            self.reader
+                .by_ref()
+                .take(u64::from_le_bytes(len))
+                .read_to_end(&mut data)
+                .unwrap();
+            // trailing newline
+            let _ = self.reader.read_exact(&mut [0u8; 1]);
+        }
+
+        data
+    }
+
+    fn next_result(&mut self) -> Option<Result<Entry, ParsingError>> {
+        while !self.finished {
+            let res = if self.format.expects_binary() {
+                let data = self.read_binary();
+                self.format.parse_binary(&data)
+            } else {
+                match self.read_line() {
+                    Some(line) => self.format.parse_line(&line),
+                    None => {
+                        self.finished = true;
+                        return self.format.finish();
+                    }
+                }
+            };
+
+            if let Some(res) = res {
+                return Some(res.map(|entry| self.rebase(entry)));
+            }
+        }
+
+        None
+    }
+
+    // Entries having a datetime but no running time have a zero ts, use the offset from
+    // the first entry instead. Unlike the time of the day this keeps increasing across midnight.
+    fn rebase(&mut self, mut entry: Entry) -> Entry {
+        if let Some(datetime) = entry.datetime {
+            let start = *self
+                .start
+                .get_or_insert_with(|| ClockConverter::new(ClockTime::ZERO, datetime));
+
+            if entry.ts == ClockTime::ZERO {
+                entry.ts = start.to_running_time(&datetime).unwrap_or(ClockTime::ZERO);
+            }
+        }
+
+        entry
     }
 }
 
@@ -290,22 +598,52 @@ impl<R: Read> Iterator for ParserIterator<R> {
     type Item = Entry;
 
     fn next(&mut self) -> Option<Entry> {
-        match self.lines.next() {
-            None => None,
-            Some(line) => match Entry::new(&line.unwrap()) {
-                Ok(entry) => Some(entry),
-                Err(_err) => self.next(),
-            },
+        while let Some(res) = self.next_result() {
+            if let Ok(entry) = res {
+                return Some(entry);
+            }
         }
+
+        None
     }
 }
 
 pub fn parse<R: Read>(r: R) -> ParserIterator<R> {
+    parse_with_format(r, Box::new(NativeFormat))
+}
+
+pub fn parse_with_format<R: Read>(r: R, format: Box<dyn LineFormat>) -> ParserIterator<R> {
     gst::init().expect("Failed to initialize gst");
 
     let file = BufReader::new(r);
 
-    ParserIterator::new(file.lines())
+    ParserIterator::new(Cursor::new(Vec::new()).chain(file), format)
+}
+
+// Number of lines inspected to detect the format of logs
+const DETECT_LINES: usize = 100;
+
+// Parse logs using the first format from @formats detected in its first lines,
+// defaulting to the native format.
+pub fn parse_auto_with<R: Read>(r: R, formats: Vec<Box<dyn LineFormat>>) -> ParserIterator<R> {
+    gst::init().expect("Failed to initialize gst");
+
+    let mut file = BufReader::new(r);
+    let mut head = Vec::new();
+    for _ in 0..DETECT_LINES {
+        if file.read_until(b'\n', &mut head).unwrap() == 0 {
+            break;
+        }
+    }
+
+    let lines = String::from_utf8_lossy(&head).into_owned();
+    let format = detect_format(lines.lines(), formats).unwrap_or_else(|| Box::new(NativeFormat));
+
+    ParserIterator::new(Cursor::new(head).chain(file), format)
+}
+
+pub fn parse_auto<R: Read>(r: R) -> ParserIterator<R> {
+    parse_auto_with(r, Format::all().iter().map(|f| f.line_format()).collect())
 }
 
 #[cfg(test)]
@@ -332,10 +670,14 @@ mod tests {
             entry.message,
             "Initializing GStreamer Core Library version 1.10.4"
         );
+        assert_eq!(
+            entry.to_string(),
+            "0:00:00.007773544  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Initializing GStreamer Core Library version 1.10.4"
+        );
 
         let entry = parsed.nth(3).expect("3th entry missing");
         assert_eq!(entry.message, "0x55895101d040 ref 1->2");
-        assert_eq!(entry.object, Some("allocatorsysmem0".to_string()));
+        assert_eq!(entry.object, Some(ObjectRef::from("allocatorsysmem0")));
     }
 
     fn parse_file(f: File) -> (Entry, usize) {
@@ -393,6 +735,17 @@ mod tests {
         assert!(count > 0);
     }
 
+    #[test]
+    fn results() {
+        let f = File::open("test-logs/corrupted-nocolor.log").expect("Failed to open log file");
+        let mut parsed = parse(f);
+        let results: Vec<_> = parsed.results().collect();
+        assert_eq!(results.len(), 7);
+        assert_eq!(results[0].as_ref().unwrap_err().kind(), "InvalidTimestamp");
+        assert!(results[1..].iter().all(|r| r.is_ok()));
+        assert!(parsed.next().is_none());
+    }
+
     #[test]
     fn timestamps() {
         assert!(Entry::new("foo").is_err());
@@ -435,6 +788,117 @@ mod tests {
         };
     }
 
+    #[test]
+    fn datetime() {
+        let e = Entry::new("2024-02-09T10:11:12.123456Z 0:00:00.007773544  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Init").unwrap();
+        assert_eq!(e.ts.nseconds(), 7773544);
+        assert_eq!(
+            e.datetime.unwrap().to_rfc3339(),
+            "2024-02-09T10:11:12.123456+00:00"
+        );
+        assert_eq!(e.pid, 8874);
+        assert_eq!(e.message, "Init");
+
+        let e = Entry::new("2024-02-09 10:11:12.5 0:00:01.000000000  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Init").unwrap();
+        assert_eq!(e.ts, ClockTime::from_seconds(1));
+        assert_eq!(
+            e.datetime.unwrap().to_rfc3339(),
+            "2024-02-09T10:11:12.500+00:00"
+        );
+
+        // No running time, computed by the parser
+        let e = Entry::new("2024-02-09T10:11:12.000000001+0100  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Init").unwrap();
+        assert_eq!(e.ts, ClockTime::ZERO);
+        assert_eq!(
+            e.datetime.unwrap().to_rfc3339(),
+            "2024-02-09T10:11:12.000000001+01:00"
+        );
+        assert_eq!(e.pid, 8874);
+        assert_eq!(e.thread, "0x558951015c00");
+
+        let e = Entry::new("2024-02-09 10:11:12  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Init").unwrap();
+        assert_eq!(e.ts, ClockTime::ZERO);
+        assert_eq!(e.pid, 8874);
+
+        let e1 = "2024-02-09T25:11:12Z 0:00:00.007773544  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Init";
+        match Entry::new(e1) {
+            Ok(_) => unreachable!(),
+            Err(e) => assert_eq!(
+                e,
+                ParsingError::InvalidDateTime {
+                    datetime: "2024-02-09T25:11:12Z".to_string(),
+                }
+            ),
+        };
+
+        let mut nocolor = parse(File::open("test-logs/nocolor.log").unwrap());
+        assert!(nocolor.all(|e| e.datetime.is_none()));
+    }
+
+    #[test]
+    fn datetime_running_time() {
+        // The running time is the offset from the first entry and keeps increasing across midnight
+        let log = "2024-02-09T23:59:59.5+01:00  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Init
+2024-02-09T23:59:59.75+01:00  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Init
+2024-02-10T00:00:01+01:00  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Init
+";
+        let ts: Vec<_> = parse(Cursor::new(log)).map(|e| e.ts).collect();
+        assert_eq!(
+            ts,
+            vec![
+                ClockTime::ZERO,
+                ClockTime::from_mseconds(250),
+                ClockTime::from_mseconds(1500)
+            ]
+        );
+
+        // Logged running times are kept
+        let log = "2024-02-09T10:11:12Z 0:00:01.000000000  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Init
+2024-02-09T10:11:13Z 0:00:02.000000000  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Init
+";
+        let ts: Vec<_> = parse(Cursor::new(log)).map(|e| e.ts).collect();
+        assert_eq!(
+            ts,
+            vec![ClockTime::from_seconds(1), ClockTime::from_seconds(2)]
+        );
+    }
+
+    #[test]
+    fn object() {
+        let f = File::open("test-logs/nocolor.log").expect("Failed to open log file");
+        let mut parsed = parse(f);
+
+        let entry = parsed.nth(2).expect("3rd entry missing");
+        let object = entry.object.unwrap();
+        assert_eq!(
+            object,
+            ObjectRef::Address {
+                type_name: Some("GstObject".to_string()),
+                address: "0x55895101d040".to_string()
+            }
+        );
+        assert_eq!(object.to_string(), "GstObject@0x55895101d040");
+        assert_eq!(object.element(), None);
+        assert_eq!(object.type_name(), Some("GstObject"));
+        assert_eq!(object.address(), Some("0x55895101d040"));
+
+        let entry = parsed.nth(1).expect("5th entry missing");
+        let object = entry.object.unwrap();
+        assert_eq!(object.element(), Some("allocatorsysmem0"));
+        assert_eq!(object.pad(), None);
+        assert_eq!(object.address(), None);
+
+        let object = ObjectRef::from("v4l2src0:src");
+        assert_eq!(object.element(), Some("v4l2src0"));
+        assert_eq!(object.pad(), Some("src"));
+        assert_eq!(object.to_string(), "v4l2src0:src");
+
+        let object = ObjectRef::from("0x7f8c00bd90");
+        assert_eq!(object.address(), Some("0x7f8c00bd90"));
+        assert_eq!(object.type_name(), None);
+        assert_eq!(object.to_string(), "0x7f8c00bd90");
+    }
+
     #[test]
     fn pid() {
         let e1 = "00:00:00.007773544 ";
@@ -0,0 +1,207 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Interleave entries from several logs into a single timestamp-ordered stream.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use gst::{ClockTime, Signed};
+use gstreamer as gst;
+
+use crate::clock::nseconds;
+use crate::Entry;
+
+#[derive(Debug)]
+pub struct MergedEntry {
+    // index of the source the entry is coming from, in the order they were added
+    pub source: usize,
+    pub entry: Entry,
+}
+
+struct Source<I: Iterator<Item = Entry>> {
+    iter: I,
+    offset: Signed<ClockTime>,
+    next: Option<Entry>,
+}
+
+impl<I: Iterator<Item = Entry>> Source<I> {
+    fn pull(&mut self) -> Option<ClockTime> {
+        let offset = self.offset;
+        self.next = self.iter.next().map(|mut entry| {
+            shift(&mut entry, offset);
+            entry
+        });
+        self.next.as_ref().map(|entry| entry.ts)
+    }
+}
+
+// Shift both the running and the absolute time of @entry, running times
+// shifted before the start of the log are clamped to zero.
+fn shift(entry: &mut Entry, offset: Signed<ClockTime>) {
+    match offset {
+        Signed::Positive(offset) => {
+            entry.ts += offset;
+            entry.datetime = entry.datetime.map(|dt| dt + nseconds(offset));
+        }
+        Signed::Negative(offset) => {
+            entry.ts = entry.ts.saturating_sub(offset);
+            entry.datetime = entry.datetime.map(|dt| dt - nseconds(offset));
+        }
+    }
+}
+
+pub struct Merge<I: Iterator<Item = Entry>> {
+    sources: Vec<Source<I>>,
+    // (ts, source) of the next entry of each source, entries with the same ts
+    // are ordered by source.
+    heap: BinaryHeap<Reverse<(ClockTime, usize)>>,
+}
+
+impl<I: Iterator<Item = Entry>> Merge<I> {
+    pub fn new() -> Self {
+        Self {
+            sources: Vec::new(),
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    // Add a source and return its index
+    pub fn add(&mut self, iter: I) -> usize {
+        self.add_with_offset(iter, Signed::Positive(ClockTime::ZERO))
+    }
+
+    // Add a source whose timestamps are shifted by @offset, used to align logs
+    // from processes which have not been started at the same time.
+    pub fn add_with_offset(&mut self, iter: I, offset: Signed<ClockTime>) -> usize {
+        let idx = self.sources.len();
+        let mut source = Source {
+            iter,
+            offset,
+            next: None,
+        };
+
+        if let Some(ts) = source.pull() {
+            self.heap.push(Reverse((ts, idx)));
+        }
+        self.sources.push(source);
+
+        idx
+    }
+}
+
+impl<I: Iterator<Item = Entry>> Default for Merge<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I: Iterator<Item = Entry>> Iterator for Merge<I> {
+    type Item = MergedEntry;
+
+    fn next(&mut self) -> Option<MergedEntry> {
+        let Reverse((_ts, idx)) = self.heap.pop()?;
+        let source = &mut self.sources[idx];
+        let entry = source.next.take().expect("source without pending entry");
+
+        if let Some(ts) = source.pull() {
+            self.heap.push(Reverse((ts, idx)));
+        }
+
+        Some(MergedEntry { source: idx, entry })
+    }
+}
+
+pub fn merge<I: Iterator<Item = Entry>>(iters: impl IntoIterator<Item = I>) -> Merge<I> {
+    let mut merge = Merge::new();
+
+    for iter in iters {
+        merge.add(iter);
+    }
+
+    merge
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use std::fs::File;
+
+    #[test]
+    fn interleave() {
+        let nocolor = parse(File::open("test-logs/nocolor.log").unwrap());
+        let color = parse(File::open("test-logs/color.log").unwrap());
+
+        let merged: Vec<_> = merge(vec![nocolor, color]).collect();
+        assert_eq!(merged.len(), 6 + 15);
+        assert!(merged.windows(2).all(|w| w[0].entry.ts <= w[1].entry.ts));
+
+        assert_eq!(merged[0].source, 1);
+        assert_eq!(merged[0].entry.pid, 17267);
+        let last = merged.last().unwrap();
+        assert_eq!(last.source, 0);
+        assert_eq!(last.entry.pid, 8874);
+    }
+
+    #[test]
+    fn offset() {
+        let mut merge = Merge::new();
+        merge.add(parse(File::open("test-logs/nocolor.log").unwrap()));
+        merge.add_with_offset(
+            parse(File::open("test-logs/nocolor.log").unwrap()),
+            Signed::Positive(ClockTime::from_seconds(1)),
+        );
+
+        let merged: Vec<_> = merge.map(|m| (m.source, m.entry.ts.nseconds())).collect();
+        assert_eq!(
+            &merged[0..4],
+            &[
+                (0, 7773544),
+                (1, 1_007_773_544),
+                (0, 1_007_927_372),
+                (1, 2_007_927_372)
+            ]
+        );
+    }
+
+    #[test]
+    fn negative_offset() {
+        let entries = |lines: &[&str]| -> Vec<Entry> {
+            lines.iter().map(|l| Entry::new(l).unwrap()).collect()
+        };
+        let client = entries(&[
+            "2024-02-09T10:11:12+00:00 0:00:01.000000000  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: client",
+        ]);
+        let server = entries(&[
+            "2024-02-09T10:11:12+00:00 0:00:00.500000000  8875 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: early",
+            "2024-02-09T10:11:14+00:00 0:00:03.000000000  8875 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: server",
+        ]);
+
+        let mut merge = Merge::new();
+        merge.add(client.into_iter());
+        merge.add_with_offset(
+            server.into_iter(),
+            Signed::Negative(ClockTime::from_seconds(1)),
+        );
+
+        let merged: Vec<_> = merge.collect();
+        assert_eq!(merged[0].entry.message, "early");
+        assert_eq!(merged[0].entry.ts, ClockTime::ZERO);
+        assert_eq!(
+            merged[0].entry.datetime.unwrap().to_rfc3339(),
+            "2024-02-09T10:11:11+00:00"
+        );
+        assert_eq!(merged[1].entry.message, "client");
+        assert_eq!(merged[2].entry.ts, ClockTime::from_seconds(2));
+        assert_eq!(
+            merged[2].entry.datetime.unwrap().to_rfc3339(),
+            "2024-02-09T10:11:13+00:00"
+        );
+    }
+}
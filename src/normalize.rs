@@ -0,0 +1,131 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Replace addresses, which change between runs, by symbolic names so logs can be compared.
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+use crate::anonymize::{Pseudonyms, ADDRESS_RE};
+use crate::{Entry, ObjectRef};
+
+lazy_static! {
+    static ref VARIABLE_RE: Regex = Regex::new(
+        r"(?P<ts>\b\d+:\d{2}:\d{2}\.\d+\b)|(?P<ptr>\b0x[0-9a-fA-F]+\b|\b(?:obj|thread)#\d+\b)|(?P<num>\b\d+(?:\.\d+)?\b)"
+    )
+    .unwrap();
+}
+
+// Threads are renamed 'thread#N' and other addresses 'obj#N', in order of first appearance.
+#[derive(Debug, Default)]
+pub struct Normalizer {
+    threads: Pseudonyms,
+    objects: Pseudonyms,
+}
+
+impl Normalizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn address(&mut self, address: &str) -> String {
+        let address = address.to_lowercase();
+
+        if self.threads.contains(&address) {
+            format!("thread#{}", self.threads.get(&address))
+        } else {
+            format!("obj#{}", self.objects.get(&address))
+        }
+    }
+
+    pub fn normalize(&mut self, mut entry: Entry) -> Entry {
+        entry.thread = format!("thread#{}", self.threads.get(&entry.thread.to_lowercase()));
+
+        if let Some(ObjectRef::Address { address, .. }) = &mut entry.object {
+            *address = self.address(address);
+        }
+
+        entry.message = ADDRESS_RE
+            .replace_all(&entry.message, |caps: &Captures| self.address(&caps[0]))
+            .to_string();
+
+        entry
+    }
+}
+
+pub fn normalize<I: Iterator<Item = Entry>>(iter: I) -> impl Iterator<Item = Entry> {
+    let mut normalizer = Normalizer::new();
+    iter.map(move |entry| normalizer.normalize(entry))
+}
+
+// Mask the parts of @message changing between runs or between entries logged from the same place
+// (timestamps, pointers and numbers) so messages can be grouped by template.
+pub fn message_template(message: &str) -> String {
+    VARIABLE_RE
+        .replace_all(message, |caps: &Captures| {
+            if caps.name("ts").is_some() {
+                "<ts>"
+            } else if caps.name("ptr").is_some() {
+                "<ptr>"
+            } else {
+                "<num>"
+            }
+        })
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use std::fs::File;
+
+    #[test]
+    fn addresses() {
+        let entries: Vec<_> =
+            normalize(parse(File::open("test-logs/nocolor.log").unwrap())).collect();
+
+        assert!(entries.iter().all(|e| e.thread == "thread#1"));
+        assert_eq!(
+            entries[2].object,
+            Some(ObjectRef::Address {
+                type_name: Some("GstObject".to_string()),
+                address: "obj#1".to_string()
+            })
+        );
+        assert_eq!(entries[2].object.as_ref().unwrap().element(), None);
+        assert_eq!(entries[2].message, "obj#1 new");
+        assert_eq!(entries[3].message, "init allocator obj#1");
+        assert_eq!(entries[4].object, Some(ObjectRef::from("allocatorsysmem0")));
+        assert_eq!(entries[4].message, "obj#1 ref 1->2");
+
+        let mut normalizer = Normalizer::new();
+        let entry = normalizer.normalize(Entry::new("0:00:00.007773544  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: thread 0x558951015c00 created 0x7f8c00bd90 and 0x7f8c00bd99").unwrap());
+        assert_eq!(entry.message, "thread thread#1 created obj#1 and obj#2");
+
+        // the object field uses the same names as the message
+        let entry = normalizer.normalize(Entry::new("0:00:00.007773544  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre:<GstTask@0x558951015c00> running 0x7f8c00bd99").unwrap());
+        assert_eq!(
+            entry.object.map(|o| o.to_string()),
+            Some("GstTask@thread#1".to_string())
+        );
+        assert_eq!(entry.message, "running obj#2");
+    }
+
+    #[test]
+    fn template() {
+        assert_eq!(
+            message_template("pushing buffer 0x7f8c00bd90 pts 0:00:01.033333333 size 4096"),
+            "pushing buffer <ptr> pts <ts> size <num>"
+        );
+        assert_eq!(
+            message_template("obj#3 ref 1->2, rate 29.97 on queue0"),
+            "<ptr> ref <num>-><num>, rate <num> on queue0"
+        );
+    }
+}
@@ -0,0 +1,455 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Decoding of the OMX_API_TRACE logs of gst-omx.
+//!
+//! Each buffer exchanged with an OMX component is logged as a structure named
+//! after the OMX call (EmptyThisBuffer, FillBufferDone, ...) with the fields of
+//! the buffer header. Callbacks are logged a second time with a '-FINISHED'
+//! suffix once they returned.
+
+use std::fmt;
+use std::str::FromStr;
+
+use gst::ClockTime;
+use gstreamer as gst;
+use regex::Regex;
+use thiserror::Error;
+
+use crate::Entry;
+
+// OMX_BUFFERFLAG_*
+pub const FLAG_EOS: u32 = 0x1;
+pub const FLAG_DATA_CORRUPT: u32 = 0x8;
+pub const FLAG_END_OF_FRAME: u32 = 0x10;
+pub const FLAG_SYNC_FRAME: u32 = 0x20;
+pub const FLAG_CODEC_CONFIG: u32 = 0x80;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum OmxError {
+    #[error("entry has no object")]
+    NoObject,
+    #[error("no component name in object {}", object)]
+    NoComponent { object: String },
+    #[error("invalid structure: {}", message)]
+    InvalidStructure { message: String },
+    #[error("missing or invalid field '{}' in {}", field, call)]
+    InvalidField { call: OmxCall, field: String },
+    #[error("invalid component naming rule: {}", rule)]
+    InvalidNaming { rule: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OmxCall {
+    EmptyThisBuffer,
+    EmptyBufferDone,
+    FillThisBuffer,
+    FillBufferDone,
+}
+
+impl OmxCall {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "EmptyThisBuffer" => Some(OmxCall::EmptyThisBuffer),
+            "EmptyBufferDone" => Some(OmxCall::EmptyBufferDone),
+            "FillThisBuffer" => Some(OmxCall::FillThisBuffer),
+            "FillBufferDone" => Some(OmxCall::FillBufferDone),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OmxCall::EmptyThisBuffer => "EmptyThisBuffer",
+            OmxCall::EmptyBufferDone => "EmptyBufferDone",
+            OmxCall::FillThisBuffer => "FillThisBuffer",
+            OmxCall::FillBufferDone => "FillBufferDone",
+        }
+    }
+}
+
+impl fmt::Display for OmxCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// How to name OMX components from the object of the entries
+#[derive(Debug, Clone)]
+pub enum ComponentNaming {
+    // Last chars of the object name, such as "dec0" for "omxh264dec0"
+    Suffix(usize),
+    // The full object name
+    Object,
+    // First group captured by the regex in the object name, or the whole match
+    Regex(Regex),
+}
+
+impl Default for ComponentNaming {
+    fn default() -> Self {
+        ComponentNaming::Suffix(4)
+    }
+}
+
+impl ComponentNaming {
+    // Help of the command line options parsed as a naming rule
+    pub const HELP: &'static str = "How to name OMX components from the object name: 'suffix:N' for its N last chars, 'object' or 'regex:RE' for the first group captured by RE";
+
+    pub fn component(&self, object: &str) -> Option<String> {
+        match self {
+            ComponentNaming::Suffix(n) => {
+                let start = object
+                    .char_indices()
+                    .rev()
+                    .nth(n.checked_sub(1)?)
+                    .map_or(0, |(i, _)| i);
+                Some(object[start..].to_string())
+            }
+            ComponentNaming::Object => Some(object.to_string()),
+            ComponentNaming::Regex(re) => {
+                let cap = re.captures(object)?;
+                let m = cap.get(1).or_else(|| cap.get(0))?;
+                Some(m.as_str().to_string())
+            }
+        }
+    }
+}
+
+// Rules are written "suffix:N", "object" or "regex:RE"
+impl FromStr for ComponentNaming {
+    type Err = OmxError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || OmxError::InvalidNaming {
+            rule: s.to_string(),
+        };
+
+        match s.split_once(':') {
+            Some(("suffix", n)) => match n.parse() {
+                Ok(n) if n > 0 => Ok(ComponentNaming::Suffix(n)),
+                _ => Err(invalid()),
+            },
+            Some(("regex", re)) => Regex::new(re)
+                .map(ComponentNaming::Regex)
+                .map_err(|_| invalid()),
+            None if s == "object" => Ok(ComponentNaming::Object),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OmxEvent {
+    pub ts: ClockTime,
+    pub component: String,
+    pub call: OmxCall,
+    // Logged once the callback returned
+    pub finished: bool,
+    // Buffer fields, not logged by all '-FINISHED' events
+    pub timestamp: Option<ClockTime>,
+    pub filled_len: Option<u32>,
+    pub flags: u32,
+}
+
+impl OmxEvent {
+    // Return None for entries which are not buffer calls
+    pub fn from_entry(entry: &Entry, naming: &ComponentNaming) -> Result<Option<Self>, OmxError> {
+        if entry.category != "OMX_API_TRACE" {
+            return Ok(None);
+        }
+
+        // Other API calls are logged as well, check the name of the structure before decoding it
+        let name = entry
+            .message
+            .split([',', ';'])
+            .next()
+            .unwrap_or_default()
+            .trim();
+        let (name, finished) = match name.strip_suffix("-FINISHED") {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+        let call = match OmxCall::from_name(name) {
+            Some(call) => call,
+            None => return Ok(None),
+        };
+
+        let object = entry.object.as_ref().ok_or(OmxError::NoObject)?.to_string();
+        let component = naming
+            .component(&object)
+            .ok_or(OmxError::NoComponent { object })?;
+        let s = entry
+            .message_to_struct()
+            .ok_or_else(|| OmxError::InvalidStructure {
+                message: entry.message.clone(),
+            })?;
+
+        let invalid = |field: &str| OmxError::InvalidField {
+            call,
+            field: field.to_string(),
+        };
+        let ticks = s.get::<u64>("TimeStamp").ok();
+        let filled_len = s.get::<u32>("FilledLen").ok();
+        if !finished {
+            ticks.ok_or_else(|| invalid("TimeStamp"))?;
+            filled_len.ok_or_else(|| invalid("FilledLen"))?;
+        }
+
+        Ok(Some(Self {
+            ts: entry.ts,
+            component,
+            call,
+            finished,
+            // OMX ticks are in microseconds
+            timestamp: ticks
+                .and_then(|t| t.checked_mul(1000))
+                .map(ClockTime::from_nseconds),
+            filled_len,
+            flags: s.get::<u32>("flags").unwrap_or(0),
+        }))
+    }
+
+    pub fn has_flag(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filled_len == Some(0)
+    }
+}
+
+// Number of OMX_API_TRACE entries which could not be decoded
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Skipped {
+    pub no_object: u64,
+    pub no_component: u64,
+    pub malformed: u64,
+}
+
+impl Skipped {
+    fn add(&mut self, err: &OmxError) {
+        match err {
+            OmxError::NoObject => self.no_object += 1,
+            OmxError::NoComponent { .. } => self.no_component += 1,
+            _ => self.malformed += 1,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.no_object + self.no_component + self.malformed
+    }
+}
+
+impl fmt::Display for Skipped {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} without object, {} without component name, {} malformed",
+            self.no_object, self.no_component, self.malformed
+        )
+    }
+}
+
+// Decode entries, skipping and counting those which are invalid
+#[derive(Debug, Default)]
+pub struct OmxParser {
+    naming: ComponentNaming,
+    skipped: Skipped,
+}
+
+impl OmxParser {
+    pub fn new(naming: ComponentNaming) -> Self {
+        Self {
+            naming,
+            skipped: Skipped::default(),
+        }
+    }
+
+    pub fn parse(&mut self, entry: &Entry) -> Option<OmxEvent> {
+        match OmxEvent::from_entry(entry, &self.naming) {
+            Ok(event) => event,
+            Err(err) => {
+                self.skipped.add(&err);
+                None
+            }
+        }
+    }
+
+    pub fn skipped(&self) -> &Skipped {
+        &self.skipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn naming() {
+        let default = ComponentNaming::default();
+        assert_eq!(default.component("omxh264dec0"), Some("dec0".to_string()));
+        assert_eq!(default.component("enc"), Some("enc".to_string()));
+
+        let object: ComponentNaming = "object".parse().unwrap();
+        assert_eq!(
+            object.component("omxh264dec0"),
+            Some("omxh264dec0".to_string())
+        );
+
+        let suffix: ComponentNaming = "suffix:6".parse().unwrap();
+        assert_eq!(suffix.component("omxh264dec0"), Some("64dec0".to_string()));
+
+        let re: ComponentNaming = "regex:^omx(\\w+?)\\d*$".parse().unwrap();
+        assert_eq!(re.component("omxh264dec0"), Some("h264dec".to_string()));
+        assert_eq!(re.component("v4l2src0"), None);
+        let re: ComponentNaming = "regex:dec".parse().unwrap();
+        assert_eq!(re.component("omxh264dec0"), Some("dec".to_string()));
+
+        for rule in ["suffix:0", "suffix:a", "regex:(", "name", ""] {
+            assert_eq!(
+                rule.parse::<ComponentNaming>().unwrap_err(),
+                OmxError::InvalidNaming {
+                    rule: rule.to_string()
+                }
+            );
+        }
+    }
+
+    fn omx_entry(ts: &str, object: &str, message: &str) -> Entry {
+        Entry::new(&format!(
+            "{} 11245 0x7f5c1c0022d0 TRACE        OMX_API_TRACE gstomx.c:167:log_omx_api_trace_buffer:{} {}",
+            ts, object, message
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn events() {
+        gst::init().unwrap();
+        let naming = ComponentNaming::default();
+        let decode = |ts, message| {
+            OmxEvent::from_entry(&omx_entry(ts, "<omxh264dec-omxh264dec0>", message), &naming)
+        };
+
+        let e = decode(
+            "0:00:01.000000000",
+            "EmptyThisBuffer, GstOMXBuffer=(string)0x7f5c14002a20, OMX-buffer=(string)0x7f5c14002b40, pBuffer=(string)0x7f5c20000000, TimeStamp=(guint64)33333, AllocLen=(uint)2097152, FilledLen=(uint)5120, flags=(uint)48, flags-str=(string)endofframe+sync;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            e,
+            OmxEvent {
+                ts: ClockTime::from_seconds(1),
+                component: "dec0".to_string(),
+                call: OmxCall::EmptyThisBuffer,
+                finished: false,
+                timestamp: Some(ClockTime::from_useconds(33333)),
+                filled_len: Some(5120),
+                flags: FLAG_END_OF_FRAME | FLAG_SYNC_FRAME,
+            }
+        );
+
+        let e = decode(
+            "0:00:01.020000000",
+            "FillBufferDone, GstOMXBuffer=(string)0x7f5c14003c80, OMX-buffer=(string)0x7f5c14003da0, pBuffer=(string)0x7f5c21000000, TimeStamp=(guint64)33333, AllocLen=(uint)3110400, FilledLen=(uint)3110400, flags=(uint)16, flags-str=(string)endofframe;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(e.call, OmxCall::FillBufferDone);
+        assert!(!e.finished);
+        assert_eq!(e.filled_len, Some(3110400));
+        assert!(e.has_flag(FLAG_END_OF_FRAME));
+        assert!(!e.has_flag(FLAG_EOS));
+        assert_eq!(e.timestamp, Some(ClockTime::from_useconds(33333)));
+
+        let e = decode("0:00:01.021000000", "FillBufferDone-FINISHED;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(e.call, OmxCall::FillBufferDone);
+        assert!(e.finished);
+        assert_eq!(e.timestamp, None);
+        assert_eq!(e.filled_len, None);
+        assert_eq!(e.flags, 0);
+
+        assert_eq!(
+            decode(
+                "0:00:01.022000000",
+                "SendCommand, cmd=(string)OMX_CommandStateSet, param=(string)OMX_StateIdle;"
+            ),
+            Ok(None)
+        );
+        assert!(matches!(
+            decode(
+                "0:00:01.023000000",
+                "EmptyThisBuffer, GstOMXBuffer=(string)0x7f5c14002a20, OMX-buffer=(string)0x7f5c14002b40, TimeStamp=(guint64"
+            ),
+            Err(OmxError::InvalidStructure { .. })
+        ));
+        assert_eq!(
+            decode(
+                "0:00:01.024000000",
+                "EmptyThisBuffer, AllocLen=(uint)2097152, FilledLen=(uint)5120;"
+            ),
+            Err(OmxError::InvalidField {
+                call: OmxCall::EmptyThisBuffer,
+                field: "TimeStamp".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn skipped() {
+        gst::init().unwrap();
+        let mut parser = OmxParser::new("regex:^omx".parse().unwrap());
+
+        for entry in [
+            omx_entry("0:00:00.010000000", "", "EmptyThisBuffer, TimeStamp=(guint64)0, FilledLen=(uint)0;"),
+            omx_entry("0:00:00.011000000", "<v4l2src0>", "EmptyThisBuffer, TimeStamp=(guint64)0, FilledLen=(uint)0;"),
+            omx_entry("0:00:00.012000000", "<omxh264dec-omxh264dec0>", "FillBufferDone, GstOMXBuffer=(string)0x7f5c14003c80, TimeStamp=(guint64"),
+            omx_entry("0:00:00.013000000", "<omxh264dec-omxh264dec0>", "FillBufferDone, FilledLen=(uint)0;"),
+            // not buffer calls
+            omx_entry("0:00:00.014000000", "<v4l2src0>", "SendCommand, cmd=(string)OMX_CommandStateSet;"),
+            omx_entry("0:00:00.015000000", "", "EventHandler, event=(string)OMX_EventCmdComplete;"),
+            Entry::new("0:00:00.016000000 11245 0x7f5c1c0022d0 TRACE GST_BUFFER gstomx.c:1:f: EmptyThisBuffer, TimeStamp=(guint64)0;").unwrap(),
+        ] {
+            assert_eq!(parser.parse(&entry), None);
+        }
+
+        assert_eq!(
+            parser.skipped(),
+            &Skipped {
+                no_object: 1,
+                no_component: 1,
+                malformed: 2,
+            }
+        );
+        assert_eq!(parser.skipped().total(), 4);
+        assert_eq!(
+            parser.skipped().to_string(),
+            "1 without object, 1 without component name, 2 malformed"
+        );
+    }
+
+    #[test]
+    fn flags() {
+        let e = OmxEvent {
+            ts: ClockTime::ZERO,
+            component: "dec0".to_string(),
+            call: OmxCall::EmptyThisBuffer,
+            finished: false,
+            timestamp: None,
+            filled_len: Some(1024),
+            flags: FLAG_END_OF_FRAME | FLAG_SYNC_FRAME,
+        };
+        assert!(e.has_flag(FLAG_SYNC_FRAME));
+        assert!(!e.has_flag(FLAG_EOS));
+        assert!(!e.is_empty());
+        assert_eq!(OmxCall::EmptyBufferDone.to_string(), "EmptyBufferDone");
+    }
+}
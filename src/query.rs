@@ -0,0 +1,749 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A small expression language selecting log entries.
+//!
+//! Expressions compare entry fields with values and can be combined using
+//! `and`, `or`, `not` and parentheses, for example:
+//!
+//! ```text
+//! level<=WARN and object~"^v4l2" and ts in 5s..8s
+//! (category == GST_PADS or location == "gstpad.c:4431") and not thread == 0x7f8c00bd90
+//! ```
+//!
+//! Fields are `ts`, `pid`, `thread`, `level`, `category`, `file`, `line`,
+//! `function`, `object`, `element`, `pad`, `message` and `location` (`file:line`).
+//! Operators are `==`, `!=`, `<`, `<=`, `>`, `>=`, `~` (regex match) and `!~`.
+//! `ts in START..END` selects entries from `START` (included) to `END` (excluded),
+//! either bound can be omitted.
+//! Durations are either in the GStreamer format (`0:00:05.000000000`) or a number
+//! followed by one of the `h`, `m`, `s`, `ms`, `us` or `ns` units.
+
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::fmt;
+use std::str::FromStr;
+
+use gst::{ClockTime, DebugLevel};
+use gstreamer as gst;
+use regex::Regex;
+use thiserror::Error;
+
+use crate::{parse_debug_level, parse_time, Entry};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum QueryError {
+    #[error("unexpected end of query")]
+    UnexpectedEnd,
+    #[error("unexpected '{}' at position {}", found, pos)]
+    UnexpectedToken { found: String, pos: usize },
+    #[error("unterminated string starting at position {}", pos)]
+    UnterminatedString { pos: usize },
+    #[error("unknown field: {}", name)]
+    UnknownField { name: String },
+    #[error("operator '{}' cannot be used with field {}", op, field)]
+    InvalidOperator { field: Field, op: Op },
+    #[error("invalid value for field {}: {}", field, value)]
+    InvalidValue { field: Field, value: String },
+    #[error("invalid regular expression {}: {}", regex, msg)]
+    InvalidRegex { regex: String, msg: String },
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Field {
+    Ts,
+    Pid,
+    Thread,
+    Level,
+    Category,
+    File,
+    Line,
+    Function,
+    Object,
+    Element,
+    Pad,
+    Message,
+    Location,
+}
+
+impl FromStr for Field {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ts" => Ok(Field::Ts),
+            "pid" => Ok(Field::Pid),
+            "thread" => Ok(Field::Thread),
+            "level" => Ok(Field::Level),
+            "category" => Ok(Field::Category),
+            "file" => Ok(Field::File),
+            "line" => Ok(Field::Line),
+            "function" => Ok(Field::Function),
+            "object" => Ok(Field::Object),
+            "element" => Ok(Field::Element),
+            "pad" => Ok(Field::Pad),
+            "message" => Ok(Field::Message),
+            "location" => Ok(Field::Location),
+            _ => Err(QueryError::UnknownField {
+                name: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Field::Ts => "ts",
+            Field::Pid => "pid",
+            Field::Thread => "thread",
+            Field::Level => "level",
+            Field::Category => "category",
+            Field::File => "file",
+            Field::Line => "line",
+            Field::Function => "function",
+            Field::Object => "object",
+            Field::Element => "element",
+            Field::Pad => "pad",
+            Field::Message => "message",
+            Field::Location => "location",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl Field {
+    fn text<'a>(&self, entry: &'a Entry) -> Option<Cow<'a, str>> {
+        match self {
+            Field::Ts => Some(Cow::Owned(entry.ts.to_string())),
+            Field::Pid => Some(Cow::Owned(entry.pid.to_string())),
+            Field::Thread => Some(Cow::Borrowed(&entry.thread)),
+            // gst_debug_level_get_name() pads names to the same width
+            Field::Level => Some(Cow::Borrowed(entry.level.name().as_str().trim_end())),
+            Field::Category => Some(Cow::Borrowed(&entry.category)),
+            Field::File => Some(Cow::Borrowed(&entry.file)),
+            Field::Line => Some(Cow::Owned(entry.line.to_string())),
+            Field::Function => Some(Cow::Borrowed(&entry.function)),
+            Field::Object => entry.object.as_ref().map(|o| Cow::Owned(o.to_string())),
+            Field::Element => entry.object.as_ref()?.element().map(Cow::Borrowed),
+            Field::Pad => entry.object.as_ref()?.pad().map(Cow::Borrowed),
+            Field::Message => Some(Cow::Borrowed(&entry.message)),
+            Field::Location => Some(Cow::Owned(format!("{}:{}", entry.file, entry.line))),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Match,
+    NotMatch,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Op::Eq => "==",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Match => "~",
+            Op::NotMatch => "!~",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl Op {
+    fn compare<T: Ord>(self, a: &T, b: &T) -> bool {
+        match self {
+            Op::Eq => a == b,
+            Op::Ne => a != b,
+            Op::Lt => a < b,
+            Op::Le => a <= b,
+            Op::Gt => a > b,
+            Op::Ge => a >= b,
+            Op::Match | Op::NotMatch => unreachable!(),
+        }
+    }
+
+    fn is_ordering(self) -> bool {
+        matches!(self, Op::Lt | Op::Le | Op::Gt | Op::Ge)
+    }
+}
+
+#[derive(Debug)]
+enum Predicate {
+    Ts {
+        op: Op,
+        value: ClockTime,
+    },
+    TsRange {
+        start: Option<ClockTime>,
+        end: Option<ClockTime>,
+    },
+    Pid {
+        op: Op,
+        value: u32,
+    },
+    Line {
+        op: Op,
+        value: u32,
+    },
+    Level {
+        op: Op,
+        value: DebugLevel,
+    },
+    Text {
+        field: Field,
+        op: Op,
+        value: String,
+    },
+    Regex {
+        field: Field,
+        regex: Regex,
+        negate: bool,
+    },
+}
+
+impl Predicate {
+    fn matches(&self, entry: &Entry) -> bool {
+        match self {
+            Predicate::Ts { op, value } => op.compare(&entry.ts, value),
+            Predicate::TsRange { start, end } => {
+                start.is_none_or(|start| entry.ts >= start) && end.is_none_or(|end| entry.ts < end)
+            }
+            Predicate::Pid { op, value } => op.compare(&entry.pid, value),
+            Predicate::Line { op, value } => op.compare(&entry.line, value),
+            Predicate::Level { op, value } => op.compare(&entry.level, value),
+            Predicate::Text { field, op, value } => match field.text(entry) {
+                Some(text) => op.compare(&text.as_ref(), &value.as_str()),
+                None => *op == Op::Ne,
+            },
+            Predicate::Regex {
+                field,
+                regex,
+                negate,
+            } => match field.text(entry) {
+                Some(text) => regex.is_match(&text) != *negate,
+                None => *negate,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Predicate(Predicate),
+}
+
+impl Expr {
+    fn matches(&self, entry: &Entry) -> bool {
+        match self {
+            Expr::And(a, b) => a.matches(entry) && b.matches(entry),
+            Expr::Or(a, b) => a.matches(entry) || b.matches(entry),
+            Expr::Not(e) => !e.matches(entry),
+            Expr::Predicate(p) => p.matches(entry),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Tok {
+    Word(String),
+    Quoted(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    In,
+    Range,
+    LParen,
+    RParen,
+}
+
+impl fmt::Display for Tok {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tok::Word(w) => write!(f, "{}", w),
+            Tok::Quoted(w) => write!(f, "\"{}\"", w),
+            Tok::Op(op) => write!(f, "{}", op),
+            Tok::And => write!(f, "and"),
+            Tok::Or => write!(f, "or"),
+            Tok::Not => write!(f, "not"),
+            Tok::In => write!(f, "in"),
+            Tok::Range => write!(f, ".."),
+            Tok::LParen => write!(f, "("),
+            Tok::RParen => write!(f, ")"),
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !"()\"=!<>~&|".contains(c)
+}
+
+fn tokenize(s: &str) -> Result<Vec<(usize, Tok)>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+
+    while let Some((pos, c)) = chars.next() {
+        let next = chars.peek().map(|(_, c)| *c);
+        let tok = match (c, next) {
+            _ if c.is_whitespace() => continue,
+            ('(', _) => Tok::LParen,
+            (')', _) => Tok::RParen,
+            ('=', Some('='))
+            | ('!', Some('='))
+            | ('<', Some('='))
+            | ('>', Some('='))
+            | ('!', Some('~'))
+            | ('&', Some('&'))
+            | ('|', Some('|')) => {
+                chars.next();
+                match c {
+                    '=' => Tok::Op(Op::Eq),
+                    '<' => Tok::Op(Op::Le),
+                    '>' => Tok::Op(Op::Ge),
+                    '&' => Tok::And,
+                    '|' => Tok::Or,
+                    _ if next == Some('=') => Tok::Op(Op::Ne),
+                    _ => Tok::Op(Op::NotMatch),
+                }
+            }
+            ('=', _) => Tok::Op(Op::Eq),
+            ('<', _) => Tok::Op(Op::Lt),
+            ('>', _) => Tok::Op(Op::Gt),
+            ('~', _) => Tok::Op(Op::Match),
+            ('!', _) => Tok::Not,
+            ('"', _) => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err(QueryError::UnterminatedString { pos }),
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            None => return Err(QueryError::UnterminatedString { pos }),
+                            Some((_, c)) => {
+                                // Keep escapes other than \" and \\ for regexes
+                                if c != '"' && c != '\\' {
+                                    value.push('\\');
+                                }
+                                value.push(c);
+                            }
+                        },
+                        Some((_, c)) => value.push(c),
+                    }
+                }
+                Tok::Quoted(value)
+            }
+            ('.', Some('.')) => {
+                chars.next();
+                Tok::Range
+            }
+            _ if is_word_char(c) => {
+                let mut word = c.to_string();
+                while let Some((_, c)) = chars.peek() {
+                    if !is_word_char(*c) || (*c == '.' && s[pos + word.len()..].starts_with("..")) {
+                        break;
+                    }
+                    word.push(*c);
+                    chars.next();
+                }
+                match word.to_lowercase().as_str() {
+                    "and" => Tok::And,
+                    "or" => Tok::Or,
+                    "not" => Tok::Not,
+                    "in" => Tok::In,
+                    _ => Tok::Word(word),
+                }
+            }
+            _ => {
+                return Err(QueryError::UnexpectedToken {
+                    found: c.to_string(),
+                    pos,
+                })
+            }
+        };
+
+        tokens.push((pos, tok));
+    }
+
+    Ok(tokens)
+}
+
+pub fn parse_duration(s: &str) -> Option<ClockTime> {
+    if s.contains(':') {
+        return parse_time(s).ok();
+    }
+
+    let unit_start = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(unit_start);
+    let mult: u128 = match unit {
+        "h" => 3_600_000_000_000,
+        "m" => 60_000_000_000,
+        "s" | "" => 1_000_000_000,
+        "ms" => 1_000_000,
+        "us" => 1_000,
+        "ns" => 1,
+        _ => return None,
+    };
+
+    let (int, frac) = number.split_once('.').unwrap_or((number, ""));
+    if int.is_empty() && frac.is_empty() {
+        return None;
+    }
+    let int: u128 = if int.is_empty() { 0 } else { int.parse().ok()? };
+    let frac_ns = if frac.is_empty() {
+        0
+    } else {
+        if !frac.bytes().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        // extra digits are ignored so the multiplication cannot overflow
+        let frac = &frac[..frac.len().min(9)];
+        frac.parse::<u128>().ok()?.checked_mul(mult)? / 10u128.pow(frac.len() as u32)
+    };
+
+    let ns = int.checked_mul(mult)?.checked_add(frac_ns)?;
+    Some(ClockTime::from_nseconds(ns.try_into().ok()?))
+}
+
+struct Parser {
+    tokens: Vec<(usize, Tok)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn next(&mut self) -> Result<Tok, QueryError> {
+        let tok = self
+            .tokens
+            .get(self.pos)
+            .map(|(_, t)| t.clone())
+            .ok_or(QueryError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(tok)
+    }
+
+    fn unexpected(&self, offset: usize) -> QueryError {
+        match self.tokens.get(self.pos - offset) {
+            Some((pos, tok)) => QueryError::UnexpectedToken {
+                found: tok.to_string(),
+                pos: *pos,
+            },
+            None => QueryError::UnexpectedEnd,
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Tok::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.parse_unary()?;
+        while self.peek() == Some(&Tok::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, QueryError> {
+        match self.next()? {
+            Tok::Not => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            Tok::LParen => {
+                let expr = self.parse_or()?;
+                match self.next()? {
+                    Tok::RParen => Ok(expr),
+                    _ => Err(self.unexpected(1)),
+                }
+            }
+            Tok::Word(field) => {
+                let field = field.parse()?;
+                self.parse_predicate(field).map(Expr::Predicate)
+            }
+            _ => Err(self.unexpected(1)),
+        }
+    }
+
+    fn value(&mut self) -> Result<String, QueryError> {
+        match self.next()? {
+            Tok::Word(w) | Tok::Quoted(w) => Ok(w),
+            _ => Err(self.unexpected(1)),
+        }
+    }
+
+    fn parse_predicate(&mut self, field: Field) -> Result<Predicate, QueryError> {
+        let op = match self.next()? {
+            Tok::In if field == Field::Ts => return self.parse_range(),
+            Tok::Op(op) => op,
+            _ => return Err(self.unexpected(1)),
+        };
+        let value = self.value()?;
+        let invalid_value = || QueryError::InvalidValue {
+            field,
+            value: value.clone(),
+        };
+
+        if op == Op::Match || op == Op::NotMatch {
+            let regex = Regex::new(&value).map_err(|e| QueryError::InvalidRegex {
+                regex: value.clone(),
+                msg: e.to_string(),
+            })?;
+            return Ok(Predicate::Regex {
+                field,
+                regex,
+                negate: op == Op::NotMatch,
+            });
+        }
+
+        match field {
+            Field::Ts => {
+                let value = parse_duration(&value).ok_or_else(invalid_value)?;
+                Ok(Predicate::Ts { op, value })
+            }
+            Field::Pid => {
+                let value = value.parse().map_err(|_e| invalid_value())?;
+                Ok(Predicate::Pid { op, value })
+            }
+            Field::Line => {
+                let value = value.parse().map_err(|_e| invalid_value())?;
+                Ok(Predicate::Line { op, value })
+            }
+            Field::Level => {
+                let value = parse_level(&value).ok_or_else(invalid_value)?;
+                Ok(Predicate::Level { op, value })
+            }
+            _ if op.is_ordering() => Err(QueryError::InvalidOperator { field, op }),
+            _ => Ok(Predicate::Text { field, op, value }),
+        }
+    }
+
+    fn parse_range(&mut self) -> Result<Predicate, QueryError> {
+        let bound = |s: String| {
+            parse_duration(&s).ok_or(QueryError::InvalidValue {
+                field: Field::Ts,
+                value: s,
+            })
+        };
+
+        let start = match self.peek() {
+            Some(Tok::Range) => None,
+            _ => Some(bound(self.value()?)?),
+        };
+        match self.next()? {
+            Tok::Range => {}
+            _ => return Err(self.unexpected(1)),
+        }
+        let end = match self.peek() {
+            Some(Tok::Word(_)) | Some(Tok::Quoted(_)) => Some(bound(self.value()?)?),
+            _ => None,
+        };
+
+        Ok(Predicate::TsRange { start, end })
+    }
+}
+
+pub(crate) fn parse_level(s: &str) -> Option<DebugLevel> {
+    match s.to_uppercase().as_str() {
+        "WARNING" => Some(DebugLevel::Warning),
+        "NONE" | "0" => Some(DebugLevel::None),
+        "1" => Some(DebugLevel::Error),
+        "2" => Some(DebugLevel::Warning),
+        "3" => Some(DebugLevel::Fixme),
+        "4" => Some(DebugLevel::Info),
+        "5" => Some(DebugLevel::Debug),
+        "6" => Some(DebugLevel::Log),
+        "7" => Some(DebugLevel::Trace),
+        "9" => Some(DebugLevel::Memdump),
+        s => parse_debug_level(s).ok(),
+    }
+}
+
+#[derive(Debug)]
+pub struct Query {
+    expr: Expr,
+}
+
+impl Query {
+    pub fn new(query: &str) -> Result<Self, QueryError> {
+        let mut parser = Parser {
+            tokens: tokenize(query)?,
+            pos: 0,
+        };
+
+        let expr = parser.parse_or()?;
+        if parser.peek().is_some() {
+            return Err(parser.unexpected(0));
+        }
+
+        Ok(Self { expr })
+    }
+
+    pub fn matches(&self, entry: &Entry) -> bool {
+        self.expr.matches(entry)
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Query::new(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: &str = "0:00:06.007773544  8874 0x558951015c00 WARN                GST_INIT gst.c:510:init_pre:<v4l2src0:src> Initializing GStreamer";
+
+    fn matches(query: &str) -> bool {
+        let entry = Entry::new(LINE).unwrap();
+        Query::new(query).unwrap().matches(&entry)
+    }
+
+    #[test]
+    fn fields() {
+        assert!(matches("level<=WARN"));
+        assert!(matches("level < INFO"));
+        assert!(!matches("level==ERROR"));
+        assert!(matches("level == 2"));
+        assert!(matches("level ~ \"^WARN$\""));
+        assert!(!matches("level ~ Warning"));
+        assert!(matches("pid == 8874"));
+        assert!(matches("thread == 0x558951015c00"));
+        assert!(matches("category == GST_INIT"));
+        assert!(matches("file == gst.c and line >= 500 and line < 600"));
+        assert!(matches("location == \"gst.c:510\""));
+        assert!(matches("function ~ init"));
+        assert!(matches("object ~ \"^v4l2\""));
+        assert!(matches("element == v4l2src0 and pad == src"));
+        assert!(matches("message !~ \"\\d+\""));
+        assert!(matches("ts > 6s and ts < 6.1s"));
+        assert!(matches("ts >= 0:00:06.007773544"));
+    }
+
+    #[test]
+    fn ranges() {
+        assert!(matches("ts in 5s..8s"));
+        assert!(matches("ts in 6007ms.."));
+        assert!(matches("ts in ..7s"));
+        assert!(!matches("ts in 1m..2m"));
+        assert!(!matches("ts in 5s..6s"));
+    }
+
+    #[test]
+    fn combinations() {
+        assert!(matches("level<=WARN and object~\"^v4l2\" and ts in 5s..8s"));
+        assert!(matches("pid == 1 or pid == 8874"));
+        assert!(!matches("not pid == 8874"));
+        assert!(matches("!(pid == 1 && line == 1) || level == ERROR"));
+        assert!(!matches("pid == 1 or pid == 8874 and level == ERROR"));
+        assert!(matches("(pid == 1 or pid == 8874) and not level == ERROR"));
+    }
+
+    #[test]
+    fn missing_object() {
+        let entry = Entry::new("0:00:00.007773544  8874 0x558951015c00 INFO                GST_INIT gst.c:510:init_pre: Init").unwrap();
+        assert!(!Query::new("object ~ .").unwrap().matches(&entry));
+        assert!(Query::new("object != foo").unwrap().matches(&entry));
+        assert!(Query::new("object !~ foo").unwrap().matches(&entry));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Query::new("").unwrap_err(), QueryError::UnexpectedEnd);
+        assert_eq!(Query::new("pid ==").unwrap_err(), QueryError::UnexpectedEnd);
+        assert_eq!(
+            Query::new("foo == 1").unwrap_err(),
+            QueryError::UnknownField {
+                name: "foo".to_string()
+            }
+        );
+        assert_eq!(
+            Query::new("pid == 1 pid").unwrap_err(),
+            QueryError::UnexpectedToken {
+                found: "pid".to_string(),
+                pos: 9
+            }
+        );
+        assert_eq!(
+            Query::new("level < FUZZ").unwrap_err(),
+            QueryError::InvalidValue {
+                field: Field::Level,
+                value: "FUZZ".to_string()
+            }
+        );
+        assert_eq!(
+            Query::new("category < foo").unwrap_err(),
+            QueryError::InvalidOperator {
+                field: Field::Category,
+                op: Op::Lt
+            }
+        );
+        assert_eq!(
+            Query::new("message == \"foo").unwrap_err(),
+            QueryError::UnterminatedString { pos: 11 }
+        );
+        assert!(matches!(
+            Query::new("message ~ \"(\"").unwrap_err(),
+            QueryError::InvalidRegex { .. }
+        ));
+        assert_eq!(
+            Query::new("pid in 1..2").unwrap_err(),
+            QueryError::UnexpectedToken {
+                found: "in".to_string(),
+                pos: 4
+            }
+        );
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("5s"), Some(ClockTime::from_seconds(5)));
+        assert_eq!(
+            parse_duration("1.5ms"),
+            Some(ClockTime::from_useconds(1500))
+        );
+        assert_eq!(parse_duration("2m"), Some(ClockTime::from_seconds(120)));
+        assert_eq!(parse_duration("7"), Some(ClockTime::from_seconds(7)));
+        assert_eq!(parse_duration("3ns"), Some(ClockTime::from_nseconds(3)));
+        assert_eq!(
+            parse_duration("0:00:01.000000005"),
+            Some(ClockTime::from_nseconds(1_000_000_005))
+        );
+        assert_eq!(
+            parse_duration("1.12345678912345678912345678912345678h"),
+            Some(ClockTime::from_nseconds(4_044_444_440_400))
+        );
+        assert_eq!(parse_duration("5x"), None);
+        assert_eq!(parse_duration("."), None);
+        assert_eq!(parse_duration("1.+5s"), None);
+    }
+}
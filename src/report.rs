@@ -0,0 +1,626 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Build self-contained HTML reports with tables and SVG charts, not relying on any external resource.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+const COLORS: [&str; 10] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+    "#bcbd22", "#17becf",
+];
+
+// Points drawn for each series, longer ones are decimated to keep reports small
+const MAX_POINTS: usize = 2000;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; font-size: 0.9em; }
+th { background: #eee; }
+td.num { text-align: right; }
+svg { display: block; margin: 1em 0; }
+svg text { font-family: sans-serif; font-size: 11px; }
+pre { white-space: pre-wrap; font-size: 0.85em; }
+";
+
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn format_value(v: f64, range: f64) -> String {
+    if range >= 100.0 {
+        format!("{:.0}", v)
+    } else if range >= 1.0 {
+        format!("{:.2}", v)
+    } else {
+        format!("{:.4}", v)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&str]) -> Self {
+        Self {
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = String::from("<table>\n<tr>");
+        for header in self.headers.iter() {
+            write!(html, "<th>{}</th>", escape(header)).unwrap();
+        }
+        html.push_str("</tr>\n");
+
+        for row in self.rows.iter() {
+            html.push_str("<tr>");
+            for cell in row.iter() {
+                // right-align numbers so they are easier to compare
+                let class = if !cell.is_empty() && cell.parse::<f64>().is_ok() {
+                    " class=\"num\""
+                } else {
+                    ""
+                };
+                write!(html, "<td{}>{}</td>", class, escape(cell)).unwrap();
+            }
+            html.push_str("</tr>\n");
+        }
+
+        html.push_str("</table>\n");
+        html
+    }
+}
+
+// Horizontal bars, one per label
+#[derive(Debug, Default)]
+pub struct BarChart {
+    title: String,
+    bars: Vec<(String, f64)>,
+}
+
+impl BarChart {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            bars: Vec::new(),
+        }
+    }
+
+    pub fn bar(&mut self, label: &str, value: f64) {
+        self.bars.push((label.to_string(), value));
+    }
+
+    pub fn to_svg(&self) -> String {
+        const BAR: usize = 18;
+        const LABEL_WIDTH: usize = 220;
+        const BARS_WIDTH: usize = 480;
+
+        let width = LABEL_WIDTH + BARS_WIDTH + 80;
+        let height = 30 + self.bars.len() * BAR;
+        let max = self.bars.iter().map(|(_, v)| *v).fold(0.0, f64::max);
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+            width, height
+        );
+        writeln!(
+            svg,
+            "<text x=\"0\" y=\"15\" font-weight=\"bold\">{}</text>",
+            escape(&self.title)
+        )
+        .unwrap();
+
+        for (i, (label, value)) in self.bars.iter().enumerate() {
+            let y = 25 + i * BAR;
+            let w = if max > 0.0 {
+                value / max * BARS_WIDTH as f64
+            } else {
+                0.0
+            };
+            writeln!(
+                svg,
+                "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+                LABEL_WIDTH - 5,
+                y + 12,
+                escape(label)
+            )
+            .unwrap();
+            writeln!(
+                svg,
+                "<rect x=\"{}\" y=\"{}\" width=\"{:.1}\" height=\"{}\" fill=\"{}\"/>",
+                LABEL_WIDTH,
+                y + 2,
+                w,
+                BAR - 4,
+                COLORS[0]
+            )
+            .unwrap();
+            writeln!(
+                svg,
+                "<text x=\"{:.1}\" y=\"{}\">{}</text>",
+                LABEL_WIDTH as f64 + w + 5.0,
+                y + 12,
+                value
+            )
+            .unwrap();
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartStyle {
+    Points,
+    Lines,
+}
+
+// Series of (x, y) values sharing the same axes
+#[derive(Debug)]
+pub struct Chart {
+    title: String,
+    x_label: String,
+    y_label: String,
+    style: ChartStyle,
+    series: Vec<(String, Vec<(f64, f64)>)>,
+}
+
+impl Chart {
+    pub fn new(title: &str, x_label: &str, y_label: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            x_label: x_label.to_string(),
+            y_label: y_label.to_string(),
+            style: ChartStyle::Points,
+            series: Vec::new(),
+        }
+    }
+
+    pub fn style(mut self, style: ChartStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn series(&mut self, name: &str, points: Vec<(f64, f64)>) {
+        self.series.push((name.to_string(), points));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.series.iter().all(|(_, points)| points.is_empty())
+    }
+
+    pub fn to_svg(&self) -> String {
+        const WIDTH: f64 = 800.0;
+        const PLOT_HEIGHT: f64 = 300.0;
+        const LEFT: f64 = 70.0;
+        const RIGHT: f64 = 20.0;
+        const TOP: f64 = 30.0;
+        const LEGEND_LINE: f64 = 16.0;
+
+        let points = self.series.iter().flat_map(|(_, points)| points.iter());
+        let (mut x_min, mut x_max, mut y_min, mut y_max) = (
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        );
+        for (x, y) in points {
+            x_min = x_min.min(*x);
+            x_max = x_max.max(*x);
+            y_min = y_min.min(*y);
+            y_max = y_max.max(*y);
+        }
+        if x_min > x_max {
+            (x_min, x_max, y_min, y_max) = (0.0, 1.0, 0.0, 1.0);
+        }
+        if x_min == x_max {
+            x_max = x_min + 1.0;
+        }
+        if y_min == y_max {
+            y_max = y_min + 1.0;
+        }
+
+        let plot_width = WIDTH - LEFT - RIGHT;
+        let bottom = TOP + PLOT_HEIGHT;
+        let height = bottom + 45.0 + LEGEND_LINE * self.series.len() as f64;
+        let sx = |x: f64| LEFT + (x - x_min) / (x_max - x_min) * plot_width;
+        let sy = |y: f64| bottom - (y - y_min) / (y_max - y_min) * PLOT_HEIGHT;
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+            WIDTH, height
+        );
+        writeln!(
+            svg,
+            "<text x=\"{}\" y=\"15\" font-weight=\"bold\">{}</text>",
+            LEFT,
+            escape(&self.title)
+        )
+        .unwrap();
+
+        // axes and ticks
+        writeln!(
+            svg,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"#888\"/>",
+            LEFT, TOP, plot_width, PLOT_HEIGHT
+        )
+        .unwrap();
+        for i in 0..=5 {
+            let x = x_min + (x_max - x_min) * i as f64 / 5.0;
+            let y = y_min + (y_max - y_min) * i as f64 / 5.0;
+            writeln!(
+                svg,
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+                sx(x),
+                bottom + 14.0,
+                format_value(x, x_max - x_min)
+            )
+            .unwrap();
+            writeln!(
+                svg,
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+                LEFT - 4.0,
+                sy(y) + 4.0,
+                format_value(y, y_max - y_min)
+            )
+            .unwrap();
+            writeln!(
+                svg,
+                "<line x1=\"{}\" y1=\"{:.1}\" x2=\"{}\" y2=\"{:.1}\" stroke=\"#eee\"/>",
+                LEFT,
+                sy(y),
+                LEFT + plot_width,
+                sy(y)
+            )
+            .unwrap();
+        }
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            LEFT + plot_width / 2.0,
+            bottom + 30.0,
+            escape(&self.x_label)
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "<text x=\"12\" y=\"{:.1}\" text-anchor=\"middle\" transform=\"rotate(-90 12 {:.1})\">{}</text>",
+            TOP + PLOT_HEIGHT / 2.0,
+            TOP + PLOT_HEIGHT / 2.0,
+            escape(&self.y_label)
+        )
+        .unwrap();
+
+        for (i, (name, points)) in self.series.iter().enumerate() {
+            let color = COLORS[i % COLORS.len()];
+            let step = points.len().div_ceil(MAX_POINTS).max(1);
+            let points = points.iter().step_by(step);
+
+            writeln!(svg, "<g><title>{}</title>", escape(name)).unwrap();
+            match self.style {
+                ChartStyle::Points => {
+                    for (x, y) in points {
+                        writeln!(
+                            svg,
+                            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"1.5\" fill=\"{}\"/>",
+                            sx(*x),
+                            sy(*y),
+                            color
+                        )
+                        .unwrap();
+                    }
+                }
+                ChartStyle::Lines => {
+                    let coords: Vec<_> = points
+                        .map(|(x, y)| format!("{:.1},{:.1}", sx(*x), sy(*y)))
+                        .collect();
+                    writeln!(
+                        svg,
+                        "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\"/>",
+                        coords.join(" "),
+                        color
+                    )
+                    .unwrap();
+                }
+            }
+            svg.push_str("</g>\n");
+
+            let y = bottom + 45.0 + LEGEND_LINE * i as f64;
+            writeln!(
+                svg,
+                "<rect x=\"{}\" y=\"{:.1}\" width=\"10\" height=\"10\" fill=\"{}\"/>",
+                LEFT,
+                y - 9.0,
+                color
+            )
+            .unwrap();
+            writeln!(
+                svg,
+                "<text x=\"{}\" y=\"{:.1}\">{}</text>",
+                LEFT + 15.0,
+                y,
+                escape(name)
+            )
+            .unwrap();
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+// Directed graph drawn from left to right, each node being placed after all its predecessors
+#[derive(Debug, Default)]
+pub struct Graph {
+    nodes: Vec<String>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl Graph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn node(&mut self, label: &str) -> usize {
+        self.nodes.push(label.to_string());
+        self.nodes.len() - 1
+    }
+
+    pub fn edge(&mut self, from: usize, to: usize) {
+        self.edges.push((from, to));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // Column of each node: the length of the longest path leading to it, ignoring cycles
+    fn layers(&self) -> Vec<usize> {
+        let mut layers = vec![0; self.nodes.len()];
+
+        for _ in 0..self.nodes.len() {
+            let mut changed = false;
+            for (from, to) in self.edges.iter() {
+                if layers[*to] < layers[*from] + 1 && layers[*from] + 1 < self.nodes.len() {
+                    layers[*to] = layers[*from] + 1;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        layers
+    }
+
+    pub fn to_svg(&self) -> String {
+        const NODE_HEIGHT: f64 = 24.0;
+        const ROW: f64 = 40.0;
+        const GAP: f64 = 50.0;
+        const CHAR_WIDTH: f64 = 7.0;
+
+        let layers = self.layers();
+        let n_layers = layers.iter().max().map(|l| l + 1).unwrap_or(0);
+
+        let mut column_width = vec![0.0f64; n_layers];
+        let mut rows: HashMap<usize, usize> = HashMap::new();
+        let mut position = Vec::new();
+        for (i, label) in self.nodes.iter().enumerate() {
+            let layer = layers[i];
+            column_width[layer] = column_width[layer].max(label.len() as f64 * CHAR_WIDTH + 16.0);
+            let row = rows.entry(layer).or_insert(0);
+            position.push(*row);
+            *row += 1;
+        }
+
+        let column_x: Vec<f64> = column_width
+            .iter()
+            .scan(10.0, |x, w| {
+                let current = *x;
+                *x += w + GAP;
+                Some(current)
+            })
+            .collect();
+        let width = column_x.last().unwrap_or(&0.0) + column_width.last().unwrap_or(&0.0) + 10.0;
+        let height = rows.values().max().cloned().unwrap_or(0) as f64 * ROW + 10.0;
+
+        let rect = |i: usize| {
+            let layer = layers[i];
+            let w = self.nodes[i].len() as f64 * CHAR_WIDTH + 16.0;
+            (column_x[layer], 10.0 + position[i] as f64 * ROW, w)
+        };
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\">\n",
+            width, height
+        );
+        svg.push_str(
+            "<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" orient=\"auto\"><path d=\"M0,0 L10,5 L0,10 z\" fill=\"#555\"/></marker></defs>\n",
+        );
+
+        for (from, to) in self.edges.iter() {
+            let (x1, y1, w1) = rect(*from);
+            let (x2, y2, _) = rect(*to);
+            writeln!(
+                svg,
+                "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#555\" marker-end=\"url(#arrow)\"/>",
+                x1 + w1,
+                y1 + NODE_HEIGHT / 2.0,
+                x2,
+                y2 + NODE_HEIGHT / 2.0
+            )
+            .unwrap();
+        }
+
+        for (i, label) in self.nodes.iter().enumerate() {
+            let (x, y, w) = rect(i);
+            writeln!(
+                svg,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{}\" rx=\"4\" fill=\"#e8f0fa\" stroke=\"{}\"/>",
+                x, y, w, NODE_HEIGHT, COLORS[0]
+            )
+            .unwrap();
+            writeln!(
+                svg,
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+                x + w / 2.0,
+                y + 16.0,
+                escape(label)
+            )
+            .unwrap();
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[derive(Debug)]
+pub struct Report {
+    title: String,
+    body: String,
+}
+
+impl Report {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            body: String::new(),
+        }
+    }
+
+    pub fn heading(&mut self, text: &str) {
+        writeln!(self.body, "<h2>{}</h2>", escape(text)).unwrap();
+    }
+
+    pub fn paragraph(&mut self, text: &str) {
+        writeln!(self.body, "<p>{}</p>", escape(text)).unwrap();
+    }
+
+    pub fn preformatted(&mut self, text: &str) {
+        writeln!(self.body, "<pre>{}</pre>", escape(text)).unwrap();
+    }
+
+    // @html is included as is, it has to be escaped already
+    pub fn html(&mut self, html: &str) {
+        self.body.push_str(html);
+    }
+
+    pub fn to_html(&self) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n{}</body>\n</html>\n",
+            escape(&self.title),
+            STYLE,
+            escape(&self.title),
+            self.body
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html() {
+        assert_eq!(
+            escape("<a href=\"x\">&'"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&#39;"
+        );
+
+        let mut table = Table::new(&["name", "count"]);
+        table.row(vec!["<src>".to_string(), "12".to_string()]);
+        assert_eq!(
+            table.to_html(),
+            "<table>\n<tr><th>name</th><th>count</th></tr>\n<tr><td>&lt;src&gt;</td><td class=\"num\">12</td></tr>\n</table>\n"
+        );
+
+        let mut report = Report::new("gst <log>");
+        report.heading("Summary");
+        report.html(&table.to_html());
+        let html = report.to_html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h1>gst &lt;log&gt;</h1>"));
+        assert!(html.contains("<td>&lt;src&gt;</td>"));
+        // no external resource
+        assert!(!html.contains("src=") && !html.contains("href="));
+    }
+
+    #[test]
+    fn charts() {
+        let mut bars = BarChart::new("levels");
+        bars.bar("ERROR", 2.0);
+        bars.bar("DEBUG", 10.0);
+        let svg = bars.to_svg();
+        assert_eq!(svg.matches("<rect").count(), 2);
+        assert!(svg.contains("width=\"480.0\""));
+
+        let mut chart = Chart::new("pts", "time (ms)", "pts (ms)");
+        assert!(chart.is_empty());
+        chart.series("src:src", (0..5000).map(|i| (i as f64, i as f64)).collect());
+        chart.series("sink:sink", vec![(0.0, 1.0)]);
+        assert!(!chart.is_empty());
+        let svg = chart.to_svg();
+        assert_eq!(svg.matches("<circle").count(), 1667 + 1);
+        assert!(svg.contains("<title>src:src</title>"));
+
+        let chart = Chart::new("latency", "time", "latency").style(ChartStyle::Lines);
+        assert!(chart.to_svg().contains("</svg>"));
+    }
+
+    #[test]
+    fn graph() {
+        let mut graph = Graph::new();
+        let src = graph.node("src");
+        let tee = graph.node("tee");
+        let sink1 = graph.node("sink1");
+        let sink2 = graph.node("sink2");
+        graph.edge(src, tee);
+        graph.edge(tee, sink1);
+        graph.edge(tee, sink2);
+        graph.edge(src, sink2);
+        assert_eq!(graph.layers(), vec![0, 1, 2, 2]);
+
+        // cycles do not prevent the graph from being drawn
+        graph.edge(sink1, src);
+        assert!(graph.layers().iter().all(|l| *l < 4));
+
+        let svg = graph.to_svg();
+        assert_eq!(svg.matches("<rect").count(), 4);
+        assert_eq!(svg.matches("<line").count(), 5);
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Overview of the content of a log: what is logged, when and by whom.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+
+use gst::{ClockTime, DebugLevel};
+use gstreamer as gst;
+
+use crate::frequency::CallSite;
+use crate::normalize::message_template;
+use crate::{Entry, ParserIterator, ParsingError};
+
+#[derive(Debug, PartialEq)]
+pub struct ThreadSummary {
+    pub count: u64,
+    pub first: ClockTime,
+    pub last: ClockTime,
+}
+
+// Errors and warnings logged by the same statement with the same message template
+#[derive(Debug)]
+pub struct Problem {
+    // first occurrence
+    pub entry: Entry,
+    pub count: u64,
+    pub last: ClockTime,
+}
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub entries: u64,
+    pub first: Option<ClockTime>,
+    pub last: Option<ClockTime>,
+    // number of entries per PID
+    pub pids: BTreeMap<u32, u64>,
+    pub threads: BTreeMap<String, ThreadSummary>,
+    pub levels: BTreeMap<DebugLevel, u64>,
+    pub categories: BTreeMap<String, u64>,
+    // ERROR and WARN entries, in order of first appearance
+    pub problems: Vec<Problem>,
+    problems_idx: HashMap<(DebugLevel, CallSite, String), usize>,
+    // number of lines which could not be parsed, per ParsingError variant
+    pub errors: BTreeMap<&'static str, u64>,
+}
+
+impl Summary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_entry(&mut self, entry: Entry) {
+        self.entries += 1;
+        self.first = Some(self.first.map_or(entry.ts, |first| first.min(entry.ts)));
+        self.last = Some(self.last.map_or(entry.ts, |last| last.max(entry.ts)));
+
+        *self.pids.entry(entry.pid).or_insert(0) += 1;
+        *self.levels.entry(entry.level).or_insert(0) += 1;
+        *self.categories.entry(entry.category.clone()).or_insert(0) += 1;
+
+        let thread = self
+            .threads
+            .entry(entry.thread.clone())
+            .or_insert(ThreadSummary {
+                count: 0,
+                first: entry.ts,
+                last: entry.ts,
+            });
+        thread.count += 1;
+        thread.first = thread.first.min(entry.ts);
+        thread.last = thread.last.max(entry.ts);
+
+        if entry.level == DebugLevel::Error || entry.level == DebugLevel::Warning {
+            let key = (
+                entry.level,
+                CallSite {
+                    category: entry.category.clone(),
+                    file: entry.file.clone(),
+                    line: entry.line,
+                    function: entry.function.clone(),
+                },
+                message_template(&entry.message),
+            );
+
+            match self.problems_idx.get(&key) {
+                Some(idx) => {
+                    let problem = &mut self.problems[*idx];
+                    problem.count += 1;
+                    problem.last = problem.last.max(entry.ts);
+                }
+                None => {
+                    self.problems_idx.insert(key, self.problems.len());
+                    self.problems.push(Problem {
+                        last: entry.ts,
+                        entry,
+                        count: 1,
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn add_error(&mut self, error: &ParsingError) {
+        *self.errors.entry(error.kind()).or_insert(0) += 1;
+    }
+
+    pub fn add(&mut self, result: Result<Entry, ParsingError>) {
+        match result {
+            Ok(entry) => self.add_entry(entry),
+            Err(err) => self.add_error(&err),
+        }
+    }
+
+    pub fn duration(&self) -> ClockTime {
+        match (self.first, self.last) {
+            (Some(first), Some(last)) => last.checked_sub(first).unwrap_or(ClockTime::ZERO),
+            _ => ClockTime::ZERO,
+        }
+    }
+
+    // Number of lines which could not be parsed
+    pub fn failures(&self) -> u64 {
+        self.errors.values().sum()
+    }
+}
+
+// Summarize all the lines of @parser
+pub fn summarize<R: Read>(parser: &mut ParserIterator<R>) -> Summary {
+    let mut summary = Summary::new();
+    for result in parser.results() {
+        summary.add(result);
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use std::fs::File;
+
+    #[test]
+    fn summary() {
+        let f = File::open("test-logs/corrupted-nocolor.log").unwrap();
+        let mut summary = summarize(&mut parse(f));
+
+        assert_eq!(summary.entries, 6);
+        assert_eq!(summary.first, Some(ClockTime::from_nseconds(7_773_544)));
+        assert_eq!(
+            summary.duration(),
+            ClockTime::from_nseconds(5_447_000_304_659)
+        );
+        assert_eq!(summary.pids.get(&8874), Some(&6));
+        assert_eq!(
+            summary.threads.get("0x558951015c00"),
+            Some(&ThreadSummary {
+                count: 6,
+                first: ClockTime::from_nseconds(7_773_544),
+                last: ClockTime::from_nseconds(5_447_008_078_203),
+            })
+        );
+        assert_eq!(summary.levels.get(&DebugLevel::Debug), Some(&3));
+        assert_eq!(summary.levels.get(&DebugLevel::Trace), Some(&2));
+        assert_eq!(summary.categories.get("GST_MEMORY"), Some(&3));
+        assert_eq!(summary.errors.get("InvalidTimestamp"), Some(&1));
+        assert_eq!(summary.failures(), 1);
+        assert!(summary.problems.is_empty());
+
+        // warnings are grouped by statement and message template
+        for (ts, msg) in [("0:00:02.0", 1), ("0:00:03.0", 2)].iter() {
+            summary.add_entry(Entry::new(&format!("{}  8874 0x558951015c00 WARN         omxvideodec gstomxvideodec.c:1790:gst_omx_video_dec_loop:<omxh264dec-omxh264dec0> error {}", ts, msg)).unwrap());
+        }
+        summary.add(Entry::new("0:00:04.0  8874 0x558951015c00 ERROR         omxvideodec gstomxvideodec.c:1790:gst_omx_video_dec_loop:<omxh264dec-omxh264dec0> error 3"));
+        assert_eq!(summary.problems.len(), 2);
+        assert_eq!(summary.problems[0].count, 2);
+        assert_eq!(summary.problems[0].entry.message, "error 1");
+        assert_eq!(summary.problems[0].last, ClockTime::from_seconds(3));
+        assert_eq!(summary.problems[1].entry.level, DebugLevel::Error);
+        assert_eq!(summary.entries, 9);
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Helpers building tracer records for the unit tests.
+
+use gst::{BufferFlags, ClockTime};
+use gstreamer as gst;
+
+use crate::tracer::{BufferRecord, EventRecord, Record};
+
+pub(crate) struct TestBuffer(BufferRecord);
+
+// Empty buffer pushed at @ts by the pad @pad_ix of the element @element_ix to an unlinked pad
+pub(crate) fn buffer(ts: ClockTime, pad_ix: u32, element_ix: u32) -> TestBuffer {
+    TestBuffer(BufferRecord {
+        ts,
+        pad_ix,
+        element_ix,
+        peer_pad_ix: u32::MAX,
+        peer_element_ix: u32::MAX,
+        size: 0,
+        pts: None,
+        dts: None,
+        duration: None,
+        flags: BufferFlags::empty(),
+    })
+}
+
+impl TestBuffer {
+    pub(crate) fn peer(mut self, pad_ix: u32, element_ix: u32) -> Self {
+        self.0.peer_pad_ix = pad_ix;
+        self.0.peer_element_ix = element_ix;
+        self
+    }
+
+    pub(crate) fn size(mut self, size: u32) -> Self {
+        self.0.size = size;
+        self
+    }
+
+    pub(crate) fn pts(mut self, pts: impl Into<Option<ClockTime>>) -> Self {
+        self.0.pts = pts.into();
+        self
+    }
+
+    pub(crate) fn dts(mut self, dts: impl Into<Option<ClockTime>>) -> Self {
+        self.0.dts = dts.into();
+        self
+    }
+
+    pub(crate) fn duration(mut self, duration: impl Into<Option<ClockTime>>) -> Self {
+        self.0.duration = duration.into();
+        self
+    }
+
+    pub(crate) fn flags(mut self, flags: BufferFlags) -> Self {
+        self.0.flags = flags;
+        self
+    }
+
+    pub(crate) fn record(self) -> Record {
+        Record::Buffer(self.0)
+    }
+}
+
+// Event pushed at @ts by the pad @pad_ix of the element @element_ix
+pub(crate) fn event(ts: ClockTime, pad_ix: u32, element_ix: u32, name: &str) -> Record {
+    Record::Event(EventRecord {
+        ts,
+        pad_ix,
+        element_ix,
+        name: name.to_string(),
+    })
+}
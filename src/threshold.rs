@@ -0,0 +1,132 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Filter entries using the GST_DEBUG syntax, such as `3,omx*:6,GST_PADS:LOG`.
+
+use std::str::FromStr;
+
+use gst::DebugLevel;
+use gstreamer as gst;
+use regex::Regex;
+use thiserror::Error;
+
+use crate::query::parse_level;
+use crate::Entry;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ThresholdError {
+    #[error("invalid debug level: {}", level)]
+    InvalidLevel { level: String },
+    #[error("invalid category pattern: {}", pattern)]
+    InvalidPattern { pattern: String },
+}
+
+// Categories patterns may use '*' and '?' wildcards, as g_pattern_match_simple()
+fn pattern_to_regex(pattern: &str) -> Result<Regex, ThresholdError> {
+    let re = regex::escape(pattern)
+        .replace(r"\*", ".*")
+        .replace(r"\?", ".");
+
+    Regex::new(&format!("^{}$", re)).map_err(|_| ThresholdError::InvalidPattern {
+        pattern: pattern.to_string(),
+    })
+}
+
+#[derive(Debug)]
+pub struct Thresholds {
+    default: DebugLevel,
+    categories: Vec<(Regex, DebugLevel)>,
+}
+
+impl Thresholds {
+    // As in GStreamer, nothing is displayed unless a default level or categories are set
+    // and the last pattern matching a category defines its threshold.
+    pub fn new(spec: &str) -> Result<Self, ThresholdError> {
+        let mut thresholds = Self {
+            default: DebugLevel::None,
+            categories: Vec::new(),
+        };
+
+        for part in spec.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            let (pattern, level) = match part.rfind(':') {
+                Some(idx) => (Some(&part[..idx]), &part[idx + 1..]),
+                None => (None, part),
+            };
+            let level = parse_level(level.trim()).ok_or_else(|| ThresholdError::InvalidLevel {
+                level: level.to_string(),
+            })?;
+
+            match pattern {
+                Some(pattern) => thresholds
+                    .categories
+                    .push((pattern_to_regex(pattern.trim())?, level)),
+                None => thresholds.default = level,
+            }
+        }
+
+        Ok(thresholds)
+    }
+
+    pub fn level(&self, category: &str) -> DebugLevel {
+        self.categories
+            .iter()
+            .rev()
+            .find(|(re, _)| re.is_match(category))
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    pub fn matches(&self, entry: &Entry) -> bool {
+        entry.level <= self.level(&entry.category)
+    }
+}
+
+impl FromStr for Thresholds {
+    type Err = ThresholdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use std::fs::File;
+
+    #[test]
+    fn thresholds() {
+        let t = Thresholds::new("2,GST_*:5,GST_REFCOUNTING:0,omx?:LOG").unwrap();
+        assert_eq!(t.level("GST_MEMORY"), DebugLevel::Debug);
+        assert_eq!(t.level("GST_REFCOUNTING"), DebugLevel::None);
+        assert_eq!(t.level("omxh"), DebugLevel::Log);
+        assert_eq!(t.level("omxvideodec"), DebugLevel::Warning);
+
+        let entries: Vec<_> = parse(File::open("test-logs/nocolor.log").unwrap())
+            .filter(|e| t.matches(e))
+            .map(|e| e.category)
+            .collect();
+        assert_eq!(
+            entries,
+            vec!["GST_INIT", "GST_MEMORY", "GST_MEMORY", "GST_MEMORY"]
+        );
+
+        let t = Thresholds::new("").unwrap();
+        assert_eq!(t.level("GST_INIT"), DebugLevel::None);
+        let t: Thresholds = "*:INFO".parse().unwrap();
+        assert_eq!(t.level("GST_INIT"), DebugLevel::Info);
+
+        assert_eq!(
+            Thresholds::new("GST_*:8").unwrap_err(),
+            ThresholdError::InvalidLevel {
+                level: "8".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,598 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Records logged by the 'stats' and 'latency' tracers.
+//!
+//! Generate logs with: GST_DEBUG="GST_TRACER:7" GST_TRACERS="stats;latency(flags=pipeline+element+reported)"
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use gst::glib::value::FromValue;
+use gst::{BufferFlags, ClockTime, DebugLevel, PadDirection, StructureRef};
+use gstreamer as gst;
+use thiserror::Error;
+
+use crate::Entry;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TracerError {
+    #[error("missing or invalid field '{}' in {} record", field, record)]
+    InvalidField { record: String, field: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferRecord {
+    pub ts: ClockTime,
+    // pad pushing the buffer and the element owning it
+    pub pad_ix: u32,
+    pub element_ix: u32,
+    // pad receiving the buffer and the element owning it
+    pub peer_pad_ix: u32,
+    pub peer_element_ix: u32,
+    pub size: u32,
+    pub pts: Option<ClockTime>,
+    pub dts: Option<ClockTime>,
+    pub duration: Option<ClockTime>,
+    pub flags: BufferFlags,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventRecord {
+    pub ts: ClockTime,
+    pub pad_ix: u32,
+    pub element_ix: u32,
+    // name of the event type, such as 'flush-stop' or 'segment'
+    pub name: String,
+}
+
+// Time spent by a buffer in an element, reported by the latency tracer with the 'element' flag
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementLatencyRecord {
+    pub ts: Option<ClockTime>,
+    // not logged by GStreamer before 1.16
+    pub element: Option<String>,
+    pub src: String,
+    pub time: ClockTime,
+}
+
+impl ElementLatencyRecord {
+    // The element if logged, or its source pad
+    pub fn name(&self) -> &str {
+        self.element.as_ref().unwrap_or(&self.src)
+    }
+}
+
+// Time spent by a buffer between a source and a sink, reported with the 'pipeline' flag
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyRecord {
+    pub ts: Option<ClockTime>,
+    pub src: String,
+    pub sink: String,
+    pub time: ClockTime,
+}
+
+impl LatencyRecord {
+    // "src -> sink"
+    pub fn path(&self) -> String {
+        format!("{} -> {}", self.src, self.sink)
+    }
+}
+
+// Latency reported by an element in the latency query, reported with the 'reported' flag
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportedLatencyRecord {
+    pub ts: Option<ClockTime>,
+    pub element: String,
+    pub live: bool,
+    pub min: ClockTime,
+    pub max: Option<ClockTime>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Record {
+    NewElement {
+        ix: u32,
+        parent_ix: Option<u32>,
+        name: String,
+        type_name: String,
+        is_bin: bool,
+    },
+    NewPad {
+        ix: u32,
+        parent_ix: Option<u32>,
+        name: String,
+        direction: PadDirection,
+    },
+    Buffer(BufferRecord),
+    Event(EventRecord),
+    ElementLatency(ElementLatencyRecord),
+    Latency(LatencyRecord),
+    ReportedLatency(ReportedLatencyRecord),
+}
+
+fn field<'a, T: FromValue<'a>>(s: &'a StructureRef, name: &str) -> Result<T, TracerError> {
+    s.get::<T>(name).map_err(|_| TracerError::InvalidField {
+        record: s.name().to_string(),
+        field: name.to_string(),
+    })
+}
+
+fn optional<'a, T: FromValue<'a>>(s: &'a StructureRef, name: &str) -> Option<T> {
+    s.get::<T>(name).ok()
+}
+
+// GST_CLOCK_TIME_NONE is logged as G_MAXUINT64
+fn clock_time(s: &StructureRef, name: &str) -> Result<Option<ClockTime>, TracerError> {
+    field::<u64>(s, name).map(|t| {
+        if t == u64::MAX {
+            None
+        } else {
+            Some(ClockTime::from_nseconds(t))
+        }
+    })
+}
+
+fn time(s: &StructureRef, name: &str) -> Result<ClockTime, TracerError> {
+    clock_time(s, name)?.ok_or_else(|| TracerError::InvalidField {
+        record: s.name().to_string(),
+        field: name.to_string(),
+    })
+}
+
+// The 'ix' fields referring to no object are set to G_MAXUINT
+fn index(ix: u32) -> Option<u32> {
+    if ix == u32::MAX {
+        None
+    } else {
+        Some(ix)
+    }
+}
+
+impl Record {
+    // Name of the element or path and the latency measured by latency records
+    pub fn latency(&self) -> Option<(String, ClockTime)> {
+        match self {
+            Record::ElementLatency(latency) => Some((latency.name().to_string(), latency.time)),
+            Record::Latency(latency) => Some((latency.path(), latency.time)),
+            _ => None,
+        }
+    }
+
+    // Return None if @s is not a known record
+    pub fn from_structure(s: &StructureRef) -> Result<Option<Self>, TracerError> {
+        let record = match s.name().as_str() {
+            "new-element" => Record::NewElement {
+                ix: field(s, "ix")?,
+                parent_ix: optional::<u32>(s, "parent-ix").and_then(index),
+                name: field::<String>(s, "name")?,
+                type_name: optional::<String>(s, "type").unwrap_or_default(),
+                is_bin: optional(s, "is-bin").unwrap_or(false),
+            },
+            "new-pad" => Record::NewPad {
+                ix: field(s, "ix")?,
+                parent_ix: optional::<u32>(s, "parent-ix").and_then(index),
+                name: field::<String>(s, "name")?,
+                direction: optional(s, "pad-direction").unwrap_or(PadDirection::Unknown),
+            },
+            "buffer" => {
+                let opt_time = |flag: &str, name: &str| -> Result<Option<ClockTime>, TracerError> {
+                    if optional(s, flag).unwrap_or(false) {
+                        clock_time(s, name)
+                    } else {
+                        Ok(None)
+                    }
+                };
+
+                Record::Buffer(BufferRecord {
+                    ts: time(s, "ts")?,
+                    pad_ix: field(s, "pad-ix")?,
+                    element_ix: field(s, "element-ix")?,
+                    peer_pad_ix: field(s, "peer-pad-ix")?,
+                    peer_element_ix: field(s, "peer-element-ix")?,
+                    size: optional(s, "buffer-size").unwrap_or(0),
+                    pts: opt_time("have-buffer-pts", "buffer-pts")?,
+                    dts: opt_time("have-buffer-dts", "buffer-dts")?,
+                    duration: opt_time("have-buffer-duration", "buffer-duration")?,
+                    flags: optional(s, "buffer-flags").unwrap_or_else(BufferFlags::empty),
+                })
+            }
+            "event" => Record::Event(EventRecord {
+                ts: time(s, "ts")?,
+                pad_ix: field(s, "pad-ix")?,
+                element_ix: field(s, "element-ix")?,
+                name: field::<String>(s, "name")?,
+            }),
+            "element-latency" => Record::ElementLatency(ElementLatencyRecord {
+                ts: time(s, "ts").ok(),
+                element: optional::<String>(s, "element"),
+                src: field::<String>(s, "src")?,
+                time: time(s, "time")?,
+            }),
+            "latency" => Record::Latency(LatencyRecord {
+                ts: time(s, "ts").ok(),
+                src: field::<String>(s, "src")?,
+                sink: field::<String>(s, "sink")?,
+                time: time(s, "time")?,
+            }),
+            "element-reported-latency" => Record::ReportedLatency(ReportedLatencyRecord {
+                ts: time(s, "ts").ok(),
+                element: field::<String>(s, "element")?,
+                live: optional(s, "live").unwrap_or(false),
+                min: time(s, "min")?,
+                max: clock_time(s, "max").ok().flatten(),
+            }),
+            _ => return Ok(None),
+        };
+
+        Ok(Some(record))
+    }
+
+    pub fn from_entry(entry: &Entry) -> Result<Option<Self>, TracerError> {
+        if !is_tracer_entry(entry) {
+            return Ok(None);
+        }
+
+        match entry.message_to_struct() {
+            Some(s) => Self::from_structure(&s),
+            None => Ok(None),
+        }
+    }
+}
+
+pub fn is_tracer_entry(entry: &Entry) -> bool {
+    entry.category == "GST_TRACER" && entry.level == DebugLevel::Trace
+}
+
+// Records logged in @entries, invalid ones are ignored
+pub fn records<I: Iterator<Item = Entry>>(entries: I) -> impl Iterator<Item = Record> {
+    entries.filter_map(|entry| Record::from_entry(&entry).ok().flatten())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementInfo {
+    pub ix: u32,
+    pub parent_ix: Option<u32>,
+    pub name: String,
+    pub type_name: String,
+    pub is_bin: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PadInfo {
+    pub ix: u32,
+    // element owning the pad
+    pub parent_ix: Option<u32>,
+    pub name: String,
+    pub direction: PadDirection,
+}
+
+// Elements and pads of the pipeline, as well as the links between pads which have been used to push buffers
+#[derive(Debug, Default)]
+pub struct Topology {
+    elements: BTreeMap<u32, ElementInfo>,
+    pads: BTreeMap<u32, PadInfo>,
+    // (src pad, sink pad)
+    links: BTreeSet<(u32, u32)>,
+}
+
+impl Topology {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, record: &Record) {
+        match record {
+            Record::NewElement {
+                ix,
+                parent_ix,
+                name,
+                type_name,
+                is_bin,
+            } => {
+                self.elements.entry(*ix).or_insert_with(|| ElementInfo {
+                    ix: *ix,
+                    parent_ix: *parent_ix,
+                    name: name.clone(),
+                    type_name: type_name.clone(),
+                    is_bin: *is_bin,
+                });
+            }
+            Record::NewPad {
+                ix,
+                parent_ix,
+                name,
+                direction,
+            } => {
+                self.pads.entry(*ix).or_insert_with(|| PadInfo {
+                    ix: *ix,
+                    parent_ix: *parent_ix,
+                    name: name.clone(),
+                    direction: *direction,
+                });
+            }
+            Record::Buffer(buffer) => {
+                // pads created before the tracer was enabled are not announced
+                for (pad, element, direction) in [
+                    (buffer.pad_ix, buffer.element_ix, PadDirection::Src),
+                    (
+                        buffer.peer_pad_ix,
+                        buffer.peer_element_ix,
+                        PadDirection::Sink,
+                    ),
+                ] {
+                    if index(pad).is_none() {
+                        continue;
+                    }
+                    let pad = self.pads.entry(pad).or_insert_with(|| PadInfo {
+                        ix: pad,
+                        parent_ix: None,
+                        name: format!("pad{}", pad),
+                        direction,
+                    });
+                    if pad.parent_ix.is_none() {
+                        pad.parent_ix = index(element);
+                    }
+                }
+
+                if index(buffer.peer_pad_ix).is_some() {
+                    self.links.insert((buffer.pad_ix, buffer.peer_pad_ix));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn element(&self, ix: u32) -> Option<&ElementInfo> {
+        self.elements.get(&ix)
+    }
+
+    pub fn pad(&self, ix: u32) -> Option<&PadInfo> {
+        self.pads.get(&ix)
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = &ElementInfo> {
+        self.elements.values()
+    }
+
+    pub fn pads(&self) -> impl Iterator<Item = &PadInfo> {
+        self.pads.values()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty() && self.pads.is_empty()
+    }
+
+    // Links between pads, from the src pad to the sink pad
+    pub fn links(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.links.iter().cloned()
+    }
+
+    pub fn element_name(&self, ix: u32) -> String {
+        match self.elements.get(&ix) {
+            Some(element) => element.name.clone(),
+            None => format!("element{}", ix),
+        }
+    }
+
+    // 'element:pad', as used by GStreamer to display pads
+    pub fn pad_name(&self, ix: u32) -> String {
+        match self.pads.get(&ix) {
+            Some(pad) => match pad.parent_ix {
+                Some(parent) => format!("{}:{}", self.element_name(parent), pad.name),
+                None => pad.name.clone(),
+            },
+            None => format!("pad{}", ix),
+        }
+    }
+
+    // Links between elements, deduced from the links between their pads
+    pub fn element_links(&self) -> BTreeSet<(u32, u32)> {
+        self.links
+            .iter()
+            .filter_map(|(src, sink)| {
+                let src = self.pads.get(src)?.parent_ix?;
+                let sink = self.pads.get(sink)?.parent_ix?;
+                Some((src, sink))
+            })
+            .filter(|(src, sink)| src != sink)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(message: &str) -> Result<Option<Record>, TracerError> {
+        gst::init().unwrap();
+        let entry = Entry::new(&format!(
+            "0:00:00.041839483 31047 0x55f3b00ffd90 TRACE             GST_TRACER :0:: {}",
+            message
+        ))
+        .unwrap();
+        Record::from_entry(&entry)
+    }
+
+    #[test]
+    fn buffer() {
+        let record = parse("buffer, thread-id=(guint64)94524373917072, ts=(guint64)41839483, pad-ix=(uint)7, element-ix=(uint)6, peer-pad-ix=(uint)8, peer-element-ix=(uint)1, buffer-size=(uint)4096, have-buffer-pts=(boolean)false, buffer-pts=(guint64)18446744073709551615, have-buffer-dts=(boolean)true, buffer-dts=(guint64)33333333, have-buffer-duration=(boolean)true, buffer-duration=(guint64)33333333, buffer-flags=(GstBufferFlags)discont+delta-unit, buffer-offset=(guint64)1, buffer-offset-end=(guint64)2;");
+        assert_eq!(
+            record,
+            Ok(Some(Record::Buffer(BufferRecord {
+                ts: ClockTime::from_nseconds(41_839_483),
+                pad_ix: 7,
+                element_ix: 6,
+                peer_pad_ix: 8,
+                peer_element_ix: 1,
+                size: 4096,
+                pts: None,
+                dts: Some(ClockTime::from_nseconds(33_333_333)),
+                duration: Some(ClockTime::from_nseconds(33_333_333)),
+                flags: BufferFlags::DISCONT | BufferFlags::DELTA_UNIT,
+            })))
+        );
+    }
+
+    #[test]
+    fn event() {
+        let record = parse("event, thread-id=(guint64)94524373917072, ts=(guint64)40537549, pad-ix=(uint)3, element-ix=(uint)2, name=(string)stream-start;");
+        assert_eq!(
+            record,
+            Ok(Some(Record::Event(EventRecord {
+                ts: ClockTime::from_nseconds(40_537_549),
+                pad_ix: 3,
+                element_ix: 2,
+                name: "stream-start".to_string(),
+            })))
+        );
+    }
+
+    #[test]
+    fn objects() {
+        let record = parse("new-element, thread-id=(guint64)94524373917072, ts=(guint64)39645218, ix=(uint)1, parent-ix=(uint)0, name=(string)videotestsrc0, type=(string)GstVideoTestSrc, is-bin=(boolean)false;");
+        assert_eq!(
+            record,
+            Ok(Some(Record::NewElement {
+                ix: 1,
+                parent_ix: Some(0),
+                name: "videotestsrc0".to_string(),
+                type_name: "GstVideoTestSrc".to_string(),
+                is_bin: false,
+            }))
+        );
+
+        let record = parse("new-pad, thread-id=(guint64)94524373917072, ts=(guint64)39661473, ix=(uint)5, parent-ix=(uint)4294967295, name=(string)proxypad0, type=(string)GstProxyPad, is-real=(boolean)false, pad-direction=(GstPadDirection)GST_PAD_SRC;");
+        assert_eq!(
+            record,
+            Ok(Some(Record::NewPad {
+                ix: 5,
+                parent_ix: None,
+                name: "proxypad0".to_string(),
+                direction: PadDirection::Src,
+            }))
+        );
+    }
+
+    #[test]
+    fn latency() {
+        let record = parse("element-latency, element-id=(string)0x55f3b0152140, element=(string)videoconvert0, src=(string)src, time=(guint64)1234567, ts=(guint64)135485046;");
+        assert_eq!(
+            record,
+            Ok(Some(Record::ElementLatency(ElementLatencyRecord {
+                ts: Some(ClockTime::from_nseconds(135_485_046)),
+                element: Some("videoconvert0".to_string()),
+                src: "src".to_string(),
+                time: ClockTime::from_nseconds(1_234_567),
+            })))
+        );
+
+        // GStreamer < 1.16 did not log the element
+        let record = parse("element-latency, src=(string)videoconvert0_src, time=(guint64)1234567, ts=(guint64)135485046;").unwrap().unwrap();
+        assert_eq!(
+            record.latency(),
+            Some((
+                "videoconvert0_src".to_string(),
+                ClockTime::from_nseconds(1_234_567)
+            ))
+        );
+
+        let record = parse("latency, src=(string)videotestsrc0_src, sink=(string)fakesink0_sink, time=(guint64)2431516, ts=(guint64)139345785;");
+        assert_eq!(
+            record,
+            Ok(Some(Record::Latency(LatencyRecord {
+                ts: Some(ClockTime::from_nseconds(139_345_785)),
+                src: "videotestsrc0_src".to_string(),
+                sink: "fakesink0_sink".to_string(),
+                time: ClockTime::from_nseconds(2_431_516),
+            })))
+        );
+
+        let record = parse("element-reported-latency, element-id=(string)0x55f3b0158270, element=(string)queue0, live=(boolean)true, min=(guint64)0, max=(guint64)18446744073709551615, ts=(guint64)140053420;");
+        assert_eq!(
+            record,
+            Ok(Some(Record::ReportedLatency(ReportedLatencyRecord {
+                ts: Some(ClockTime::from_nseconds(140_053_420)),
+                element: "queue0".to_string(),
+                live: true,
+                min: ClockTime::ZERO,
+                max: None,
+            })))
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            parse("event, thread-id=(guint64)94524373917072, ts=(guint64)40537549, element-ix=(uint)2, name=(string)stream-start;"),
+            Err(TracerError::InvalidField {
+                record: "event".to_string(),
+                field: "pad-ix".to_string()
+            })
+        );
+        // unknown records are ignored
+        assert_eq!(
+            parse("thread-rusage, thread-id=(guint64)94524373917072, ts=(guint64)40537549;"),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn topology() {
+        let mut topology = Topology::new();
+        topology.add(&Record::NewElement {
+            ix: 0,
+            parent_ix: None,
+            name: "src".to_string(),
+            type_name: "GstVideoTestSrc".to_string(),
+            is_bin: false,
+        });
+        topology.add(&Record::NewElement {
+            ix: 1,
+            parent_ix: None,
+            name: "sink".to_string(),
+            type_name: "GstFakeSink".to_string(),
+            is_bin: false,
+        });
+        topology.add(&Record::NewPad {
+            ix: 2,
+            parent_ix: Some(0),
+            name: "src".to_string(),
+            direction: PadDirection::Src,
+        });
+        let buffer = |pad_ix, element_ix, peer_pad_ix, peer_element_ix| {
+            Record::Buffer(BufferRecord {
+                ts: ClockTime::ZERO,
+                pad_ix,
+                element_ix,
+                peer_pad_ix,
+                peer_element_ix,
+                size: 0,
+                pts: None,
+                dts: None,
+                duration: None,
+                flags: BufferFlags::empty(),
+            })
+        };
+        topology.add(&buffer(2, 0, 3, 1));
+        topology.add(&buffer(2, 0, 3, 1));
+        // buffer pushed to an unlinked pad
+        topology.add(&buffer(4, 1, u32::MAX, u32::MAX));
+
+        assert_eq!(topology.pad_name(2), "src:src");
+        assert_eq!(topology.pad_name(3), "sink:pad3");
+        assert_eq!(topology.pad(3).unwrap().direction, PadDirection::Sink);
+        assert_eq!(topology.pad_name(10), "pad10");
+        assert_eq!(topology.links().collect::<Vec<_>>(), vec![(2, 3)]);
+        assert_eq!(
+            topology.element_links().into_iter().collect::<Vec<_>>(),
+            vec![(0, 1)]
+        );
+        assert_eq!(topology.pads().count(), 3);
+        assert!(!topology.is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright (C) 2017-2019 Guillaume Desmottes <guillaume@desmottes.be>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Write entries using the same layout as the GStreamer default log handler.
+
+use std::io::{self, Write};
+
+use gst::DebugLevel;
+use gstreamer as gst;
+
+use crate::Entry;
+
+const CLEAR: &str = "\x1b[00m";
+
+// Names are padded the same way gst_debug_level_get_name() does
+fn level_name(level: DebugLevel) -> &'static str {
+    match level {
+        DebugLevel::Error => "ERROR  ",
+        DebugLevel::Warning => "WARN   ",
+        DebugLevel::Fixme => "FIXME  ",
+        DebugLevel::Info => "INFO   ",
+        DebugLevel::Debug => "DEBUG  ",
+        DebugLevel::Log => "LOG    ",
+        DebugLevel::Trace => "TRACE  ",
+        DebugLevel::Memdump => "MEMDUMP",
+        _ => "",
+    }
+}
+
+// Colors used by GStreamer, which are shifted compared to the levels
+fn level_color(level: DebugLevel) -> &'static str {
+    match level {
+        DebugLevel::Error => "\x1b[31;01m",
+        DebugLevel::Warning => "\x1b[33;01m",
+        DebugLevel::Fixme => "\x1b[32;01m",
+        DebugLevel::Info => "\x1b[36m",
+        DebugLevel::Log => "\x1b[33;01m",
+        _ => "\x1b[37m",
+    }
+}
+
+pub fn format_entry(entry: &Entry, color: bool) -> String {
+    let object = entry
+        .object
+        .as_ref()
+        .map(|o| format!("<{}>", o))
+        .unwrap_or_default();
+
+    if color {
+        // The color of categories is not logged so they are all written with the default one
+        format!(
+            "{} \x1b[3{}m{:>5}{} {:>14} {}{}{} {}{:>20} {}:{}:{}:{}{} {}",
+            entry.ts,
+            entry.pid % 6 + 31,
+            entry.pid,
+            CLEAR,
+            entry.thread,
+            level_color(entry.level),
+            level_name(entry.level),
+            CLEAR,
+            CLEAR,
+            entry.category,
+            entry.file,
+            entry.line,
+            entry.function,
+            object,
+            CLEAR,
+            entry.message
+        )
+    } else {
+        format!(
+            "{} {:>5} {:>14} {} {:>20} {}:{}:{}:{} {}",
+            entry.ts,
+            entry.pid,
+            entry.thread,
+            level_name(entry.level),
+            entry.category,
+            entry.file,
+            entry.line,
+            entry.function,
+            object,
+            entry.message
+        )
+    }
+}
+
+pub struct EntryWriter<W: Write> {
+    writer: W,
+    color: bool,
+}
+
+impl<W: Write> EntryWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            color: false,
+        }
+    }
+
+    // Use the same colors as GST_DEBUG_COLOR_MODE=on
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn write(&mut self, entry: &Entry) -> io::Result<()> {
+        writeln!(self.writer, "{}", format_entry(entry, self.color))
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, strip_colors};
+    use std::fs;
+
+    #[test]
+    fn round_trip() {
+        for log in fs::read_dir("test-logs").unwrap() {
+            let path = log.unwrap().path();
+            let content = fs::read_to_string(&path).unwrap();
+            let mut n = 0;
+
+            for line in content.lines() {
+                let entry = match Entry::new(line) {
+                    Ok(entry) => entry,
+                    Err(_) => continue,
+                };
+                n += 1;
+
+                assert_eq!(format_entry(&entry, false), strip_colors(line));
+                assert_eq!(
+                    strip_colors(&format_entry(&entry, true)),
+                    format_entry(&entry, false)
+                );
+            }
+            assert!(n > 0, "no entry in {}", path.display());
+
+            // Write all entries and parse them back
+            let entries: Vec<_> = parse(fs::File::open(&path).unwrap()).collect();
+            let mut writer = EntryWriter::new(Vec::new()).color(true);
+            for entry in entries.iter() {
+                writer.write(entry).unwrap();
+            }
+            let output = writer.into_inner();
+            let parsed: Vec<_> = parse(output.as_slice()).collect();
+
+            assert_eq!(entries.len(), parsed.len());
+            for (a, b) in entries.iter().zip(parsed.iter()) {
+                assert_eq!(format_entry(a, false), format_entry(b, false));
+            }
+        }
+    }
+
+    #[test]
+    fn color() {
+        // Category using the default color
+        let line = "0:00:00.223498220 \x1b[333m 4394\x1b[00m   0x7f8c00bd90 \x1b[33;01mWARN   \x1b[00m \x1b[00m         omxvideodec gstomxvideodec.c:1790:gst_omx_video_dec_loop:<omxh264dec-omxh264dec0>\x1b[00m error: Unable to reconfigure output port";
+        let entry = Entry::new(line).unwrap();
+        assert_eq!(format_entry(&entry, true), line);
+    }
+}